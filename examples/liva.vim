@@ -1,34 +1,41 @@
-" liva syntax file
+" Vim syntax file
 " Language: Liva
-" Maintainer: Patrick Haller
+" Generated from the token tables of liva_parser, do not edit
 
 if exists("b:current_syntax")
     finish
 endif
 
-" Keywords
-syn keyword basicLanguageKeywords return class end fun do while for if let in else external as import
-
-" Comments
-syn keyword todos TODO FIXME 
-syn match comment "//.*$" contains=todos
-
-" Numbers
-syn match number '\d\+' contained display
-syn match number '[-+]\d\+' contained display
-
-" Floating point number with decimal no E or e (+,-)
-syn match number '\d\+\.\d*' contained display
-syn match number '[-+]\d\+\.\d*' contained display
-
-" Strings
-syn region string start='"' end='"' contained
-syn region sesc start='"' end='"'
-
-let b:current_syntax = "cel"
-
-hi def link todos        Todo
-hi def link comment      Comment
-hi def link string       Constant
-hi def link sesc         PreProc
-hi def link number       Constant
+syn keyword livaKeyword return class end fun do while for if let in else external as import match case try catch finally raise then pub export from
+syn keyword livaOperatorWord and or not
+syn keyword livaBuiltin print len range
+syn match livaOperator "\V..=\|..\|??\|?.\|==\|!=\|<=\|>=\|+\|-\|*\|/\|%\|\^\|<\|>\|=\|."
+syn match livaFunction "\(\<fun\s\+\)\@<=[A-Za-z_][A-Za-z0-9_]*"
+syn match livaClass "\(\<class\s\+\)\@<=[A-Za-z_][A-Za-z0-9_]*"
+syn match livaConstant "\c\<\(true\|false\|nil\)\>"
+syn match livaNumber "\<\d\+\(\.\d\+\)\=\>"
+
+syn match livaEscape +\\.+ contained
+syn region livaString start=+"+ skip=+\\.+ end=+"+ contains=livaEscape
+syn region livaString start=+'+ skip=+\\.+ end=+'+ contains=livaEscape
+
+" Comments are defined last, so they take priority over the operators
+syn keyword livaTodo TODO FIXME XXX contained
+syn match livaComment "//.*$" contains=livaTodo
+syn region livaComment start="/\*" end="\*/" contains=livaComment,livaTodo
+syn match livaComment "\%^#!.*$"
+
+hi def link livaKeyword      Keyword
+hi def link livaOperatorWord Operator
+hi def link livaOperator     Operator
+hi def link livaBuiltin      Function
+hi def link livaFunction     Function
+hi def link livaClass        Type
+hi def link livaConstant     Constant
+hi def link livaNumber       Number
+hi def link livaString       String
+hi def link livaEscape       SpecialChar
+hi def link livaTodo         Todo
+hi def link livaComment      Comment
+
+let b:current_syntax = "liva"
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use liva_parser::fuzzing::check_lexer;
+
+fuzz_target!(|source: &str| {
+    if let Err(message) = check_lexer(source) {
+        panic!("{}", message);
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use liva_parser::{parse_complete, parse_source, Span};
+
+fuzz_target!(|source: &str| {
+    let _ = parse_source(Span::new(source));
+    let _ = parse_complete(Span::new(source));
+});
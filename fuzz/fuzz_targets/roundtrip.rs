@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use liva_parser::fuzzing::check_roundtrip;
+
+// Formatting a program keeps its meaning and formatting again changes nothing
+fuzz_target!(|source: &str| {
+    if let Err(message) = check_roundtrip(source) {
+        panic!("{}", message);
+    }
+});
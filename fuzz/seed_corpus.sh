@@ -0,0 +1,14 @@
+#!/bin/sh
+# Seeds the corpus of every fuzz target with the example programs and the
+# inputs of the tests
+set -e
+cd "$(dirname "$0")"
+for target in parse_source lexer roundtrip; do
+    mkdir -p "corpus/$target"
+    for dir in ../examples ../tests/fixtures/golden ../tests/fixtures/crashes \
+        ../tests/fixtures/conformance/*; do
+        for file in "$dir"/*.lv; do
+            cp "$file" "corpus/$target/$(basename "$dir")-$(basename "$file")"
+        done
+    done
+done
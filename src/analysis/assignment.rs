@@ -0,0 +1,269 @@
+//! Checks the difference between declarations and re-assignments
+//!
+//! A name has to be declared before it can be re-assigned:
+//!
+//! ```code
+//! let x = 1   // Declaration
+//! x = 2       // Re-assignment
+//! ```
+//!
+//! The checker reports
+//!
+//! * re-assignments of names which were never declared
+//! * re-assignments of functions, classes, methods, imports and builtins. Only
+//!   names declared by `let`, parameters, loop items and bindings of `match`
+//!   and `catch` can be re-assigned
+//! * a `let` of a name, which is already declared in the same scope
+//! * assignments to values which can not be assigned to, like `f() = 3`.
+//!   Targets have to be a name, a property `a.b` or an index `a[i]`
+
+use std::fmt;
+
+use crate::analysis::{
+    scope::{analyze, ScopeTree, SymbolKind},
+    visit::{walk_statement, Visitor},
+};
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, PrefixExpr},
+    literals::Variable,
+    position::{Spanned, TokenSpan},
+    statement::{Block, Statement},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AssignmentError<'a> {
+    /// Re-assignment of a name, which is not declared at this point
+    Undeclared(Variable<'a>),
+
+    /// Re-assignment of a name, which is not a variable
+    NotAVariable {
+        name: Variable<'a>,
+        kind: SymbolKind,
+    },
+
+    /// `let` of a name, which is already declared in the same scope
+    Redeclaration {
+        name: Variable<'a>,
+        previous: TokenSpan<'a>,
+    },
+
+    /// Assignment to something else than a name, property or index
+    InvalidTarget(PrefixExpr<'a>),
+}
+
+impl<'a> AssignmentError<'a> {
+    /// Span of the assigned name or the first name of an invalid target
+    pub fn span(&self) -> Option<TokenSpan<'a>> {
+        match self {
+            AssignmentError::Undeclared(name) => Some(name.pos),
+            AssignmentError::NotAVariable { name, .. } => Some(name.pos),
+            AssignmentError::Redeclaration { name, .. } => Some(name.pos),
+            AssignmentError::InvalidTarget(target) => target.span(),
+        }
+    }
+}
+
+impl<'a> fmt::Display for AssignmentError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssignmentError::Undeclared(name) => {
+                write!(f, "`{}` is assigned before it is declared", name.value)
+            }
+            AssignmentError::NotAVariable { name, kind } => write!(
+                f,
+                "`{}` can not be assigned, it is a {}",
+                name.value,
+                format!("{:?}", kind).to_lowercase()
+            ),
+            AssignmentError::Redeclaration { name, .. } => {
+                write!(f, "`{}` is already declared in this scope", name.value)
+            }
+            AssignmentError::InvalidTarget(_) => write!(f, "invalid assignment target"),
+        }
+    }
+}
+
+/// Runs the scope analysis and checks all assignments of the block
+pub fn check_assignments<'a>(block: &Block<'a>) -> Vec<AssignmentError<'a>> {
+    check_assignments_with(block, &analyze(block))
+}
+
+/// Checks all assignments of the block with the result of a previous scope analysis
+pub fn check_assignments_with<'a>(
+    block: &Block<'a>,
+    scopes: &ScopeTree<'a>,
+) -> Vec<AssignmentError<'a>> {
+    let mut checker = AssignmentChecker {
+        scopes,
+        errors: vec![],
+    };
+    checker.visit_block(block);
+    checker.errors
+}
+
+struct AssignmentChecker<'a, 's> {
+    scopes: &'s ScopeTree<'a>,
+    errors: Vec<AssignmentError<'a>>,
+}
+
+impl<'a, 's> AssignmentChecker<'a, 's> {
+    fn check_target(&mut self, target: &PrefixExpr<'a>) {
+        match (&target.prefix, target.suffix_chain.last()) {
+            (ExprOrVarname::Varname(name), None) => self.check_reassignment(*name),
+            (_, Some(ExprSuffix::TableDot(_))) | (_, Some(ExprSuffix::TableIdx(_))) => {}
+            _ => self
+                .errors
+                .push(AssignmentError::InvalidTarget(target.clone())),
+        }
+    }
+
+    fn check_reassignment(&mut self, name: Variable<'a>) {
+        let reference = self
+            .scopes
+            .references
+            .iter()
+            .find(|reference| same_span(reference.name.pos, name.pos));
+
+        let symbol = reference
+            .and_then(|reference| reference.symbol)
+            .map(|id| &self.scopes.symbols[id]);
+
+        match symbol {
+            None => self.errors.push(AssignmentError::Undeclared(name)),
+            Some(symbol) => match symbol.kind {
+                SymbolKind::Variable
+                | SymbolKind::Parameter
+                | SymbolKind::LoopItem
+                | SymbolKind::PatternBinding
+                | SymbolKind::CatchBinding => {}
+                kind => self
+                    .errors
+                    .push(AssignmentError::NotAVariable { name, kind }),
+            },
+        }
+    }
+
+    fn check_declaration(&mut self, name: Variable<'a>) {
+        let symbols = &self.scopes.symbols;
+        let declared = symbols.iter().position(|symbol| match symbol.declaration {
+            Some(span) => same_span(span, name.pos),
+            None => false,
+        });
+
+        let declared = match declared {
+            Some(id) => id,
+            None => return,
+        };
+
+        let scope = &self.scopes.scopes[symbols[declared].scope];
+        let previous = scope
+            .symbols
+            .iter()
+            .take_while(|&&id| id != declared)
+            .find(|&&id| symbols[id].name == name.value);
+
+        if let Some(&previous) = previous {
+            self.errors.push(AssignmentError::Redeclaration {
+                name,
+                previous: symbols[previous].declaration.unwrap(),
+            });
+        }
+    }
+}
+
+impl<'a, 's> Visitor<'a> for AssignmentChecker<'a, 's> {
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        match stmt {
+            Statement::Assignment(assignment) => self.check_target(&assignment.variable),
+            Statement::LAssignment(assignment) => self.check_declaration(assignment.variable),
+            _ => {}
+        }
+        walk_statement(self, stmt);
+    }
+}
+
+fn same_span(a: TokenSpan, b: TokenSpan) -> bool {
+    a.location_offset() == b.location_offset() && a.fragment() == b.fragment()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    fn check(source: &str) -> Vec<String> {
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        check_assignments(&block)
+            .iter()
+            .map(|error| match error {
+                AssignmentError::Undeclared(name) => format!("undeclared {}", name.value),
+                AssignmentError::NotAVariable { name, kind } => {
+                    format!("{:?} {}", kind, name.value)
+                }
+                AssignmentError::Redeclaration { name, previous } => {
+                    format!("redeclared {} {}", name.value, previous.location_line())
+                }
+                AssignmentError::InvalidTarget(_) => String::from("invalid target"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_assignments() {
+        let source = "let x = 1\nx = 2\nfun f(n)\n    n = n - 1\n    let y = n\n    y = 3\nend\nfor i in [1] do\n    i = 2\nend\nx.y = 1\nx[0] = 1";
+        assert!(check(source).is_empty());
+    }
+
+    #[test]
+    fn test_undeclared() {
+        let source = "x = 2\nlet x = 1\ny = 3";
+        assert_eq!(check(source), vec!["undeclared x", "undeclared y"]);
+    }
+
+    #[test]
+    fn test_not_a_variable() {
+        let source = "import math\nfun f() end\nf = 1\nmath = 2\nprint = 3";
+        assert_eq!(
+            check(source),
+            vec!["Function f", "Import math", "Builtin print"]
+        );
+    }
+
+    #[test]
+    fn test_redeclaration() {
+        let source =
+            "let x = 1\nlet x = 2\nif x do\n    let x = 3\nend\nfun f(a)\n    let a = 1\nend";
+        assert_eq!(check(source), vec!["redeclared x 1", "redeclared a 6"]);
+    }
+
+    #[test]
+    fn test_invalid_targets() {
+        let source = "let a = 1\nf() = 3\na.b() = 1\na?.b = 2\na[1:2] = 3";
+        assert_eq!(check(source), vec!["invalid target"; 4]);
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let source = "y = 1
+fun f() end
+f = 2
+let x = 1
+let x = 2
+f() = 3";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let messages: Vec<(u32, String)> = check_assignments(&block)
+            .iter()
+            .map(|error| (error.span().unwrap().location_line(), error.to_string()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, String::from("`y` is assigned before it is declared")),
+                (3, String::from("`f` can not be assigned, it is a function")),
+                (5, String::from("`x` is already declared in this scope")),
+                (6, String::from("invalid assignment target")),
+            ]
+        );
+    }
+}
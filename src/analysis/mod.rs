@@ -0,0 +1,57 @@
+//! Semantic passes over the parsed AST
+
+pub mod assignment;
+pub mod scope;
+pub mod visit;
+
+use crate::parser::{position::TokenSpan, statement::Block};
+
+/// Runs the scope analysis and the assignment checks and returns the span
+/// and message of every error ordered by position.
+/// An assignment to an undeclared name is also an undefined name, only the
+/// more specific assignment error is reported
+pub fn semantic_errors<'a>(block: &Block<'a>) -> Vec<(TokenSpan<'a>, String)> {
+    let scopes = scope::analyze(block);
+    let mut errors: Vec<(TokenSpan<'a>, String)> =
+        assignment::check_assignments_with(block, &scopes)
+            .iter()
+            .filter_map(|error| Some((error.span()?, error.to_string())))
+            .collect();
+
+    for error in scopes.errors.iter() {
+        let span = error.name().pos;
+        let reported = errors
+            .iter()
+            .any(|(other, _)| other.location_offset() == span.location_offset());
+        if !reported {
+            errors.push((span, error.to_string()));
+        }
+    }
+    errors.sort_by_key(|(span, _)| span.location_offset());
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    #[test]
+    fn test_semantic_errors() {
+        let source = "x = 1\nprint(y)\nfun f() end\nf = 2";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let errors: Vec<(u32, String)> = semantic_errors(&block)
+            .into_iter()
+            .map(|(span, message)| (span.location_line(), message))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, String::from("`x` is assigned before it is declared")),
+                (2, String::from("`y` is not defined")),
+                (4, String::from("`f` can not be assigned, it is a function")),
+            ]
+        );
+    }
+}
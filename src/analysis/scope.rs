@@ -0,0 +1,615 @@
+//! Scope analysis and name resolution
+//!
+//! Builds the lexical scopes of a parsed [`Block`] and binds every use of a
+//! name to its declaration. Names can be declared by
+//!
+//! * `let` assignments
+//! * function parameters
+//! * the item of a `for` loop
+//! * imports, either by their alias, the selectively imported names or the
+//!   last segment of the path
+//! * functions and classes, which are visible in their whole block
+//! * bindings of `match` patterns and `catch` clauses
+//!
+//! Function bodies are resolved after their enclosing block, so they can
+//! use every name of the enclosing scopes. Methods do not see the names of
+//! their class, they are accessed through `self`.
+//!
+//! ```rust
+//! use liva_parser::{analysis::scope::analyze, parse_source, Span};
+//!
+//! let (_, block) = parse_source(Span::new("let x = 3\nprint(x + y)")).unwrap();
+//! let scopes = analyze(&block);
+//!
+//! assert_eq!(scopes.symbol_at(2, 7).unwrap().name, "x");
+//! assert_eq!(scopes.errors.len(), 1);
+//! ```
+
+use std::fmt;
+
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr},
+    literals::{Collection, Variable},
+    position::TokenSpan,
+    statement::{
+        declaration::{
+            class::Class, function::Function, match_statement::Pattern, try_statement::Try,
+        },
+        Block, Statement,
+    },
+};
+
+/// Names which are always defined
+pub const BUILTINS: [&str; 3] = ["print", "len", "range"];
+
+pub type ScopeId = usize;
+pub type SymbolId = usize;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScopeKind {
+    /// The outermost scope, which contains the builtins
+    Builtin,
+    Module,
+    Function,
+    Class,
+    For,
+    Block,
+    MatchArm,
+    Catch,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+
+    /// Symbols in the order they are declared
+    pub symbols: Vec<SymbolId>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolKind {
+    Builtin,
+    Variable,
+    Parameter,
+    LoopItem,
+    Import,
+    Function,
+    Class,
+    Method,
+    PatternBinding,
+    CatchBinding,
+}
+
+/// A declared name
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub kind: SymbolKind,
+
+    /// Span of the name in the declaration, builtins have none
+    pub declaration: Option<TokenSpan<'a>>,
+    pub scope: ScopeId,
+}
+
+/// A use of a name
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference<'a> {
+    pub name: Variable<'a>,
+
+    /// If the name is the target of a re-assignment
+    pub write: bool,
+
+    pub scope: ScopeId,
+
+    /// The declaration the name refers to, `None` if the name could not be resolved
+    pub symbol: Option<SymbolId>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum NameError<'a> {
+    /// The name is not declared in any enclosing scope
+    Undefined(Variable<'a>),
+
+    /// The name is used before its declaration in a enclosing scope
+    UseBeforeDefinition {
+        name: Variable<'a>,
+        declaration: TokenSpan<'a>,
+    },
+}
+
+impl<'a> NameError<'a> {
+    pub fn name(&self) -> Variable<'a> {
+        match self {
+            NameError::Undefined(name) => *name,
+            NameError::UseBeforeDefinition { name, .. } => *name,
+        }
+    }
+}
+
+impl<'a> fmt::Display for NameError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NameError::Undefined(name) => write!(f, "`{}` is not defined", name.value),
+            NameError::UseBeforeDefinition { name, .. } => {
+                write!(f, "`{}` is used before its declaration", name.value)
+            }
+        }
+    }
+}
+
+/// Result of the scope analysis
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ScopeTree<'a> {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol<'a>>,
+    pub references: Vec<Reference<'a>>,
+
+    /// Errors ordered by their position in the source
+    pub errors: Vec<NameError<'a>>,
+}
+
+impl<'a> ScopeTree<'a> {
+    /// Returns the symbol the identifier at the given line and column refers to.
+    /// This works for uses as well as for declarations of a name
+    pub fn symbol_at(&self, line: u32, column: usize) -> Option<&Symbol<'a>> {
+        let reference = self
+            .references
+            .iter()
+            .find(|reference| covers(reference.name.pos, line, column));
+
+        match reference {
+            Some(reference) => reference.symbol.map(|id| &self.symbols[id]),
+            None => self.symbols.iter().find(|symbol| match symbol.declaration {
+                Some(span) => covers(span, line, column),
+                None => false,
+            }),
+        }
+    }
+
+    /// Looks up the name in the scope and all enclosing scopes. The names
+    /// declared in a class are only visible in the class scope itself
+    pub fn lookup(&self, name: &str, scope: ScopeId) -> Option<SymbolId> {
+        let mut scope = Some(scope);
+        let mut first = true;
+        while let Some(id) = scope {
+            let current = &self.scopes[id];
+            if first || current.kind != ScopeKind::Class {
+                let found = current
+                    .symbols
+                    .iter()
+                    .rev()
+                    .find(|&&symbol| self.symbols[symbol].name == name);
+                if let Some(&symbol) = found {
+                    return Some(symbol);
+                }
+            }
+            first = false;
+            scope = current.parent;
+        }
+        None
+    }
+
+    /// Returns all references to the symbol
+    pub fn references_to(&self, symbol: SymbolId) -> Vec<&Reference<'a>> {
+        self.references
+            .iter()
+            .filter(|reference| reference.symbol == Some(symbol))
+            .collect()
+    }
+}
+
+fn covers(span: TokenSpan, line: u32, column: usize) -> bool {
+    let start = span.get_utf8_column();
+    span.location_line() == line && start <= column && column < start + span.fragment().len()
+}
+
+/// Runs the scope analysis with the default [`BUILTINS`]
+pub fn analyze<'a>(block: &Block<'a>) -> ScopeTree<'a> {
+    analyze_with_builtins(block, &BUILTINS)
+}
+
+/// Runs the scope analysis, names in `builtins` are defined everywhere
+pub fn analyze_with_builtins<'a>(block: &Block<'a>, builtins: &[&'a str]) -> ScopeTree<'a> {
+    let mut analyzer = Analyzer {
+        tree: ScopeTree::default(),
+        current: 0,
+        unresolved: vec![],
+    };
+
+    analyzer.enter(ScopeKind::Builtin);
+    for builtin in builtins {
+        analyzer.declare_builtin(builtin);
+    }
+
+    analyzer.enter(ScopeKind::Module);
+    analyzer.body(block);
+    analyzer.exit();
+
+    analyzer.finish()
+}
+
+/// A function body which is resolved after its enclosing block
+type Deferred<'a, 'b> = (&'b Function<'a>, ScopeId);
+
+struct Analyzer<'a> {
+    tree: ScopeTree<'a>,
+    current: ScopeId,
+
+    /// Indices of references which could not be resolved yet
+    unresolved: Vec<usize>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn enter(&mut self, kind: ScopeKind) {
+        let parent = if self.tree.scopes.is_empty() {
+            None
+        } else {
+            Some(self.current)
+        };
+        self.tree.scopes.push(Scope {
+            kind,
+            parent,
+            symbols: vec![],
+        });
+        self.current = self.tree.scopes.len() - 1;
+    }
+
+    fn exit(&mut self) {
+        if let Some(parent) = self.tree.scopes[self.current].parent {
+            self.current = parent;
+        }
+    }
+
+    fn declare_builtin(&mut self, name: &'a str) {
+        self.add_symbol(name, SymbolKind::Builtin, None);
+    }
+
+    fn declare(&mut self, name: Variable<'a>, kind: SymbolKind) {
+        self.add_symbol(name.value, kind, Some(name.pos));
+    }
+
+    fn add_symbol(&mut self, name: &'a str, kind: SymbolKind, declaration: Option<TokenSpan<'a>>) {
+        let id = self.tree.symbols.len();
+        self.tree.symbols.push(Symbol {
+            name,
+            kind,
+            declaration,
+            scope: self.current,
+        });
+        self.tree.scopes[self.current].symbols.push(id);
+    }
+
+    fn reference(&mut self, name: Variable<'a>, write: bool) {
+        let symbol = self.tree.lookup(name.value, self.current);
+        if symbol.is_none() {
+            self.unresolved.push(self.tree.references.len());
+        }
+        self.tree.references.push(Reference {
+            name,
+            write,
+            scope: self.current,
+            symbol,
+        });
+    }
+
+    /// Walks the statements of a block in the current scope
+    fn body<'b>(&mut self, block: &'b Block<'a>) {
+        let mut deferred: Vec<Deferred<'a, 'b>> = vec![];
+
+        // Functions and classes are visible in the whole block
+        for stmt in block.statements.iter() {
+            match stmt {
+                Statement::Fun(function) => self.declare(function.name, SymbolKind::Function),
+                Statement::Class(class) => self.declare(class.name, SymbolKind::Class),
+                _ => {}
+            }
+        }
+
+        for stmt in block.statements.iter() {
+            self.statement(stmt, &mut deferred);
+        }
+
+        if let Some(return_stmt) = &block.return_stmt {
+            for value in return_stmt.values.iter() {
+                self.expression(value);
+            }
+        }
+
+        let current = self.current;
+        for (function, scope) in deferred {
+            self.current = scope;
+            self.function(function);
+        }
+        self.current = current;
+    }
+
+    fn scoped_body(&mut self, kind: ScopeKind, block: &Block<'a>) {
+        self.enter(kind);
+        self.body(block);
+        self.exit();
+    }
+
+    fn function(&mut self, function: &Function<'a>) {
+        self.enter(ScopeKind::Function);
+        for parameter in function.parameters.iter() {
+            self.declare(*parameter, SymbolKind::Parameter);
+        }
+        self.body(&function.block);
+        self.exit();
+    }
+
+    fn class<'b>(&mut self, class: &'b Class<'a>, deferred: &mut Vec<Deferred<'a, 'b>>) {
+        self.enter(ScopeKind::Class);
+        for method in class.methods.iter() {
+            self.declare(method.name, SymbolKind::Method);
+            deferred.push((method, self.current));
+        }
+        self.exit();
+    }
+
+    fn statement<'b>(&mut self, stmt: &'b Statement<'a>, deferred: &mut Vec<Deferred<'a, 'b>>) {
+        match stmt {
+            Statement::Assignment(assignment) => {
+                self.expression(&assignment.expression);
+                self.prefix_expr(&assignment.variable, true);
+            }
+            Statement::LAssignment(assignment) => {
+                self.expression(&assignment.expression);
+                self.declare(assignment.variable, SymbolKind::Variable);
+            }
+            Statement::FuncCall(call) => self.prefix_expr(call, false),
+            Statement::While(while_stmt) => {
+                self.expression(&while_stmt.cond);
+                self.scoped_body(ScopeKind::Block, &while_stmt.block);
+            }
+            Statement::For(for_stmt) => {
+                self.expression(&for_stmt.iterator);
+                self.enter(ScopeKind::For);
+                self.declare(for_stmt.iter_item, SymbolKind::LoopItem);
+                self.body(&for_stmt.block);
+                self.exit();
+            }
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.cond);
+                self.scoped_body(ScopeKind::Block, &if_stmt.stmts);
+                if let Some(else_statements) = &if_stmt.else_statements {
+                    self.scoped_body(ScopeKind::Block, else_statements);
+                }
+            }
+            Statement::Match(match_stmt) => {
+                self.expression(&match_stmt.expr);
+                for arm in match_stmt.arms.iter() {
+                    self.enter(ScopeKind::MatchArm);
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.body(&arm.block);
+                    self.exit();
+                }
+            }
+            Statement::Try(try_stmt) => self.try_stmt(try_stmt),
+            Statement::Fun(function) => deferred.push((function, self.current)),
+            Statement::Class(class) => self.class(class, deferred),
+            Statement::Return(return_stmt) => {
+                for value in return_stmt.values.iter() {
+                    self.expression(value);
+                }
+            }
+            Statement::Import(import) => {
+                if let Some(alias) = import.alias {
+                    self.declare(alias, SymbolKind::Import);
+                } else if !import.items.is_empty() {
+                    for item in import.items.iter() {
+                        self.declare(*item, SymbolKind::Import);
+                    }
+                } else if let Some(last) = import.path.last() {
+                    self.declare(*last, SymbolKind::Import);
+                }
+            }
+            Statement::Raise(raise) => self.expression(&raise.value),
+        }
+    }
+
+    fn try_stmt(&mut self, try_stmt: &Try<'a>) {
+        self.scoped_body(ScopeKind::Block, &try_stmt.block);
+        if let Some(catch) = &try_stmt.catch {
+            self.enter(ScopeKind::Catch);
+            if let Some(binding) = catch.binding {
+                self.declare(binding, SymbolKind::CatchBinding);
+            }
+            self.body(&catch.block);
+            self.exit();
+        }
+        if let Some(finally) = &try_stmt.finally {
+            self.scoped_body(ScopeKind::Block, finally);
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern<'a>) {
+        match pattern {
+            Pattern::Binding(name) => self.declare(*name, SymbolKind::PatternBinding),
+            Pattern::Array(patterns) => {
+                for pattern in patterns.iter() {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Map(patterns) => {
+                let mut patterns: Vec<_> = patterns.iter().collect();
+                patterns.sort_by(|a, b| a.0.cmp(b.0));
+                for (_, pattern) in patterns {
+                    self.pattern(pattern);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Range(_) | Pattern::Wildcard(_) => {}
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression<'a>) {
+        match expr {
+            Expression::Literal(_) => {}
+            Expression::Call(call) => {
+                if let Some(callee) = call.callee {
+                    self.reference(callee, false);
+                }
+                for arg in call.args.iter() {
+                    self.expression(arg);
+                }
+            }
+            Expression::BinaryOp(op) => {
+                self.expression(&op.left);
+                self.expression(&op.right);
+            }
+            Expression::UnaryOp(op) => self.expression(&op.operand),
+            Expression::PrefixExpr(prefix_expr) => self.prefix_expr(prefix_expr, false),
+            Expression::Collection(Collection::Array(values)) => {
+                for value in values.iter() {
+                    self.expression(value);
+                }
+            }
+            Expression::Collection(Collection::Map(values)) => {
+                let mut values: Vec<_> = values.iter().collect();
+                values.sort_by(|a, b| a.0.cmp(b.0));
+                for (_, value) in values {
+                    self.expression(value);
+                }
+            }
+            Expression::Conditional(conditional) => {
+                self.expression(&conditional.cond);
+                self.expression(&conditional.then_expr);
+                self.expression(&conditional.else_expr);
+            }
+            Expression::NilCoalesce(coalesce) => {
+                self.expression(&coalesce.left);
+                self.expression(&coalesce.right);
+            }
+        }
+    }
+
+    fn prefix_expr(&mut self, prefix_expr: &PrefixExpr<'a>, write: bool) {
+        match &prefix_expr.prefix {
+            ExprOrVarname::Exp(expr) => self.expression(expr),
+            ExprOrVarname::Varname(name) => {
+                self.reference(*name, write && prefix_expr.suffix_chain.is_empty())
+            }
+        }
+
+        for suffix in prefix_expr.suffix_chain.iter() {
+            match suffix {
+                // Properties and methods are not resolved
+                ExprSuffix::TableDot(_) | ExprSuffix::SafeTableDot(_) => {}
+                ExprSuffix::TableIdx(expr) | ExprSuffix::SafeTableIdx(expr) => {
+                    self.expression(expr)
+                }
+                ExprSuffix::TableSlice(slice) => {
+                    for bound in slice.start.iter().chain(slice.end.iter()) {
+                        self.expression(bound);
+                    }
+                }
+                ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                    for arg in call.args.iter() {
+                        self.expression(arg);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> ScopeTree<'a> {
+        for idx in std::mem::take(&mut self.unresolved) {
+            let reference = &self.tree.references[idx];
+            let name = reference.name;
+
+            // Everything is declared by now, so a successful lookup means
+            // the name was declared after it was used
+            let error = match self.tree.lookup(name.value, reference.scope) {
+                Some(symbol) => NameError::UseBeforeDefinition {
+                    name,
+                    declaration: self.tree.symbols[symbol].declaration.unwrap(),
+                },
+                None => NameError::Undefined(name),
+            };
+            self.tree.errors.push(error);
+        }
+
+        self.tree
+            .errors
+            .sort_by_key(|error| error.name().pos.location_offset());
+        self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    fn errors(source: &str) -> Vec<(&str, bool)> {
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        analyze(&block)
+            .errors
+            .iter()
+            .map(|error| match error {
+                NameError::Undefined(name) => (name.value, false),
+                NameError::UseBeforeDefinition { name, .. } => (name.value, true),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_examples() {
+        let source = "fun fib(n)\n    if n == 0 do\n        return 0\n    end\n    return fib(n - 1) + fib(n - 2)\nend\nprint(fib(3))";
+        assert!(errors(source).is_empty());
+
+        let source = "class Person\n    fun init(self, height)\n        self.height = height\n    end\nend\nfor i in [1, 2] do\n    let person = Person(i)\n    print(person.init())\nend";
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn test_undefined_and_use_before_definition() {
+        let source = "print(x)\nlet x = 3\nprint(y)\nif x do\n    let z = 1\nend\nprint(z)";
+        assert_eq!(
+            errors(source),
+            vec![("x", true), ("y", false), ("z", false)]
+        );
+    }
+
+    #[test]
+    fn test_function_bodies_see_later_declarations() {
+        let source = "fun show()\n    print(config)\nend\nlet config = 1\nshow()";
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn test_methods_do_not_see_class_scope() {
+        let source =
+            "class A\n    fun a(self)\n        return b()\n    end\n    fun b(self)\n    end\nend";
+        assert_eq!(errors(source), vec![("b", false)]);
+    }
+
+    #[test]
+    fn test_bindings() {
+        let source = "import lib.math\nfrom util import double\nmatch math.x do\n    case [a, _] if a do\n        print(a)\n    end\nend\ntry\n    double(1)\ncatch err do\n    print(err)\nend";
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn test_symbol_at() {
+        let source = "let x = 3\nfun f(x)\n    return x\nend\nprint(x)";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let scopes = analyze(&block);
+
+        let parameter = scopes.symbol_at(3, 12).unwrap();
+        assert_eq!(parameter.kind, SymbolKind::Parameter);
+        assert_eq!(parameter.declaration.unwrap().location_line(), 2);
+
+        let variable = scopes.symbol_at(5, 7).unwrap();
+        assert_eq!(variable.kind, SymbolKind::Variable);
+        assert_eq!(scopes.symbol_at(1, 5), Some(variable));
+
+        assert_eq!(scopes.symbol_at(5, 1).unwrap().kind, SymbolKind::Builtin);
+        assert_eq!(scopes.symbol_at(4, 1), None);
+    }
+}
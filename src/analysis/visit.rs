@@ -0,0 +1,221 @@
+//! Traversal of the AST
+//!
+//! Implementors of [`Visitor`] override the methods for the nodes they are
+//! interested in and call the matching `walk_*` function to descend further.
+//!
+//! ```rust
+//! use liva_parser::{
+//!     analysis::visit::{walk_function, Visitor},
+//!     parse_source,
+//!     statement::declaration::function::Function,
+//!     Span,
+//! };
+//!
+//! struct CountFunctions(usize);
+//!
+//! impl<'a> Visitor<'a> for CountFunctions {
+//!     fn visit_function(&mut self, function: &Function<'a>) {
+//!         self.0 += 1;
+//!         walk_function(self, function);
+//!     }
+//! }
+//!
+//! let (_, block) = parse_source(Span::new("fun a()\n  fun b() end\nend")).unwrap();
+//! let mut counter = CountFunctions(0);
+//! counter.visit_block(&block);
+//! assert_eq!(counter.0, 2);
+//! ```
+
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr},
+    literals::Collection,
+    statement::{
+        declaration::{class::Class, function::Function, match_statement::Pattern},
+        Block, Statement,
+    },
+};
+
+pub trait Visitor<'a> {
+    fn visit_block(&mut self, block: &Block<'a>) {
+        walk_block(self, block)
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_function(&mut self, function: &Function<'a>) {
+        walk_function(self, function)
+    }
+
+    fn visit_class(&mut self, class: &Class<'a>) {
+        walk_class(self, class)
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern<'a>) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression<'a>) {
+        walk_expression(self, expr)
+    }
+
+    fn visit_prefix_expr(&mut self, prefix_expr: &PrefixExpr<'a>) {
+        walk_prefix_expr(self, prefix_expr)
+    }
+}
+
+pub fn walk_block<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, block: &Block<'a>) {
+    for stmt in block.statements.iter() {
+        visitor.visit_statement(stmt);
+    }
+    if let Some(return_stmt) = &block.return_stmt {
+        for value in return_stmt.values.iter() {
+            visitor.visit_expression(value);
+        }
+    }
+}
+
+pub fn walk_statement<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stmt: &Statement<'a>) {
+    match stmt {
+        Statement::Assignment(assignment) => {
+            visitor.visit_prefix_expr(&assignment.variable);
+            visitor.visit_expression(&assignment.expression);
+        }
+        Statement::LAssignment(assignment) => visitor.visit_expression(&assignment.expression),
+        Statement::FuncCall(call) => visitor.visit_prefix_expr(call),
+        Statement::While(while_stmt) => {
+            visitor.visit_expression(&while_stmt.cond);
+            visitor.visit_block(&while_stmt.block);
+        }
+        Statement::For(for_stmt) => {
+            visitor.visit_expression(&for_stmt.iterator);
+            visitor.visit_block(&for_stmt.block);
+        }
+        Statement::If(if_stmt) => {
+            visitor.visit_expression(&if_stmt.cond);
+            visitor.visit_block(&if_stmt.stmts);
+            if let Some(else_statements) = &if_stmt.else_statements {
+                visitor.visit_block(else_statements);
+            }
+        }
+        Statement::Match(match_stmt) => {
+            visitor.visit_expression(&match_stmt.expr);
+            for arm in match_stmt.arms.iter() {
+                visitor.visit_pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    visitor.visit_expression(guard);
+                }
+                visitor.visit_block(&arm.block);
+            }
+        }
+        Statement::Try(try_stmt) => {
+            visitor.visit_block(&try_stmt.block);
+            if let Some(catch) = &try_stmt.catch {
+                visitor.visit_block(&catch.block);
+            }
+            if let Some(finally) = &try_stmt.finally {
+                visitor.visit_block(finally);
+            }
+        }
+        Statement::Fun(function) => visitor.visit_function(function),
+        Statement::Class(class) => visitor.visit_class(class),
+        Statement::Return(return_stmt) => {
+            for value in return_stmt.values.iter() {
+                visitor.visit_expression(value);
+            }
+        }
+        Statement::Import(_) => {}
+        Statement::Raise(raise) => visitor.visit_expression(&raise.value),
+    }
+}
+
+pub fn walk_function<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, function: &Function<'a>) {
+    visitor.visit_block(&function.block);
+}
+
+pub fn walk_class<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, class: &Class<'a>) {
+    for method in class.methods.iter() {
+        visitor.visit_function(method);
+    }
+}
+
+pub fn walk_pattern<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, pattern: &Pattern<'a>) {
+    match pattern {
+        Pattern::Array(patterns) => {
+            for pattern in patterns.iter() {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Map(patterns) => {
+            for pattern in patterns.values() {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Pattern::Literal(_) | Pattern::Range(_) | Pattern::Wildcard(_) | Pattern::Binding(_) => {}
+    }
+}
+
+pub fn walk_expression<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &Expression<'a>) {
+    match expr {
+        Expression::Literal(_) => {}
+        Expression::Call(call) => {
+            for arg in call.args.iter() {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::BinaryOp(op) => {
+            visitor.visit_expression(&op.left);
+            visitor.visit_expression(&op.right);
+        }
+        Expression::UnaryOp(op) => visitor.visit_expression(&op.operand),
+        Expression::PrefixExpr(prefix_expr) => visitor.visit_prefix_expr(prefix_expr),
+        Expression::Collection(Collection::Array(values)) => {
+            for value in values.iter() {
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Collection(Collection::Map(values)) => {
+            for value in values.values() {
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Conditional(conditional) => {
+            visitor.visit_expression(&conditional.cond);
+            visitor.visit_expression(&conditional.then_expr);
+            visitor.visit_expression(&conditional.else_expr);
+        }
+        Expression::NilCoalesce(coalesce) => {
+            visitor.visit_expression(&coalesce.left);
+            visitor.visit_expression(&coalesce.right);
+        }
+    }
+}
+
+pub fn walk_prefix_expr<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    prefix_expr: &PrefixExpr<'a>,
+) {
+    if let ExprOrVarname::Exp(expr) = &prefix_expr.prefix {
+        visitor.visit_expression(expr);
+    }
+
+    for suffix in prefix_expr.suffix_chain.iter() {
+        match suffix {
+            ExprSuffix::TableDot(_) | ExprSuffix::SafeTableDot(_) => {}
+            ExprSuffix::TableIdx(expr) | ExprSuffix::SafeTableIdx(expr) => {
+                visitor.visit_expression(expr)
+            }
+            ExprSuffix::TableSlice(slice) => {
+                for bound in slice.start.iter().chain(slice.end.iter()) {
+                    visitor.visit_expression(bound);
+                }
+            }
+            ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                for arg in call.args.iter() {
+                    visitor.visit_expression(arg);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Language server of liva, see [`liva_parser::lsp`]
+
+use std::io;
+use std::process;
+
+fn main() {
+    match liva_parser::lsp::run(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("liva-lsp: {}", err);
+            process::exit(1);
+        }
+    }
+}
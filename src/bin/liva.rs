@@ -0,0 +1,281 @@
+//! Command line interface of the liva parser
+//!
+//! ```text
+//! liva parse [--format sexp|debug|json] <paths>...
+//! liva check <paths>...
+//! liva fmt [--check] <paths>...
+//! liva tokens <paths>...
+//! ```
+//!
+//! Directories are searched recursively for `.lv` files and `-` reads the
+//! source code from stdin.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use liva_parser::analysis::semantic_errors;
+use liva_parser::formatter::format_source;
+use liva_parser::lexer::tokenize;
+use liva_parser::lint::{Linter, Severity};
+use liva_parser::position::Position;
+use liva_parser::sexp::ToSexp;
+use liva_parser::{parse_complete, Span};
+
+const USAGE: &str = "usage: liva <command> [options] <paths>...
+
+commands:
+    parse [--format sexp|debug|json]  print the AST of the files
+    check                             report syntax, name and lint errors
+    fmt [--check]                     format the files in place
+    tokens                            print the tokens of the files
+
+Directories are searched for .lv files, `-` reads from stdin.";
+
+/// Exit code for files with errors
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Sexp,
+    Debug,
+    Json,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Command {
+    Parse(Format),
+    Check,
+    Fmt { check: bool },
+    Tokens,
+}
+
+/// A source file or stdin
+struct Input {
+    name: String,
+    path: Option<PathBuf>,
+    source: String,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, paths) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("liva: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let inputs = match collect_inputs(&paths) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("liva: {}", err);
+            process::exit(EXIT_FAILURE);
+        }
+    };
+
+    let mut success = true;
+    for input in &inputs {
+        let result = match command {
+            Command::Parse(format) => parse(input, format),
+            Command::Check => check(input),
+            Command::Fmt { check } => fmt(input, check),
+            Command::Tokens => tokens(input),
+        };
+        match result {
+            Ok(ok) => success &= ok,
+            Err(err) => {
+                eprintln!("{}: {}", input.name, err);
+                success = false;
+            }
+        }
+    }
+    if !success {
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(Command, Vec<String>), String> {
+    let (command, options) = match args.split_first() {
+        Some((command, options)) => (command.as_str(), options),
+        None => return Err(String::from("missing command")),
+    };
+
+    let mut format = Format::Sexp;
+    let mut check = false;
+    let mut paths = Vec::new();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match (command, option.as_str()) {
+            ("parse", "--format") => {
+                format = match options.next().map(String::as_str) {
+                    Some("sexp") => Format::Sexp,
+                    Some("debug") => Format::Debug,
+                    Some("json") => Format::Json,
+                    Some(other) => return Err(format!("unknown format `{}`", other)),
+                    None => return Err(String::from("missing value for --format")),
+                }
+            }
+            ("fmt", "--check") => check = true,
+            (_, "-") => paths.push(option.clone()),
+            (_, option) if option.starts_with('-') => {
+                return Err(format!("unknown option `{}` for {}", option, command))
+            }
+            _ => paths.push(option.clone()),
+        }
+    }
+
+    let command = match command {
+        "parse" => Command::Parse(format),
+        "check" => Command::Check,
+        "fmt" => Command::Fmt { check },
+        "tokens" => Command::Tokens,
+        other => return Err(format!("unknown command `{}`", other)),
+    };
+    if paths.is_empty() {
+        return Err(String::from("no input files"));
+    }
+    Ok((command, paths))
+}
+
+fn collect_inputs(paths: &[String]) -> Result<Vec<Input>, Box<dyn Error>> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        if path == "-" {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            inputs.push(Input {
+                name: String::from("<stdin>"),
+                path: None,
+                source,
+            });
+            continue;
+        }
+
+        let mut files = Vec::new();
+        find_sources(Path::new(path), &mut files)?;
+        for file in files {
+            let source =
+                fs::read_to_string(&file).map_err(|err| format!("{}: {}", file.display(), err))?;
+            inputs.push(Input {
+                name: file.display().to_string(),
+                path: Some(file),
+                source,
+            });
+        }
+    }
+    Ok(inputs)
+}
+
+/// Adds the file or all `.lv` files of the directory in sorted order
+fn find_sources(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)
+        .map_err(|err| format!("{}: {}", path.display(), err))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_sources(&entry, files)?;
+        } else if entry.extension().is_some_and(|ext| ext == "lv") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn parse(input: &Input, format: Format) -> Result<bool, Box<dyn Error>> {
+    let block = parse_complete(Span::new(&input.source))?;
+    match format {
+        Format::Sexp => print!("{}", block.to_sexp()),
+        Format::Debug => println!("{:#?}", block),
+        Format::Json => println!("{}", to_json(block)?),
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "serde")]
+fn to_json(block: liva_parser::statement::Block) -> Result<String, Box<dyn Error>> {
+    use liva_parser::schema::Document;
+
+    Ok(serde_json::to_string_pretty(&Document::new(block))?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn to_json(_block: liva_parser::statement::Block) -> Result<String, Box<dyn Error>> {
+    Err("json output requires the `serde` feature".into())
+}
+
+/// Prints all diagnostics of the file, returns false if there are errors
+fn check(input: &Input) -> Result<bool, Box<dyn Error>> {
+    let block = match parse_complete(Span::new(&input.source)) {
+        Ok(block) => block,
+        Err(err) => {
+            println!("{}:{}: error: {}", input.name, err.position, err.message);
+            return Ok(false);
+        }
+    };
+
+    let errors = semantic_errors(&block);
+    let mut success = errors.is_empty();
+    for (span, message) in errors {
+        println!(
+            "{}:{}: error: {}",
+            input.name,
+            Position::from(span),
+            message
+        );
+    }
+    for diagnostic in Linter::default().lint(&input.source, &block) {
+        success &= diagnostic.severity != Severity::Error;
+        println!("{}:{}", input.name, diagnostic);
+    }
+    Ok(success)
+}
+
+/// Formats the file in place or prints stdin formatted,
+/// with `check` only reports unformatted files
+fn fmt(input: &Input, check: bool) -> Result<bool, Box<dyn Error>> {
+    let formatted = format_source(&input.source)?;
+    let changed = formatted != input.source;
+
+    match (&input.path, check) {
+        (_, true) if changed => {
+            println!("would reformat {}", input.name);
+            Ok(false)
+        }
+        (_, true) => Ok(true),
+        (Some(path), false) => {
+            if changed {
+                fs::write(path, formatted)?;
+            }
+            Ok(true)
+        }
+        (None, false) => {
+            print!("{}", formatted);
+            Ok(true)
+        }
+    }
+}
+
+fn tokens(input: &Input) -> Result<bool, Box<dyn Error>> {
+    for lexeme in tokenize(&input.source)? {
+        println!(
+            "{} {} {}",
+            Position::from(lexeme.span),
+            lexeme.kind,
+            lexeme.text()
+        );
+    }
+    Ok(true)
+}
@@ -0,0 +1,182 @@
+//! Generates the API documentation of liva modules, see [`liva_parser::livadoc`]
+//!
+//! ```text
+//! livadoc [--format markdown|html] [--private] [--output <dir>] <paths>...
+//! ```
+//!
+//! Every `.lv` file is documented in its own file of the output directory,
+//! which is named after the module path, together with an index of all
+//! modules. Modules in a directory are named relative to the directory,
+//! `src/lib/geometry.lv` of `livadoc src` becomes `lib.geometry`.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use liva_parser::livadoc::{render, render_index, Format, ModuleDoc};
+
+const USAGE: &str = "usage: livadoc [options] <paths>...
+
+options:
+    --format markdown|html  format of the documentation, markdown by default
+    --private               document declarations, which are not exported
+    --output <dir>          directory of the documentation, doc by default
+
+Directories are searched for .lv files.";
+
+/// Exit code for modules with errors
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug)]
+struct Options {
+    format: Format,
+    private: bool,
+    output: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("livadoc: {}\n\n{}", message, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    match document(&options) {
+        Ok(true) => {}
+        Ok(false) => process::exit(EXIT_FAILURE),
+        Err(err) => {
+            eprintln!("livadoc: {}", err);
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Markdown,
+        private: false,
+        output: PathBuf::from("doc"),
+        paths: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                options.format = match args.next().map(String::as_str) {
+                    Some("markdown") | Some("md") => Format::Markdown,
+                    Some("html") => Format::Html,
+                    Some(other) => return Err(format!("unknown format `{}`", other)),
+                    None => return Err(String::from("missing value for --format")),
+                }
+            }
+            "--private" => options.private = true,
+            "--output" => match args.next() {
+                Some(output) => options.output = PathBuf::from(output),
+                None => return Err(String::from("missing value for --output")),
+            },
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option `{}`", option))
+            }
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err(String::from("no input files"));
+    }
+    Ok(options)
+}
+
+/// Writes the documentation of all modules, returns false if a module
+/// could not be parsed
+fn document(options: &Options) -> Result<bool, Box<dyn Error>> {
+    let mut modules = Vec::new();
+    for path in options.paths.iter() {
+        if path.is_dir() {
+            find_modules(path, path, &mut modules)?;
+        } else {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            modules.push((name.into_owned(), path.clone()));
+        }
+    }
+
+    fs::create_dir_all(&options.output)
+        .map_err(|err| format!("{}: {}", options.output.display(), err))?;
+
+    let mut success = true;
+    let mut documented = Vec::new();
+    for (name, path) in modules.iter() {
+        let source =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let module = if options.private {
+            ModuleDoc::with_private(name, &source)
+        } else {
+            ModuleDoc::new(name, &source)
+        };
+        let module = match module {
+            Ok(module) => module,
+            Err(err) => {
+                eprintln!(
+                    "{}:{}: error: {}",
+                    path.display(),
+                    err.position,
+                    err.message
+                );
+                success = false;
+                continue;
+            }
+        };
+
+        let file = options
+            .output
+            .join(format!("{}.{}", name, options.format.extension()));
+        fs::write(&file, render(&module, options.format))
+            .map_err(|err| format!("{}: {}", file.display(), err))?;
+        documented.push(name.as_str());
+    }
+
+    let index = options
+        .output
+        .join(format!("index.{}", options.format.extension()));
+    fs::write(&index, render_index(&documented, options.format))
+        .map_err(|err| format!("{}: {}", index.display(), err))?;
+    Ok(success)
+}
+
+/// Adds all `.lv` files below the directory in sorted order, named by their
+/// path relative to the root
+fn find_modules(
+    root: &Path,
+    dir: &Path,
+    modules: &mut Vec<(String, PathBuf)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|err| format!("{}: {}", dir.display(), err))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_modules(root, &entry, modules)?;
+        } else if entry.extension().is_some_and(|ext| ext == "lv") {
+            let relative = entry
+                .strip_prefix(root)
+                .unwrap_or(&entry)
+                .with_extension("");
+            let name: Vec<String> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            modules.push((name.join("."), entry));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! The bytecode format
+//!
+//! Every instruction starts with a single byte [`OpCode`], followed by its
+//! operands. Operands are 16 bit wide and stored in big endian order, only
+//! the argument count of [`OpCode::Call`] is a single byte.
+
+use std::any::Any;
+use std::rc::Rc;
+
+use crate::interp::value::{CompiledFunction, Value};
+
+macro_rules! opcodes {
+    ($($(#[$doc:meta])* $name:ident = $operands:expr),* $(,)?) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr(u8)]
+        pub enum OpCode {
+            $($(#[$doc])* $name),*
+        }
+
+        const OPCODES: &[OpCode] = &[$(OpCode::$name),*];
+
+        impl OpCode {
+            /// Width of all operands of the instruction in bytes
+            pub fn operand_width(self) -> usize {
+                match self {
+                    $(OpCode::$name => $operands),*
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    /// Pushes the constant with the given index
+    Constant = 2,
+    Nil = 0,
+    True = 0,
+    False = 0,
+    Pop = 0,
+
+    /// The operand of all global instructions is the constant index of the name
+    DefineGlobal = 2,
+    GetGlobal = 2,
+    SetGlobal = 2,
+
+    /// The operand of all local instructions is the slot relative to the
+    /// start of the call frame
+    GetLocal = 2,
+    SetLocal = 2,
+
+    /// The operand is the constant index of the field name
+    GetField = 2,
+    SetField = 2,
+    GetIndex = 0,
+    SetIndex = 0,
+
+    /// Pops the end and start bound and slices the value below them
+    Slice = 0,
+
+    Add = 0,
+    Sub = 0,
+    Mul = 0,
+    Div = 0,
+    Mod = 0,
+    Pow = 0,
+    Equal = 0,
+    NotEqual = 0,
+    Less = 0,
+    Greater = 0,
+    LessEqual = 0,
+    GreaterEqual = 0,
+    Negate = 0,
+    Plus = 0,
+    Not = 0,
+
+    /// Jumps forward by the given offset
+    Jump = 2,
+
+    /// Pops the condition and jumps forward, if it is falsy
+    JumpIfFalse = 2,
+
+    /// Jumps forward keeping the value if it is falsy, pops it otherwise
+    JumpIfFalseOrPop = 2,
+
+    /// Jumps forward keeping the value if it is truthy, pops it otherwise
+    JumpIfTrueOrPop = 2,
+
+    /// Jumps forward keeping the value if it is not nil, pops it otherwise
+    JumpIfNotNilOrPop = 2,
+
+    /// Jumps forward keeping the value if it is nil
+    JumpIfNil = 2,
+
+    /// Jumps backward by the given offset
+    Loop = 2,
+
+    /// Collects the given number of values into an array
+    Array = 2,
+
+    /// Collects the given number of key value pairs into a map
+    Map = 2,
+
+    /// Replaces the top value by an array of the items to iterate over
+    IterPrepare = 0,
+
+    /// The first operand is the slot of the prepared array, the slot after
+    /// it holds the current index. Pushes the next item or jumps forward by
+    /// the second operand, if all items were visited
+    ForIter = 4,
+
+    /// Calls the value below the given number of arguments
+    Call = 1,
+    Return = 0,
+    Raise = 0,
+}
+
+impl OpCode {
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        OPCODES.get(byte as usize).copied()
+    }
+}
+
+/// A sequence of instructions together with their constants
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value<'static>>,
+
+    /// Source line of every byte in `code`
+    pub lines: Vec<u32>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, line: u32) {
+        self.code.push(byte);
+        self.lines.push(line);
+    }
+
+    pub fn write_op(&mut self, op: OpCode, line: u32) {
+        self.write(op as u8, line);
+    }
+
+    pub fn write_u16(&mut self, value: u16, line: u32) {
+        let [high, low] = value.to_be_bytes();
+        self.write(high, line);
+        self.write(low, line);
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Adds a constant and returns its index. Equal strings and numbers are
+    /// only stored once
+    pub fn add_constant(&mut self, value: Value<'static>) -> usize {
+        let existing = self
+            .constants
+            .iter()
+            .position(|constant| match (constant, &value) {
+                (Value::Str(_), Value::Str(_))
+                | (Value::Int(_), Value::Int(_))
+                | (Value::Float(_), Value::Float(_)) => *constant == value,
+                _ => false,
+            });
+
+        existing.unwrap_or_else(|| {
+            self.constants.push(value);
+            self.constants.len() - 1
+        })
+    }
+}
+
+/// A compiled function
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FunctionProto {
+    pub name: String,
+    pub arity: usize,
+    pub chunk: Chunk,
+}
+
+impl FunctionProto {
+    /// The prototype of a compiled function value
+    pub fn of(function: &Rc<dyn CompiledFunction>) -> Option<Rc<FunctionProto>> {
+        Rc::clone(function).into_any().downcast().ok()
+    }
+}
+
+impl CompiledFunction for FunctionProto {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_opcode_roundtrip() {
+        for op in OPCODES.iter() {
+            assert_eq!(OpCode::from_byte(*op as u8), Some(*op));
+        }
+        assert_eq!(OpCode::from_byte(OPCODES.len() as u8), None);
+    }
+
+    #[test]
+    fn test_constants_are_deduplicated() {
+        let mut chunk = Chunk::default();
+        let a = chunk.add_constant(Value::Str("a".into()));
+        let one = chunk.add_constant(Value::Int(1));
+        assert_eq!(chunk.add_constant(Value::Str("a".into())), a);
+        assert_ne!(chunk.add_constant(Value::Float(1.0)), one);
+        assert_eq!(chunk.constants.len(), 3);
+    }
+}
@@ -0,0 +1,151 @@
+//! Human readable listing of compiled bytecode
+//!
+//! Every instruction is printed on its own line with its offset, the source
+//! line (or `|` if it is the same as the line of the previous instruction),
+//! its name and its operands:
+//!
+//! ```text
+//! == add ==
+//! 0000    2 GetLocal            1
+//! 0003    | GetLocal            2
+//! 0006    | Add
+//! 0007    | Return
+//! ```
+
+use std::fmt::Write;
+
+use crate::compile::chunk::{Chunk, FunctionProto, OpCode};
+use crate::interp::value::Value;
+
+/// Disassembles a function and all functions declared in it
+pub fn disassemble(proto: &FunctionProto) -> String {
+    let mut out = String::new();
+    disassemble_into(&mut out, proto);
+    out
+}
+
+fn disassemble_into(out: &mut String, proto: &FunctionProto) {
+    let _ = writeln!(out, "== {} ==", proto.name);
+
+    let chunk = &proto.chunk;
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        offset = instruction(out, chunk, offset);
+    }
+
+    for constant in chunk.constants.iter() {
+        if let Value::Compiled(function) = constant {
+            if let Some(function) = FunctionProto::of(function) {
+                out.push('\n');
+                disassemble_into(out, &function);
+            }
+        }
+    }
+}
+
+/// Writes a single instruction and returns the offset of the next one
+fn instruction(out: &mut String, chunk: &Chunk, offset: usize) -> usize {
+    let _ = write!(out, "{:04} ", offset);
+    if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+        let _ = write!(out, "   | ");
+    } else {
+        let _ = write!(out, "{:4} ", chunk.lines[offset]);
+    }
+
+    let op = match OpCode::from_byte(chunk.code[offset]) {
+        Some(op) => op,
+        None => {
+            let _ = writeln!(out, "<unknown {}>", chunk.code[offset]);
+            return offset + 1;
+        }
+    };
+    let next = offset + 1 + op.operand_width();
+    let name = format!("{:?}", op);
+
+    match op {
+        OpCode::Constant
+        | OpCode::DefineGlobal
+        | OpCode::GetGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetField
+        | OpCode::SetField => {
+            let idx = chunk.read_u16(offset + 1);
+            let constant = match &chunk.constants[idx as usize] {
+                Value::Str(value) => format!("{:?}", value),
+                value => value.to_string(),
+            };
+            let _ = writeln!(out, "{:<16} {:4} {}", name, idx, constant);
+        }
+        OpCode::GetLocal | OpCode::SetLocal | OpCode::Array | OpCode::Map => {
+            let _ = writeln!(out, "{:<16} {:4}", name, chunk.read_u16(offset + 1));
+        }
+        OpCode::Jump
+        | OpCode::JumpIfFalse
+        | OpCode::JumpIfFalseOrPop
+        | OpCode::JumpIfTrueOrPop
+        | OpCode::JumpIfNotNilOrPop
+        | OpCode::JumpIfNil => {
+            let target = next + chunk.read_u16(offset + 1) as usize;
+            let _ = writeln!(out, "{:<16} {:4} -> {:04}", name, "", target);
+        }
+        OpCode::Loop => {
+            let target = next - chunk.read_u16(offset + 1) as usize;
+            let _ = writeln!(out, "{:<16} {:4} -> {:04}", name, "", target);
+        }
+        OpCode::ForIter => {
+            let slot = chunk.read_u16(offset + 1);
+            let target = next + chunk.read_u16(offset + 3) as usize;
+            let _ = writeln!(out, "{:<16} {:4} -> {:04}", name, slot, target);
+        }
+        OpCode::Call => {
+            let _ = writeln!(out, "{:<16} {:4}", name, chunk.code[offset + 1]);
+        }
+        _ => {
+            let _ = writeln!(out, "{}", name);
+        }
+    }
+
+    next
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::compile::compile;
+    use crate::parser::{parse_source, Span};
+
+    #[test]
+    fn test_disassemble() {
+        let source =
+            "fun add(a, b)\n    return a + b\nend\nwhile True do\n    print(add(1, 2))\nend";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let script = compile(&block).unwrap();
+
+        let expected = "== <script> ==
+0000    1 Constant            0 <function add>
+0003    | DefineGlobal        1 \"add\"
+0006    4 True
+0007    | JumpIfFalse           -> 0030
+0010    5 GetGlobal           2 \"print\"
+0013    | GetGlobal           1 \"add\"
+0016    | Constant            3 1
+0019    | Constant            4 2
+0022    | Call                2
+0024    | Call                1
+0026    | Pop
+0027    | Loop                  -> 0006
+0030    | Nil
+0031    | Return
+
+== add ==
+0000    2 GetLocal            1
+0003    | GetLocal            2
+0006    | Add
+0007    | Return
+0008    | Nil
+0009    | Return
+";
+        assert_eq!(disassemble(&script), expected);
+    }
+}
@@ -0,0 +1,786 @@
+//! Lowers a parsed [`Block`] into bytecode for the stack based [`vm::Vm`]
+//!
+//! ```
+//! use liva_parser::compile::{compile, vm::Vm};
+//! use liva_parser::{parse_source, Span};
+//!
+//! let source = "fun square(x)\n    return x * x\nend\nprint(square(4))\n";
+//! let (_, block) = parse_source(Span::new(source)).unwrap();
+//!
+//! let script = compile(&block).unwrap();
+//! let mut vm = Vm::with_output(Vec::new());
+//! vm.run(script).unwrap();
+//! assert_eq!(vm.output(), b"16\n");
+//! ```
+//!
+//! The VM shares its values and the semantics of all operators with the
+//! [`interp`](crate::interp) module. Declarations at the top level are
+//! globals, everything declared inside of a block or function lives in a
+//! stack slot of the current call frame. Functions can not capture the
+//! variables of enclosing functions and classes, `match`, `try` and imports
+//! are not supported yet.
+
+pub mod chunk;
+pub mod disasm;
+pub mod vm;
+
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::interp::value::Value;
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr},
+    literals::{Collection, Literal, Variable},
+    position::{Position, Spanned},
+    statement::{declaration::function::Function, Block, ReturnStmt, Statement},
+    tokens::{Operator, UnOperator},
+};
+use chunk::{Chunk, FunctionProto, OpCode};
+
+pub use disasm::disassemble;
+
+/// Errors, which prevent a program from being compiled
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompileError {
+    /// A language construct, which has no bytecode representation yet
+    Unsupported {
+        construct: &'static str,
+        position: Option<Position>,
+    },
+
+    /// A function uses a local variable of an enclosing function
+    CapturedVariable {
+        name: String,
+        position: Position,
+    },
+
+    /// The left hand side of an assignment can not be assigned to
+    InvalidTarget {
+        position: Option<Position>,
+    },
+
+    TooManyConstants,
+    TooManyLocals,
+    TooManyArguments,
+    JumpTooLarge,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Unsupported {
+                construct,
+                position: Some(position),
+            } => write!(f, "{}: {} can not be compiled", position, construct),
+            CompileError::Unsupported { construct, .. } => {
+                write!(f, "{} can not be compiled", construct)
+            }
+            CompileError::CapturedVariable { name, position } => write!(
+                f,
+                "{}: `{}` is a variable of an enclosing function",
+                position, name
+            ),
+            CompileError::InvalidTarget {
+                position: Some(position),
+            } => write!(f, "{}: invalid assignment target", position),
+            CompileError::InvalidTarget { .. } => write!(f, "invalid assignment target"),
+            CompileError::TooManyConstants => write!(f, "too many constants in one function"),
+            CompileError::TooManyLocals => write!(f, "too many local variables in one function"),
+            CompileError::TooManyArguments => write!(f, "too many arguments in one call"),
+            CompileError::JumpTooLarge => write!(f, "too much code to jump over"),
+        }
+    }
+}
+
+impl Error for CompileError {}
+
+type Result<T> = std::result::Result<T, CompileError>;
+
+/// Compiles the top level block of a program into a function without
+/// parameters, which can be run by the VM
+pub fn compile(block: &Block) -> Result<Rc<FunctionProto>> {
+    let mut compiler = Compiler {
+        states: vec![FunctionState::new("<script>", 0, 0)],
+        line: 1,
+    };
+
+    compiler.block(block)?;
+    compiler.emit_op(OpCode::Nil);
+    compiler.emit_op(OpCode::Return);
+
+    let state = compiler.states.pop().unwrap();
+    Ok(Rc::new(state.proto))
+}
+
+struct Local<'a> {
+    name: &'a str,
+    depth: usize,
+}
+
+/// The function, which is currently compiled
+struct FunctionState<'a> {
+    proto: FunctionProto,
+    locals: Vec<Local<'a>>,
+    scope_depth: usize,
+}
+
+impl<'a> FunctionState<'a> {
+    fn new(name: &str, arity: usize, scope_depth: usize) -> Self {
+        FunctionState {
+            proto: FunctionProto {
+                name: name.to_string(),
+                arity,
+                chunk: Chunk::default(),
+            },
+            // The first slot holds the called function itself
+            locals: vec![Local {
+                name: "",
+                depth: scope_depth,
+            }],
+            scope_depth,
+        }
+    }
+}
+
+enum Resolved {
+    Local(u16),
+    Global,
+}
+
+struct Compiler<'a> {
+    states: Vec<FunctionState<'a>>,
+
+    /// Line of the statement, which is currently compiled
+    line: u32,
+}
+
+impl<'a> Compiler<'a> {
+    fn state(&mut self) -> &mut FunctionState<'a> {
+        self.states.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().proto.chunk
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        let line = self.line;
+        self.chunk().write_op(op, line);
+    }
+
+    fn emit_op_u16(&mut self, op: OpCode, operand: u16) {
+        let line = self.line;
+        self.chunk().write_op(op, line);
+        self.chunk().write_u16(operand, line);
+    }
+
+    fn constant(&mut self, value: Value<'static>) -> Result<u16> {
+        let idx = self.chunk().add_constant(value);
+        if idx > u16::MAX as usize {
+            return Err(CompileError::TooManyConstants);
+        }
+        Ok(idx as u16)
+    }
+
+    fn name_constant(&mut self, name: &str) -> Result<u16> {
+        self.constant(Value::Str(Rc::from(name)))
+    }
+
+    fn emit_constant(&mut self, value: Value<'static>) -> Result<()> {
+        let idx = self.constant(value)?;
+        self.emit_op_u16(OpCode::Constant, idx);
+        Ok(())
+    }
+
+    /// Emits a jump with a placeholder offset and returns the offset of
+    /// the placeholder, which is patched by [`Compiler::patch_jump`]
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op_u16(op, u16::MAX);
+        self.chunk().code.len() - 2
+    }
+
+    /// Lets the jump at `offset` continue at the end of the current code
+    fn patch_jump(&mut self, offset: usize) -> Result<()> {
+        let chunk = self.chunk();
+        let jump = chunk.code.len() - offset - 2;
+        if jump > u16::MAX as usize {
+            return Err(CompileError::JumpTooLarge);
+        }
+        let [high, low] = (jump as u16).to_be_bytes();
+        chunk.code[offset] = high;
+        chunk.code[offset + 1] = low;
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, start: usize) -> Result<()> {
+        let jump = self.chunk().code.len() + 3 - start;
+        if jump > u16::MAX as usize {
+            return Err(CompileError::JumpTooLarge);
+        }
+        self.emit_op_u16(OpCode::Loop, jump as u16);
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let state = self.state();
+        state.scope_depth -= 1;
+
+        let depth = state.scope_depth;
+        let mut popped = 0;
+        while state.locals.last().is_some_and(|local| local.depth > depth) {
+            state.locals.pop();
+            popped += 1;
+        }
+        for _ in 0..popped {
+            self.emit_op(OpCode::Pop);
+        }
+    }
+
+    /// Declares a local for the value on top of the stack
+    fn add_local(&mut self, name: &'a str) -> Result<u16> {
+        let state = self.state();
+        let slot = state.locals.len();
+        if slot > u16::MAX as usize {
+            return Err(CompileError::TooManyLocals);
+        }
+        let depth = state.scope_depth;
+        state.locals.push(Local { name, depth });
+        Ok(slot as u16)
+    }
+
+    /// Declares a global at the top level and a local everywhere else
+    fn declare_variable(&mut self, name: &'a str) -> Result<()> {
+        if self.state().scope_depth == 0 {
+            let idx = self.name_constant(name)?;
+            self.emit_op_u16(OpCode::DefineGlobal, idx);
+        } else {
+            self.add_local(name)?;
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, variable: &Variable<'a>) -> Result<Resolved> {
+        let find = |state: &FunctionState| {
+            state
+                .locals
+                .iter()
+                .rposition(|local| local.name == variable.value)
+        };
+
+        let (current, enclosing) = self.states.split_last().unwrap();
+        if let Some(slot) = find(current) {
+            return Ok(Resolved::Local(slot as u16));
+        }
+        if enclosing.iter().any(|state| find(state).is_some()) {
+            return Err(CompileError::CapturedVariable {
+                name: variable.value.to_string(),
+                position: variable.pos.into(),
+            });
+        }
+        Ok(Resolved::Global)
+    }
+
+    fn get_variable(&mut self, variable: &Variable<'a>) -> Result<()> {
+        match self.resolve(variable)? {
+            Resolved::Local(slot) => self.emit_op_u16(OpCode::GetLocal, slot),
+            Resolved::Global => {
+                let idx = self.name_constant(variable.value)?;
+                self.emit_op_u16(OpCode::GetGlobal, idx);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_variable(&mut self, variable: &Variable<'a>) -> Result<()> {
+        match self.resolve(variable)? {
+            Resolved::Local(slot) => self.emit_op_u16(OpCode::SetLocal, slot),
+            Resolved::Global => {
+                let idx = self.name_constant(variable.value)?;
+                self.emit_op_u16(OpCode::SetGlobal, idx);
+            }
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block<'a>) -> Result<()> {
+        // Functions are visible in the whole block, like in the interpreter
+        for stmt in block.statements.iter() {
+            if let Statement::Fun(function) = stmt {
+                if let Some(span) = stmt.span() {
+                    self.line = span.location_line();
+                }
+                let proto = self.function(function)?;
+                self.emit_constant(Value::Compiled(Rc::new(proto)))?;
+                self.declare_variable(function.name.value)?;
+            }
+        }
+        for stmt in block.statements.iter() {
+            self.statement(stmt)?;
+        }
+        if let Some(return_stmt) = &block.return_stmt {
+            self.return_stmt(return_stmt)?;
+        }
+        Ok(())
+    }
+
+    fn scoped_block(&mut self, block: &Block<'a>) -> Result<()> {
+        self.begin_scope();
+        self.block(block)?;
+        self.end_scope();
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Statement<'a>) -> Result<()> {
+        if let Some(span) = stmt.span() {
+            self.line = span.location_line();
+        }
+        let unsupported = |construct| {
+            Err(CompileError::Unsupported {
+                construct,
+                position: stmt.span().map(Position::from),
+            })
+        };
+
+        match stmt {
+            Statement::LAssignment(assignment) => {
+                self.expression(&assignment.expression)?;
+                self.declare_variable(assignment.variable.value)?;
+            }
+            Statement::Assignment(assignment) => {
+                self.assignment(&assignment.variable, &assignment.expression)?;
+            }
+            Statement::FuncCall(prefix_expr) => {
+                self.prefix_expr(prefix_expr)?;
+                self.emit_op(OpCode::Pop);
+            }
+            Statement::While(while_stmt) => {
+                let start = self.chunk().code.len();
+                self.expression(&while_stmt.cond)?;
+                let exit = self.emit_jump(OpCode::JumpIfFalse);
+                self.scoped_block(&while_stmt.block)?;
+                self.emit_loop(start)?;
+                self.patch_jump(exit)?;
+            }
+            Statement::For(for_stmt) => {
+                self.begin_scope();
+                self.expression(&for_stmt.iterator)?;
+                self.emit_op(OpCode::IterPrepare);
+                let items = self.add_local("(items)")?;
+                self.emit_constant(Value::Int(0))?;
+                self.add_local("(index)")?;
+
+                let start = self.chunk().code.len();
+                self.emit_op_u16(OpCode::ForIter, items);
+                let exit = self.emit_jump_operand();
+
+                self.begin_scope();
+                self.add_local(for_stmt.iter_item.value)?;
+                self.block(&for_stmt.block)?;
+                self.end_scope();
+
+                self.emit_loop(start)?;
+                self.patch_jump(exit)?;
+                self.end_scope();
+            }
+            Statement::If(if_stmt) => {
+                self.expression(&if_stmt.cond)?;
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.scoped_block(&if_stmt.stmts)?;
+
+                match &if_stmt.else_statements {
+                    Some(else_statements) => {
+                        let else_jump = self.emit_jump(OpCode::Jump);
+                        self.patch_jump(then_jump)?;
+                        self.scoped_block(else_statements)?;
+                        self.patch_jump(else_jump)?;
+                    }
+                    None => self.patch_jump(then_jump)?,
+                }
+            }
+            // Compiled when the block is entered
+            Statement::Fun(_) => {}
+            Statement::Return(return_stmt) => self.return_stmt(return_stmt)?,
+            Statement::Raise(raise) => {
+                self.expression(&raise.value)?;
+                self.emit_op(OpCode::Raise);
+            }
+            Statement::Class(_) => return unsupported("class"),
+            Statement::Match(_) => return unsupported("match"),
+            Statement::Try(_) => return unsupported("try"),
+            Statement::Import(_) => return unsupported("import"),
+        }
+        Ok(())
+    }
+
+    /// The exit offset of [`OpCode::ForIter`] is its second operand, it is
+    /// written as a placeholder like the offset of a jump
+    fn emit_jump_operand(&mut self) -> usize {
+        let line = self.line;
+        self.chunk().write_u16(u16::MAX, line);
+        self.chunk().code.len() - 2
+    }
+
+    fn function(&mut self, function: &Function<'a>) -> Result<FunctionProto> {
+        self.states.push(FunctionState::new(
+            function.name.value,
+            function.parameters.len(),
+            1,
+        ));
+        for parameter in function.parameters.iter() {
+            self.add_local(parameter.value)?;
+        }
+
+        let line = self.line;
+        let result = self.block(&function.block);
+        self.emit_op(OpCode::Nil);
+        self.emit_op(OpCode::Return);
+        self.line = line;
+
+        let state = self.states.pop().unwrap();
+        result.map(|_| state.proto)
+    }
+
+    /// Multiple returned values are returned as an array
+    fn return_stmt(&mut self, return_stmt: &ReturnStmt<'a>) -> Result<()> {
+        if let Some(span) = return_stmt.values.iter().find_map(Spanned::span) {
+            self.line = span.location_line();
+        }
+        match return_stmt.values.as_slice() {
+            [] => self.emit_op(OpCode::Nil),
+            [value] => self.expression(value)?,
+            values => self.collection(values.iter(), OpCode::Array)?,
+        }
+        self.emit_op(OpCode::Return);
+        Ok(())
+    }
+
+    fn assignment(&mut self, target: &PrefixExpr<'a>, value: &Expression<'a>) -> Result<()> {
+        let invalid = || {
+            Err(CompileError::InvalidTarget {
+                position: target.span().map(Position::from),
+            })
+        };
+
+        match target.suffix_chain.split_last() {
+            None => match &target.prefix {
+                ExprOrVarname::Varname(variable) => {
+                    self.expression(value)?;
+                    self.set_variable(variable)
+                }
+                ExprOrVarname::Exp(_) => invalid(),
+            },
+            Some((last, rest)) => {
+                self.chain(&target.prefix, rest)?;
+                match last {
+                    ExprSuffix::TableDot(field) => {
+                        self.expression(value)?;
+                        let idx = self.name_constant(field.value)?;
+                        self.emit_op_u16(OpCode::SetField, idx);
+                    }
+                    ExprSuffix::TableIdx(index) => {
+                        self.expression(index)?;
+                        self.expression(value)?;
+                        self.emit_op(OpCode::SetIndex);
+                    }
+                    _ => return invalid(),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression<'a>) -> Result<()> {
+        match expr {
+            Expression::Literal(literal) => self.literal(literal)?,
+            Expression::Call(call) => {
+                match &call.callee {
+                    Some(callee) => self.get_variable(callee)?,
+                    None => {
+                        return Err(CompileError::Unsupported {
+                            construct: "call without callee",
+                            position: None,
+                        })
+                    }
+                }
+                self.call(&call.args)?;
+            }
+            Expression::BinaryOp(op) => {
+                self.expression(&op.left)?;
+                let short_circuit = match op.op {
+                    Operator::And => Some(OpCode::JumpIfFalseOrPop),
+                    Operator::Or => Some(OpCode::JumpIfTrueOrPop),
+                    _ => None,
+                };
+
+                match short_circuit {
+                    Some(jump) => {
+                        let end = self.emit_jump(jump);
+                        self.expression(&op.right)?;
+                        self.patch_jump(end)?;
+                    }
+                    None => {
+                        self.expression(&op.right)?;
+                        self.emit_op(binary_opcode(&op.op));
+                    }
+                }
+            }
+            Expression::UnaryOp(op) => {
+                self.expression(&op.operand)?;
+                self.emit_op(match op.op {
+                    UnOperator::Add => OpCode::Plus,
+                    UnOperator::Sub => OpCode::Negate,
+                    UnOperator::Not => OpCode::Not,
+                });
+            }
+            Expression::PrefixExpr(prefix_expr) => self.prefix_expr(prefix_expr)?,
+            Expression::Collection(Collection::Array(values)) => {
+                self.collection(values.iter(), OpCode::Array)?
+            }
+            Expression::Collection(Collection::Map(values)) => {
+                // Sorted, so that the bytecode does not depend on the hash order
+                let mut keys: Vec<&String> = values.keys().collect();
+                keys.sort();
+                for key in keys.iter() {
+                    self.emit_constant(Value::Str(Rc::from(key.as_str())))?;
+                    self.expression(&values[*key])?;
+                }
+                self.emit_op_u16(OpCode::Map, len_operand(keys.len())?);
+            }
+            Expression::Conditional(conditional) => {
+                self.expression(&conditional.cond)?;
+                let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.expression(&conditional.then_expr)?;
+                let end = self.emit_jump(OpCode::Jump);
+                self.patch_jump(else_jump)?;
+                self.expression(&conditional.else_expr)?;
+                self.patch_jump(end)?;
+            }
+            Expression::NilCoalesce(coalesce) => {
+                self.expression(&coalesce.left)?;
+                let end = self.emit_jump(OpCode::JumpIfNotNilOrPop);
+                self.expression(&coalesce.right)?;
+                self.patch_jump(end)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Result<()> {
+        match literal {
+            Literal::Nil(_) => self.emit_op(OpCode::Nil),
+            Literal::Boolean(value) if value.value => self.emit_op(OpCode::True),
+            Literal::Boolean(_) => self.emit_op(OpCode::False),
+            Literal::Int(value) => self.emit_constant(Value::Int(value.value as i64))?,
+            Literal::Float(value) => self.emit_constant(Value::Float(value.value))?,
+            Literal::Str(value) => {
+                self.emit_constant(Value::Str(Rc::from(value.value.as_str())))?
+            }
+        }
+        Ok(())
+    }
+
+    fn collection<'b>(
+        &mut self,
+        values: impl ExactSizeIterator<Item = &'b Expression<'a>>,
+        op: OpCode,
+    ) -> Result<()>
+    where
+        'a: 'b,
+    {
+        let len = len_operand(values.len())?;
+        for value in values {
+            self.expression(value)?;
+        }
+        self.emit_op_u16(op, len);
+        Ok(())
+    }
+
+    fn call(&mut self, args: &[Expression<'a>]) -> Result<()> {
+        if args.len() > u8::MAX as usize {
+            return Err(CompileError::TooManyArguments);
+        }
+        for arg in args.iter() {
+            self.expression(arg)?;
+        }
+        let line = self.line;
+        self.chunk().write_op(OpCode::Call, line);
+        self.chunk().write(args.len() as u8, line);
+        Ok(())
+    }
+
+    fn prefix_expr(&mut self, prefix_expr: &PrefixExpr<'a>) -> Result<()> {
+        self.chain(&prefix_expr.prefix, &prefix_expr.suffix_chain)
+    }
+
+    /// A safe suffix on `Nil` jumps to the end of the chain, leaving `Nil`
+    /// as its value
+    fn chain(&mut self, prefix: &ExprOrVarname<'a>, suffixes: &[ExprSuffix<'a>]) -> Result<()> {
+        match prefix {
+            ExprOrVarname::Exp(expr) => self.expression(expr)?,
+            ExprOrVarname::Varname(variable) => self.get_variable(variable)?,
+        }
+
+        let mut nil_jumps = vec![];
+        for suffix in suffixes.iter() {
+            if matches!(
+                suffix,
+                ExprSuffix::SafeTableDot(_)
+                    | ExprSuffix::SafeTableIdx(_)
+                    | ExprSuffix::SafeFuncCall(_)
+            ) {
+                nil_jumps.push(self.emit_jump(OpCode::JumpIfNil));
+            }
+
+            match suffix {
+                ExprSuffix::TableDot(field) | ExprSuffix::SafeTableDot(field) => {
+                    let idx = self.name_constant(field.value)?;
+                    self.emit_op_u16(OpCode::GetField, idx);
+                }
+                ExprSuffix::TableIdx(index) | ExprSuffix::SafeTableIdx(index) => {
+                    self.expression(index)?;
+                    self.emit_op(OpCode::GetIndex);
+                }
+                ExprSuffix::TableSlice(slice) => {
+                    for bound in [&slice.start, &slice.end].iter() {
+                        match bound {
+                            Some(bound) => self.expression(bound)?,
+                            None => self.emit_op(OpCode::Nil),
+                        }
+                    }
+                    self.emit_op(OpCode::Slice);
+                }
+                ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                    if let Some(method) = &call.callee {
+                        let idx = self.name_constant(method.value)?;
+                        self.emit_op_u16(OpCode::GetField, idx);
+                    }
+                    self.call(&call.args)?;
+                }
+            }
+        }
+
+        for jump in nil_jumps {
+            self.patch_jump(jump)?;
+        }
+        Ok(())
+    }
+}
+
+fn len_operand(len: usize) -> Result<u16> {
+    if len > u16::MAX as usize {
+        return Err(CompileError::TooManyConstants);
+    }
+    Ok(len as u16)
+}
+
+fn binary_opcode(op: &Operator) -> OpCode {
+    match op {
+        Operator::Add => OpCode::Add,
+        Operator::Sub => OpCode::Sub,
+        Operator::Mul => OpCode::Mul,
+        Operator::Div => OpCode::Div,
+        Operator::Mod => OpCode::Mod,
+        Operator::Pow => OpCode::Pow,
+        Operator::EQ => OpCode::Equal,
+        Operator::Neq => OpCode::NotEqual,
+        Operator::Lt => OpCode::Less,
+        Operator::Gt => OpCode::Greater,
+        Operator::Leq => OpCode::LessEqual,
+        Operator::Geq => OpCode::GreaterEqual,
+        // Short circuiting operators are compiled to jumps
+        Operator::And | Operator::Or => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    fn compile_source(source: &str) -> Result<Rc<FunctionProto>> {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(
+            rest.fragment().trim(),
+            "",
+            "Source was not parsed completely"
+        );
+        compile(&block)
+    }
+
+    fn ops(chunk: &Chunk) -> Vec<OpCode> {
+        let mut ops = vec![];
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            let op = OpCode::from_byte(chunk.code[offset]).unwrap();
+            ops.push(op);
+            offset += 1 + op.operand_width();
+        }
+        ops
+    }
+
+    #[test]
+    fn test_globals_and_locals() {
+        let script = compile_source("let x = 1\nif x do\n    let y = x\n    y = 2\nend").unwrap();
+        assert_eq!(
+            ops(&script.chunk),
+            vec![
+                OpCode::Constant,
+                OpCode::DefineGlobal,
+                OpCode::GetGlobal,
+                OpCode::JumpIfFalse,
+                OpCode::GetGlobal,
+                OpCode::Constant,
+                OpCode::SetLocal,
+                OpCode::Pop,
+                OpCode::Nil,
+                OpCode::Return
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function() {
+        let script = compile_source("fun add(a, b)\n    return a + b\nend").unwrap();
+        let add = match &script.chunk.constants[0] {
+            Value::Compiled(function) => FunctionProto::of(function).unwrap(),
+            constant => panic!("Expected function, found {:?}", constant),
+        };
+        assert_eq!(add.name, "add");
+        assert_eq!(add.arity, 2);
+        assert_eq!(
+            ops(&add.chunk),
+            vec![
+                OpCode::GetLocal,
+                OpCode::GetLocal,
+                OpCode::Add,
+                OpCode::Return,
+                OpCode::Nil,
+                OpCode::Return
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(
+            compile_source("class A\nend"),
+            Err(CompileError::Unsupported {
+                construct: "class",
+                position: Some(Position::new(1, 7))
+            })
+        );
+        assert_eq!(
+            compile_source("fun f(x)\n    fun g()\n        return x\n    end\nend"),
+            Err(CompileError::CapturedVariable {
+                name: String::from("x"),
+                position: Position::new(3, 16)
+            })
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! The reference VM, which executes compiled bytecode
+
+use std::collections::HashMap;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+
+use crate::compile::chunk::{FunctionProto, OpCode};
+use crate::interp::{
+    binary, get_attribute, get_index, iter_items, set_index, slice_value, unary, value::Value,
+    RuntimeError, MAX_CALL_DEPTH,
+};
+use crate::parser::tokens::{Operator, UnOperator};
+
+struct Frame {
+    proto: Rc<FunctionProto>,
+    ip: usize,
+
+    /// Index of the first stack slot of the frame, which holds the called function
+    base: usize,
+}
+
+pub struct Vm<W: Write = Stdout> {
+    stack: Vec<Value<'static>>,
+    frames: Vec<Frame>,
+    globals: HashMap<Rc<str>, Value<'static>>,
+    output: W,
+}
+
+impl Vm<Stdout> {
+    /// Creates a VM, which prints to stdout
+    pub fn new() -> Self {
+        Vm::with_output(io::stdout())
+    }
+}
+
+impl Default for Vm<Stdout> {
+    fn default() -> Self {
+        Vm::new()
+    }
+}
+
+impl<W: Write> Vm<W> {
+    /// Creates a VM, which writes everything printed to `output`
+    pub fn with_output(output: W) -> Self {
+        let globals = crate::interp::builtins::BUILTINS
+            .iter()
+            .map(|builtin| (Rc::from(builtin.name()), Value::Builtin(*builtin)))
+            .collect();
+
+        Vm {
+            stack: vec![],
+            frames: vec![],
+            globals,
+            output,
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// Looks up a global variable
+    pub fn get(&self, name: &str) -> Option<Value<'static>> {
+        self.globals.get(name).cloned()
+    }
+
+    /// Runs a compiled script. Globals are kept between runs.
+    /// Returns the value of a top level `return`, `Nil` otherwise
+    pub fn run(&mut self, script: Rc<FunctionProto>) -> Result<Value<'static>, RuntimeError> {
+        self.stack.clear();
+        self.frames.clear();
+        self.stack.push(Value::Compiled(script.clone()));
+        self.frames.push(Frame {
+            proto: script,
+            ip: 0,
+            base: 0,
+        });
+
+        let result = self.execute();
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+        }
+        result
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame();
+        let byte = frame.proto.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame();
+        let value = frame.proto.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> Value<'static> {
+        let idx = self.read_u16() as usize;
+        self.frame().proto.chunk.constants[idx].clone()
+    }
+
+    fn read_name(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Value::Str(name) => name,
+            constant => panic!("Expected name constant, found {:?}", constant),
+        }
+    }
+
+    fn pop(&mut self) -> Value<'static> {
+        self.stack.pop().expect("Stack underflow")
+    }
+
+    fn peek(&self) -> &Value<'static> {
+        self.stack.last().expect("Stack underflow")
+    }
+
+    fn jump(&mut self, offset: u16) {
+        self.frame().ip += offset as usize;
+    }
+
+    fn execute(&mut self) -> Result<Value<'static>, RuntimeError> {
+        loop {
+            let byte = self.read_byte();
+            let op = OpCode::from_byte(byte).expect("Invalid opcode");
+
+            match op {
+                OpCode::Constant => {
+                    let constant = self.read_constant();
+                    self.stack.push(constant);
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Bool(true)),
+                OpCode::False => self.stack.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_name();
+                    match self.globals.get(&name) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(RuntimeError::Undefined(name.to_string())),
+                    }
+                }
+                OpCode::SetGlobal => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    match self.globals.get_mut(&name) {
+                        Some(slot) => *slot = value,
+                        None => return Err(RuntimeError::Undefined(name.to_string())),
+                    }
+                }
+                OpCode::GetLocal => {
+                    let slot = self.frame().base + self.read_u16() as usize;
+                    self.stack.push(self.stack[slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = self.frame().base + self.read_u16() as usize;
+                    self.stack[slot] = self.pop();
+                }
+                OpCode::GetField => {
+                    let name = self.read_name();
+                    let object = self.pop();
+                    self.stack.push(get_attribute(&object, &name)?);
+                }
+                OpCode::SetField => {
+                    let name = self.read_name();
+                    let value = self.pop();
+                    match self.pop() {
+                        Value::Map(values) => {
+                            values.borrow_mut().insert(name.to_string(), value);
+                        }
+                        object => {
+                            return Err(RuntimeError::Attribute {
+                                type_name: object.type_name().to_string(),
+                                name: name.to_string(),
+                            })
+                        }
+                    }
+                }
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    self.stack.push(get_index(&object, &index)?);
+                }
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    set_index(&object, &index, value)?;
+                }
+                OpCode::Slice => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let value = self.pop();
+                    self.stack.push(slice_value(&value, &start, &end)?);
+                }
+                OpCode::Add
+                | OpCode::Sub
+                | OpCode::Mul
+                | OpCode::Div
+                | OpCode::Mod
+                | OpCode::Pow
+                | OpCode::Equal
+                | OpCode::NotEqual
+                | OpCode::Less
+                | OpCode::Greater
+                | OpCode::LessEqual
+                | OpCode::GreaterEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(binary(&binary_operator(op), left, right)?);
+                }
+                OpCode::Negate | OpCode::Plus | OpCode::Not => {
+                    let un_op = match op {
+                        OpCode::Negate => UnOperator::Sub,
+                        OpCode::Plus => UnOperator::Add,
+                        _ => UnOperator::Not,
+                    };
+                    let operand = self.pop();
+                    self.stack.push(unary(&un_op, operand)?);
+                }
+                OpCode::Jump => {
+                    let offset = self.read_u16();
+                    self.jump(offset);
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_u16();
+                    if !self.pop().is_truthy() {
+                        self.jump(offset);
+                    }
+                }
+                OpCode::JumpIfFalseOrPop | OpCode::JumpIfTrueOrPop | OpCode::JumpIfNotNilOrPop => {
+                    let offset = self.read_u16();
+                    let value = self.peek();
+                    let keep = match op {
+                        OpCode::JumpIfFalseOrPop => !value.is_truthy(),
+                        OpCode::JumpIfTrueOrPop => value.is_truthy(),
+                        _ => *value != Value::Nil,
+                    };
+                    if keep {
+                        self.jump(offset);
+                    } else {
+                        self.pop();
+                    }
+                }
+                OpCode::JumpIfNil => {
+                    let offset = self.read_u16();
+                    if *self.peek() == Value::Nil {
+                        self.jump(offset);
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = self.read_u16();
+                    self.frame().ip -= offset as usize;
+                }
+                OpCode::Array => {
+                    let len = self.read_u16() as usize;
+                    let values = self.stack.split_off(self.stack.len() - len);
+                    self.stack.push(Value::array(values));
+                }
+                OpCode::Map => {
+                    let len = self.read_u16() as usize;
+                    let pairs = self.stack.split_off(self.stack.len() - 2 * len);
+                    let mut map = HashMap::new();
+                    for pair in pairs.chunks(2) {
+                        map.insert(pair[0].to_string(), pair[1].clone());
+                    }
+                    self.stack.push(Value::map(map));
+                }
+                OpCode::IterPrepare => {
+                    let value = self.pop();
+                    self.stack.push(Value::array(iter_items(value)?));
+                }
+                OpCode::ForIter => {
+                    let slot = self.frame().base + self.read_u16() as usize;
+                    let offset = self.read_u16();
+
+                    let index = match self.stack[slot + 1] {
+                        Value::Int(index) => index as usize,
+                        _ => unreachable!("For loop index is not an int"),
+                    };
+                    let item = match &self.stack[slot] {
+                        Value::Array(items) => items.borrow().get(index).cloned(),
+                        _ => unreachable!("For loop items are not an array"),
+                    };
+
+                    match item {
+                        Some(item) => {
+                            self.stack[slot + 1] = Value::Int(index as i64 + 1);
+                            self.stack.push(item);
+                        }
+                        None => self.jump(offset),
+                    }
+                }
+                OpCode::Call => {
+                    let argc = self.read_byte() as usize;
+                    self.call(argc)?;
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    self.stack.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+                    self.stack.push(result);
+                }
+                OpCode::Raise => {
+                    let value = self.pop();
+                    return Err(RuntimeError::Uncaught(value.to_string()));
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, argc: usize) -> Result<(), RuntimeError> {
+        let base = self.stack.len() - argc - 1;
+        match self.stack[base].clone() {
+            Value::Compiled(function) => {
+                let proto = FunctionProto::of(&function).ok_or_else(|| {
+                    RuntimeError::Type(format!("{} is not callable", function.name()))
+                })?;
+                if proto.arity != argc {
+                    return Err(RuntimeError::Arity {
+                        name: proto.name.clone(),
+                        expected: proto.arity,
+                        found: argc,
+                    });
+                }
+                // The frame of the script is no call
+                if self.frames.len() > MAX_CALL_DEPTH {
+                    return Err(RuntimeError::StackOverflow);
+                }
+                self.frames.push(Frame { proto, ip: 0, base });
+            }
+            Value::Builtin(builtin) => {
+                let args = self.stack.split_off(base + 1);
+                self.pop();
+                let result = builtin.call(&mut self.output, args)?;
+                self.stack.push(result);
+            }
+            value => {
+                return Err(RuntimeError::Type(format!(
+                    "{} is not callable",
+                    value.type_name()
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn binary_operator(op: OpCode) -> Operator {
+    match op {
+        OpCode::Add => Operator::Add,
+        OpCode::Sub => Operator::Sub,
+        OpCode::Mul => Operator::Mul,
+        OpCode::Div => Operator::Div,
+        OpCode::Mod => Operator::Mod,
+        OpCode::Pow => Operator::Pow,
+        OpCode::Equal => Operator::EQ,
+        OpCode::NotEqual => Operator::Neq,
+        OpCode::Less => Operator::Lt,
+        OpCode::Greater => Operator::Gt,
+        OpCode::LessEqual => Operator::Leq,
+        _ => Operator::Geq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::compile::compile;
+    use crate::parser::{parse_source, Span};
+
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(
+            rest.fragment().trim(),
+            "",
+            "Source was not parsed completely"
+        );
+
+        let mut vm = Vm::with_output(Vec::new());
+        vm.run(compile(&block).unwrap())?;
+        Ok(String::from_utf8(vm.output().clone()).unwrap())
+    }
+
+    #[test]
+    fn test_expressions() {
+        let source = "let m = {\"a\": [1, 2, 3]}\nprint(1 + 2 * 3, 7 / 2, m.a[1:], m?.b?[0], m.b ?? 4, Nil and 1, if m.a then 1 else 2)";
+        assert_eq!(run(source).unwrap(), "7 3.5 [2, 3] Nil 4 Nil 1\n");
+    }
+
+    #[test]
+    fn test_loops_and_locals() {
+        let source = "let total = 0\nfor i in range(4) do\n    let double = i * 2\n    total = total + double\nend\nlet i = 3\nwhile i > 0 do\n    i = i - 1\nend\nprint(total, i)";
+        assert_eq!(run(source).unwrap(), "12 0\n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("fun f()\n    return f()\nend\nf()"),
+            Err(RuntimeError::StackOverflow)
+        );
+        assert_eq!(
+            run("fun f(a) end\nf(1, 2)"),
+            Err(RuntimeError::Arity {
+                name: String::from("f"),
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(
+            run("raise \"oops\""),
+            Err(RuntimeError::Uncaught(String::from("oops")))
+        );
+    }
+}
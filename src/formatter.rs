@@ -0,0 +1,926 @@
+//! Formats liva source code in a canonical style
+//!
+//! Blocks are indented by four spaces, operators are surrounded by single
+//! spaces and strings use double quotes. Comments and single blank lines
+//! between statements are kept:
+//!
+//! ```rust
+//! use liva_parser::formatter::format_source;
+//!
+//! let source = "fun add(a, b)\n  // sum\n  let c = a+b\n  return c\nend\n";
+//! assert_eq!(
+//!     format_source(source).unwrap(),
+//!     "fun add(a, b)\n    // sum\n    let c = a + b\n    return c\nend\n"
+//! );
+//! ```
+//!
+//! Comments stay between the same tokens. Arrays and maps with comments
+//! inside are written with one element per line, other expressions with
+//! comments inside can not be formatted and return [`FormatError::Comment`].
+
+use std::error::Error;
+use std::fmt;
+
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr},
+    lexer::{tokenize, Lexeme, TokenKind},
+    literals::{Collection, Literal, Variable},
+    parse_complete,
+    position::{Position, Spanned, TokenSpan},
+    statement::{
+        declaration::{
+            class::Class,
+            function::Function,
+            match_statement::{MatchArm, Pattern},
+        },
+        import::Import,
+        Block, Statement,
+    },
+    Span, SyntaxError,
+};
+
+const INDENT: &str = "    ";
+
+/// Errors of [`format_source`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormatError {
+    /// The source code can not be parsed
+    Syntax(SyntaxError),
+
+    /// A comment would end up between other tokens, like a comment inside of
+    /// an expression, which is written on one line
+    Comment(Position),
+
+    /// The formatted code can not be tokenized, which is a bug of the formatter
+    InvalidOutput(Position),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Syntax(err) => write!(f, "{}", err),
+            FormatError::Comment(position) => {
+                write!(f, "{}: comment can not be kept in place", position)
+            }
+            FormatError::InvalidOutput(position) => {
+                write!(f, "{}: formatted code is invalid", position)
+            }
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+impl From<SyntaxError> for FormatError {
+    fn from(err: SyntaxError) -> Self {
+        FormatError::Syntax(err)
+    }
+}
+
+/// Parses and formats the source code
+pub fn format_source(source: &str) -> Result<String, FormatError> {
+    let block = parse_complete(Span::new(source))?;
+    let tokens = tokenize(source).map_err(|err| SyntaxError {
+        position: err.position,
+        message: format!("unexpected character {:?}", err.found),
+    })?;
+
+    let mut formatter = Formatter::new(source.lines().collect(), &tokens);
+    // The shebang has to stay in the first line, even if the first
+    // statement does not take the comments in front of it
+    if formatter
+        .comments
+        .first()
+        .is_some_and(|c| c.text.starts_with("#!"))
+    {
+        formatter.comments_before(2);
+    }
+    formatter.statements(&block);
+    formatter.remaining_comments();
+
+    check_comments(&tokens, &formatter.out)?;
+    Ok(formatter.out)
+}
+
+/// Checks, that every comment has the same number of code tokens in front of
+/// it in the formatted code as in the source
+fn check_comments(tokens: &[Lexeme], formatted: &str) -> Result<(), FormatError> {
+    let formatted = tokenize(formatted).map_err(|err| FormatError::InvalidOutput(err.position))?;
+    let moved = comment_places(tokens)
+        .zip(comment_places(&formatted))
+        .find(|((_, before), (_, after))| before != after);
+    match moved {
+        Some(((comment, _), _)) => Err(FormatError::Comment(Position::from(comment.span))),
+        None => Ok(()),
+    }
+}
+
+/// Every comment with the number of code tokens in front of it
+fn comment_places<'t, 's>(
+    tokens: &'t [Lexeme<'s>],
+) -> impl Iterator<Item = (&'t Lexeme<'s>, usize)> + 't {
+    let mut code = 0;
+    tokens.iter().filter_map(move |token| {
+        if token.kind == TokenKind::Comment {
+            Some((token, code))
+        } else {
+            code += 1;
+            None
+        }
+    })
+}
+
+/// Formats a syntax tree, which does not have to come from source code, like
+/// a generated or transformed one. The tree is written as it is: there are no
+/// comments or blank lines, and parentheses are only written for
+/// parenthesized expressions of the tree
+pub fn format_block(block: &Block) -> String {
+    let mut formatter = Formatter::new(Vec::new(), &[]);
+    formatter.statements(block);
+    formatter.out
+}
+
+struct Comment<'s> {
+    line: u32,
+    offset: usize,
+    text: &'s str,
+
+    /// If the comment follows code on the same line
+    trailing: bool,
+}
+
+/// An opening `[`, `?[` or `{` and the matching closing bracket
+#[derive(Clone, Copy)]
+struct Bracket {
+    line: u32,
+    close_line: u32,
+    close_offset: usize,
+}
+
+struct Formatter<'s> {
+    lines: Vec<&'s str>,
+    comments: Vec<Comment<'s>>,
+    next_comment: usize,
+
+    /// Source lines of the keywords, which close a block, like `end` or
+    /// `else`. They are written in the same order as in the source, so the
+    /// formatter takes the next one whenever it closes a block
+    closers: Vec<u32>,
+    next_closer: usize,
+
+    /// Square and curly brackets in the order of the opening ones
+    brackets: Vec<Bracket>,
+    next_bracket: usize,
+
+    out: String,
+    depth: usize,
+
+    /// If nothing was written in the current block yet
+    block_start: bool,
+
+    /// Source line of the statement, whose first line is being written
+    head_line: Option<u32>,
+}
+
+impl<'s> Formatter<'s> {
+    fn new(lines: Vec<&'s str>, tokens: &[Lexeme<'s>]) -> Self {
+        let mut comments = Vec::new();
+        let mut closers = Vec::new();
+        let mut brackets: Vec<Option<Bracket>> = Vec::new();
+        let mut open = Vec::new();
+        // A conditional expression has an `else` for every `then`
+        let mut thens = 0;
+        let mut code_line = 0;
+
+        for token in tokens.iter() {
+            let line = token.span.location_line();
+            if token.kind == TokenKind::Comment {
+                comments.push(Comment {
+                    line,
+                    offset: token.span.location_offset(),
+                    text: token.text().trim_end(),
+                    trailing: code_line == line,
+                });
+                continue;
+            }
+            code_line = line;
+
+            match (token.kind, token.text()) {
+                (TokenKind::Keyword, "then") => thens += 1,
+                (TokenKind::Keyword, "else") if thens > 0 => thens -= 1,
+                (TokenKind::Keyword, "end" | "else" | "catch" | "finally") => closers.push(line),
+                (TokenKind::Punctuation, "[" | "?[" | "{") => {
+                    open.push(Some((brackets.len(), line)));
+                    brackets.push(None);
+                }
+                (TokenKind::Punctuation, "(") => open.push(None),
+                (TokenKind::Punctuation, ")" | "]" | "}") => {
+                    if let Some(Some((idx, open_line))) = open.pop() {
+                        brackets[idx] = Some(Bracket {
+                            line: open_line,
+                            close_line: line,
+                            close_offset: token.span.location_offset(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Formatter {
+            lines,
+            comments,
+            next_comment: 0,
+            closers,
+            next_closer: 0,
+            brackets: brackets.into_iter().flatten().collect(),
+            next_bracket: 0,
+            out: String::new(),
+            depth: 0,
+            block_start: true,
+            head_line: None,
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Ends the current line, a trailing comment on the source line of the
+    /// statement is appended
+    fn newline(&mut self) {
+        self.newline_before(usize::MAX);
+    }
+
+    /// Ends the current line like [`Self::newline`], but only appends a
+    /// comment in front of the source offset
+    fn newline_before(&mut self, offset: usize) {
+        if let Some(line) = self.head_line.take() {
+            if let Some(comment) = self.comments.get(self.next_comment) {
+                if comment.trailing && comment.line == line && comment.offset < offset {
+                    self.out.push_str("  ");
+                    self.out.push_str(comment.text);
+                    self.next_comment += 1;
+                }
+            }
+        }
+        self.out.push('\n');
+    }
+
+    fn is_blank(&self, line: u32) -> bool {
+        line >= 2
+            && self
+                .lines
+                .get(line as usize - 2)
+                .is_some_and(|line| line.trim().is_empty())
+    }
+
+    /// Writes a blank line, if there is one in front of the line in the source
+    fn blank_line(&mut self, line: u32) {
+        if !self.block_start && self.is_blank(line) {
+            self.out.push('\n');
+        }
+        self.block_start = false;
+    }
+
+    /// Writes all comments in front of the line on their own lines
+    fn comments_before(&mut self, line: u32) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.line >= line {
+                break;
+            }
+            let (line, text) = (comment.line, comment.text);
+            self.next_comment += 1;
+
+            self.blank_line(line);
+            self.indent();
+            self.out.push_str(text);
+            self.out.push('\n');
+        }
+    }
+
+    fn remaining_comments(&mut self) {
+        self.depth = 0;
+        self.comments_before(u32::MAX);
+    }
+
+    fn statements(&mut self, block: &Block) {
+        for stmt in block.statements.iter() {
+            self.statement(stmt);
+        }
+        if let Some(return_stmt) = &block.return_stmt {
+            self.start(return_stmt.values.iter().find_map(Spanned::span));
+            self.out.push_str("return");
+            self.list(" ", &return_stmt.values);
+            self.newline();
+        }
+    }
+
+    /// Writes a nested block and ends it with `end`, if given. Returns the
+    /// source line of the keyword, which closes the block
+    fn block(&mut self, block: &Block, end: Option<&str>) -> Option<u32> {
+        self.depth += 1;
+        self.block_start = true;
+        self.statements(block);
+        let line = self.block_end();
+        self.depth -= 1;
+        self.block_start = false;
+
+        if let Some(end) = end {
+            self.indent();
+            self.out.push_str(end);
+            self.head_line = line;
+            self.newline();
+        }
+        line
+    }
+
+    /// Writes the comments in front of the keyword, which closes the current
+    /// block, as part of the block. Returns the source line of the keyword
+    fn block_end(&mut self) -> Option<u32> {
+        let line = self.closers.get(self.next_closer).copied();
+        self.next_closer += 1;
+        if let Some(line) = line {
+            self.comments_before(line);
+        }
+        line
+    }
+
+    /// Writes an opening bracket and returns its source position
+    fn open_bracket(&mut self, bracket: &str) -> Option<Bracket> {
+        self.out.push_str(bracket);
+        self.next_bracket += 1;
+        self.brackets.get(self.next_bracket - 1).copied()
+    }
+
+    /// Writes the comments and indentation in front of a statement
+    fn start(&mut self, span: Option<TokenSpan>) {
+        match span.map(Position::from) {
+            Some(position) => {
+                self.comments_before(position.line);
+                self.blank_line(position.line);
+                self.head_line = Some(position.line);
+            }
+            None => self.block_start = false,
+        }
+        self.indent();
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        // The span of a try statement is inside of its block
+        match stmt {
+            Statement::Try(_) => self.start(None),
+            _ => self.start(stmt.span()),
+        }
+
+        match stmt {
+            Statement::Assignment(assignment) => {
+                self.prefix_expr(&assignment.variable);
+                self.out.push_str(" = ");
+                self.expression(&assignment.expression);
+                self.newline();
+            }
+            Statement::LAssignment(assignment) => {
+                if assignment.exported {
+                    self.out.push_str("pub ");
+                }
+                self.out.push_str("let ");
+                self.out.push_str(assignment.variable.value);
+                self.out.push_str(" = ");
+                self.expression(&assignment.expression);
+                self.newline();
+            }
+            Statement::FuncCall(call) => {
+                self.prefix_expr(call);
+                self.newline();
+            }
+            Statement::While(while_stmt) => {
+                self.out.push_str("while ");
+                self.expression(&while_stmt.cond);
+                self.out.push_str(" do");
+                self.newline();
+                self.block(&while_stmt.block, Some("end"));
+            }
+            Statement::For(for_stmt) => {
+                self.out.push_str("for ");
+                self.out.push_str(for_stmt.iter_item.value);
+                self.out.push_str(" in ");
+                self.expression(&for_stmt.iterator);
+                self.out.push_str(" do");
+                self.newline();
+                self.block(&for_stmt.block, Some("end"));
+            }
+            Statement::If(if_stmt) => {
+                self.out.push_str("if ");
+                self.expression(&if_stmt.cond);
+                self.out.push_str(" do");
+                self.newline();
+                match &if_stmt.else_statements {
+                    Some(else_block) => {
+                        self.block(&if_stmt.stmts, Some("else"));
+                        self.block(else_block, Some("end"));
+                    }
+                    None => {
+                        self.block(&if_stmt.stmts, Some("end"));
+                    }
+                }
+            }
+            Statement::Match(match_stmt) => {
+                self.out.push_str("match ");
+                self.expression(&match_stmt.expr);
+                self.out.push_str(" do");
+                self.newline();
+                self.depth += 1;
+                self.block_start = true;
+                for arm in match_stmt.arms.iter() {
+                    self.match_arm(arm);
+                }
+                let line = self.block_end();
+                self.depth -= 1;
+                self.indent();
+                self.out.push_str("end");
+                self.head_line = line;
+                self.newline();
+            }
+            Statement::Try(try_stmt) => {
+                self.out.push_str("try");
+                self.newline();
+                let mut line = self.block(&try_stmt.block, None);
+                if let Some(catch) = &try_stmt.catch {
+                    self.indent();
+                    self.out.push_str("catch ");
+                    if let Some(binding) = &catch.binding {
+                        self.out.push_str(binding.value);
+                        self.out.push(' ');
+                    }
+                    self.out.push_str("do");
+                    self.head_line = line;
+                    self.newline();
+                    line = self.block(&catch.block, None);
+                }
+                if let Some(finally) = &try_stmt.finally {
+                    self.indent();
+                    self.out.push_str("finally");
+                    self.head_line = line;
+                    self.newline();
+                    line = self.block(finally, None);
+                }
+                self.indent();
+                self.out.push_str("end");
+                self.head_line = line;
+                self.newline();
+            }
+            Statement::Fun(function) => self.function(function),
+            Statement::Class(class) => self.class(class),
+            Statement::Return(return_stmt) => {
+                self.out.push_str("return");
+                self.list(" ", &return_stmt.values);
+                self.newline();
+            }
+            Statement::Import(import) => {
+                self.import(import);
+                self.newline();
+            }
+            Statement::Raise(raise) => {
+                self.out.push_str("raise ");
+                self.expression(&raise.value);
+                self.newline();
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        if function.exported {
+            self.out.push_str("pub ");
+        }
+        self.out.push_str("fun ");
+        self.out.push_str(function.name.value);
+        self.out.push('(');
+        self.names(&function.parameters);
+        self.out.push(')');
+        self.newline();
+        self.block(&function.block, Some("end"));
+    }
+
+    fn class(&mut self, class: &Class) {
+        if class.exported {
+            self.out.push_str("pub ");
+        }
+        self.out.push_str("class ");
+        self.out.push_str(class.name.value);
+        self.newline();
+
+        self.depth += 1;
+        self.block_start = true;
+        for method in class.methods.iter() {
+            self.start(Some(method.name.pos));
+            self.function(method);
+        }
+        let line = self.block_end();
+        self.depth -= 1;
+        self.indent();
+        self.out.push_str("end");
+        self.head_line = line;
+        self.newline();
+    }
+
+    fn import(&mut self, import: &Import) {
+        if import.external {
+            self.out.push_str("external ");
+        }
+        let path: Vec<&str> = import.path.iter().map(|name| name.value).collect();
+        if import.items.is_empty() {
+            self.out.push_str("import ");
+            self.out.push_str(&path.join("."));
+        } else {
+            self.out.push_str("from ");
+            self.out.push_str(&path.join("."));
+            self.out.push_str(" import ");
+            self.names(&import.items);
+        }
+        if let Some(alias) = &import.alias {
+            self.out.push_str(" as ");
+            self.out.push_str(alias.value);
+        }
+    }
+
+    fn match_arm(&mut self, arm: &MatchArm) {
+        self.start(arm.pattern.span());
+        self.out.push_str("case ");
+        self.pattern(&arm.pattern);
+        if let Some(guard) = &arm.guard {
+            self.out.push_str(" if ");
+            self.expression(guard);
+        }
+        self.out.push_str(" do");
+        self.newline();
+        self.block(&arm.block, Some("end"));
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal(literal) => self.literal(literal),
+            Pattern::Range(range) => {
+                self.literal(&range.start);
+                self.out
+                    .push_str(if range.inclusive { "..=" } else { ".." });
+                self.literal(&range.end);
+            }
+            Pattern::Wildcard(_) => self.out.push('_'),
+            Pattern::Binding(name) => self.out.push_str(name.value),
+            Pattern::Array(patterns) => {
+                self.open_bracket("[");
+                for (idx, pattern) in patterns.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(pattern);
+                }
+                self.out.push(']');
+            }
+            Pattern::Map(patterns) => {
+                let entries = in_source_order(
+                    patterns
+                        .iter()
+                        .map(|(key, pattern)| (key, pattern, pattern.span())),
+                );
+                self.open_bracket("{");
+                for (idx, (key, pattern)) in entries.into_iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&format!("\"{}\": ", key));
+                    self.pattern(pattern);
+                }
+                self.out.push('}');
+            }
+        }
+    }
+
+    fn names(&mut self, names: &[Variable]) {
+        let names: Vec<&str> = names.iter().map(|name| name.value).collect();
+        self.out.push_str(&names.join(", "));
+    }
+
+    /// Writes the expressions separated by commas, preceded by `prefix` if
+    /// there is at least one
+    fn list(&mut self, prefix: &str, exprs: &[Expression]) {
+        for (idx, expr) in exprs.iter().enumerate() {
+            self.out.push_str(if idx == 0 { prefix } else { ", " });
+            self.expression(expr);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Call(call) => {
+                if let Some(callee) = &call.callee {
+                    self.out.push_str(callee.value);
+                }
+                self.out.push('(');
+                self.list("", &call.args);
+                self.out.push(')');
+            }
+            Expression::BinaryOp(op) => {
+                self.expression(&op.left);
+                self.out.push_str(&format!(" {} ", op.op));
+                self.expression(&op.right);
+            }
+            Expression::UnaryOp(op) => {
+                self.out.push_str(&op.op.to_string());
+                if op.op == crate::parser::tokens::UnOperator::Not {
+                    self.out.push(' ');
+                }
+                self.expression(&op.operand);
+            }
+            Expression::PrefixExpr(prefix_expr) => self.prefix_expr(prefix_expr),
+            Expression::Collection(Collection::Array(values)) => {
+                let bracket = self.open_bracket("[");
+                let entries: Vec<_> = values.iter().map(|value| (None, value)).collect();
+                self.entries(bracket, &entries, ']');
+            }
+            Expression::Collection(Collection::Map(values)) => {
+                let bracket = self.open_bracket("{");
+                let entries: Vec<_> =
+                    in_source_order(values.iter().map(|(key, value)| (key, value, value.span())))
+                        .into_iter()
+                        .map(|(key, value)| (Some(key), value))
+                        .collect();
+                self.entries(bracket, &entries, '}');
+            }
+            Expression::Conditional(conditional) => {
+                self.out.push_str("if ");
+                self.expression(&conditional.cond);
+                self.out.push_str(" then ");
+                self.expression(&conditional.then_expr);
+                self.out.push_str(" else ");
+                self.expression(&conditional.else_expr);
+            }
+            Expression::NilCoalesce(coalesce) => {
+                self.expression(&coalesce.left);
+                self.out.push_str(" ?? ");
+                self.expression(&coalesce.right);
+            }
+        }
+    }
+
+    /// Writes the entries of an array or map and the closing bracket. If there
+    /// are comments inside of the brackets, every entry gets its own line
+    fn entries(
+        &mut self,
+        bracket: Option<Bracket>,
+        entries: &[(Option<&String>, &Expression)],
+        close: char,
+    ) {
+        let next_comment = self.comments.get(self.next_comment);
+        let bracket = bracket.filter(|bracket| {
+            next_comment.is_some_and(|comment| comment.offset < bracket.close_offset)
+        });
+        let bracket = match bracket {
+            Some(bracket) => bracket,
+            None => {
+                for (idx, (key, value)) in entries.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.entry(*key, value);
+                }
+                self.out.push(close);
+                return;
+            }
+        };
+
+        // Trailing comments are only taken up to the next entry
+        let spans: Vec<_> = entries.iter().map(|(_, value)| value.span()).collect();
+        let offset = |idx: usize| match spans.get(idx) {
+            Some(span) => span.map_or(0, |span| span.location_offset()),
+            None => bracket.close_offset,
+        };
+
+        self.head_line = Some(bracket.line);
+        self.newline_before(offset(0));
+        self.depth += 1;
+        for (idx, (key, value)) in entries.iter().enumerate() {
+            let line = spans[idx].map(|span| span.location_line());
+            if let Some(line) = line {
+                self.comments_before(line);
+            }
+            self.indent();
+            self.entry(*key, value);
+            if idx + 1 < entries.len() {
+                self.out.push(',');
+            }
+            // A nested collection ends on the line of its closing bracket
+            if self.head_line.is_none() {
+                self.head_line = line;
+            }
+            self.newline_before(offset(idx + 1));
+        }
+        self.comments_before(bracket.close_line);
+        self.depth -= 1;
+        self.indent();
+        self.out.push(close);
+        self.head_line = Some(bracket.close_line);
+    }
+
+    fn entry(&mut self, key: Option<&String>, value: &Expression) {
+        if let Some(key) = key {
+            self.out.push_str(&format!("\"{}\": ", key));
+        }
+        self.expression(value);
+    }
+
+    fn prefix_expr(&mut self, prefix_expr: &PrefixExpr) {
+        match &prefix_expr.prefix {
+            ExprOrVarname::Varname(name) => self.out.push_str(name.value),
+            ExprOrVarname::Exp(expr) => {
+                self.out.push('(');
+                self.expression(expr);
+                self.out.push(')');
+            }
+        }
+
+        for suffix in prefix_expr.suffix_chain.iter() {
+            match suffix {
+                ExprSuffix::TableDot(name) => {
+                    self.out.push('.');
+                    self.out.push_str(name.value);
+                }
+                ExprSuffix::SafeTableDot(name) => {
+                    self.out.push_str("?.");
+                    self.out.push_str(name.value);
+                }
+                ExprSuffix::TableIdx(index) => {
+                    self.open_bracket("[");
+                    self.expression(index);
+                    self.out.push(']');
+                }
+                ExprSuffix::SafeTableIdx(index) => {
+                    self.open_bracket("?[");
+                    self.expression(index);
+                    self.out.push(']');
+                }
+                ExprSuffix::TableSlice(slice) => {
+                    self.open_bracket("[");
+                    if let Some(start) = &slice.start {
+                        self.expression(start);
+                    }
+                    self.out.push(':');
+                    if let Some(end) = &slice.end {
+                        self.expression(end);
+                    }
+                    self.out.push(']');
+                }
+                ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                    // A safe call needs the `?.` even without a method name
+                    match (suffix, &call.callee) {
+                        (ExprSuffix::SafeFuncCall(_), _) => self.out.push_str("?."),
+                        (_, Some(_)) => self.out.push('.'),
+                        (_, None) => {}
+                    }
+                    if let Some(method) = &call.callee {
+                        self.out.push_str(method.value);
+                    }
+                    self.out.push('(');
+                    self.list("", &call.args);
+                    self.out.push(')');
+                }
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Str(token) => self.out.push_str(&format!("\"{}\"", token.value)),
+            Literal::Boolean(token) => {
+                self.out
+                    .push_str(if token.value { "True" } else { "False" })
+            }
+            Literal::Nil(_) => self.out.push_str("Nil"),
+            // Numbers keep the way they are written
+            Literal::Float(token) => self.out.push_str(token.pos.fragment()),
+            Literal::Int(token) => self.out.push_str(token.pos.fragment()),
+        }
+    }
+}
+
+/// Orders the entries of a map by the position of their values. Entries
+/// without position come last, ordered by their key
+fn in_source_order<'k, T>(
+    entries: impl Iterator<Item = (&'k String, T, Option<TokenSpan<'k>>)>,
+) -> Vec<(&'k String, T)> {
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_by_key(|(key, _, span)| {
+        (
+            span.map_or(usize::MAX, |span| span.location_offset()),
+            key.to_string(),
+        )
+    });
+    entries
+        .into_iter()
+        .map(|(key, value, _)| (key, value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_format_statements() {
+        let source =
+            "let   x=1\nif x==1 do print( x )\nelse\nprint(-x)  end\nwhile x<3 do x=x+1 end\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "let x = 1\nif x == 1 do\n    print(x)\nelse\n    print(-x)\nend\nwhile x < 3 do\n    x = x + 1\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_format_safe_calls() {
+        let source = "x = f?.( 1 )\ny = a?.b(2)?.c\n";
+        assert_eq!(format_source(source).unwrap(), "x = f?.(1)\ny = a?.b(2)?.c\n");
+    }
+
+    #[test]
+    fn test_format_comments_and_blank_lines() {
+        let source = "// header\n\nlet a = 1 // one\n\n\n// about b\nlet b = {'k': a, \"j\": 2}\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "// header\n\nlet a = 1  // one\n\n// about b\nlet b = {\"k\": a, \"j\": 2}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_block_comments_and_shebang() {
+        let source = "#!/usr/bin/env liva\n/* Prints\n   a number */\nprint(1) /* done */";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "#!/usr/bin/env liva\n/* Prints\n   a number */\nprint(1)  /* done */\n"
+        );
+        assert_eq!(
+            format_source("#!/usr/bin/env liva\nreturn").unwrap(),
+            "#!/usr/bin/env liva\nreturn\n"
+        );
+    }
+
+    #[test]
+    fn test_format_block() {
+        let source = "// comment\nlet x = (1 + 2) * y?.(3)\n\nfun f(a)\n    return a\nend\n";
+        let block = parse_complete(Span::new(source)).unwrap();
+        assert_eq!(
+            format_block(&block),
+            "let x = (1 + 2) * y?.(3)\nfun f(a)\n    return a\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_format_is_stable() {
+        let source = "try\n  risky()\ncatch err do\n  raise err\nfinally\n  done()\nend\nmatch x do\n  case 1..=3 if x > 1 do\n    return [1,xs[0:1]]\n  end\n  case _ do\n  end\nend\n";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_syntax_error() {
+        match format_source("let x = 1\nlet = 2") {
+            Err(FormatError::Syntax(err)) => assert_eq!(err.position, Position::new(2, 1)),
+            result => panic!("Expected syntax error, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_format_comments_at_block_end() {
+        let source = "fun f()\n  g()\n  // end of f\nend // f\ntry\n  risky()\n  // try\ncatch err do // catch\n  // nothing\nend\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "fun f()\n    g()\n    // end of f\nend  // f\ntry\n    risky()\n    // try\ncatch err do  // catch\n    // nothing\nend\n"
+        );
+    }
+
+    #[test]
+    fn test_format_comments_in_collections() {
+        let source = "let a = [1, // one\n  2, {\"k\": 3 // three\n  }]\n";
+        assert_eq!(
+            format_source(source).unwrap(),
+            "let a = [\n    1,  // one\n    2,\n    {\n        \"k\": 3  // three\n    }\n]\n"
+        );
+        assert_eq!(
+            format_source("let a = f(1, // one\n  2)\n"),
+            Err(FormatError::Comment(Position::new(1, 14)))
+        );
+    }
+
+    #[test]
+    fn test_check_comments_of_invalid_output() {
+        let tokens = tokenize("x = 1").unwrap();
+        assert_eq!(
+            check_comments(&tokens, "x = \u{2000}1"),
+            Err(FormatError::InvalidOutput(Position::new(1, 5)))
+        );
+    }
+}
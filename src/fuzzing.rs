@@ -0,0 +1,75 @@
+//! Checks shared by the fuzz targets in `fuzz/` and the crash tests
+//!
+//! Not part of the public API, it is only public so the fuzz crate can use it.
+//! The checks return a description of the first violation instead of
+//! panicking, so callers can add where the source came from.
+
+use crate::formatter::{format_source, FormatError};
+use crate::lexer::tokenize;
+use crate::sexp::ToSexp;
+use crate::{parse_complete, Span};
+
+/// The lexemes are in order and only whitespace is left out
+pub fn check_lexer(source: &str) -> Result<(), String> {
+    let lexemes = match tokenize(source) {
+        Ok(lexemes) => lexemes,
+        Err(_) => return Ok(()),
+    };
+
+    let mut end = 0;
+    for lexeme in lexemes.iter() {
+        let start = lexeme.span.location_offset();
+        if !source[end..start].trim().is_empty() {
+            return Err(format!(
+                "lexer skipped {:?} in {:?}",
+                &source[end..start],
+                source
+            ));
+        }
+        end = start + lexeme.text().len();
+        if source[start..end] != *lexeme.text() {
+            return Err(format!(
+                "lexeme {:?} does not match {:?} in {:?}",
+                lexeme.text(),
+                &source[start..end],
+                source
+            ));
+        }
+    }
+    if !source[end..].trim().is_empty() {
+        return Err(format!(
+            "lexer skipped {:?} in {:?}",
+            &source[end..],
+            source
+        ));
+    }
+    Ok(())
+}
+
+/// Formatting a valid program keeps its meaning and formatting again
+/// changes nothing
+pub fn check_roundtrip(source: &str) -> Result<(), String> {
+    let block = match parse_complete(Span::new(source)) {
+        Ok(block) => block,
+        Err(_) => return Ok(()),
+    };
+    let formatted = match format_source(source) {
+        Ok(formatted) => formatted,
+        // Comments inside of expressions can not always be kept in place
+        Err(FormatError::Comment(_)) => return Ok(()),
+        Err(err) => return Err(format!("formatting {:?} failed: {}", source, err)),
+    };
+    let reparsed = parse_complete(Span::new(&formatted))
+        .map_err(|err| format!("reparsing {:?} failed: {}", formatted, err))?;
+    if block.to_sexp() != reparsed.to_sexp() {
+        return Err(format!(
+            "formatting {:?} changed its meaning to {:?}",
+            source, formatted
+        ));
+    }
+    match format_source(&formatted) {
+        Ok(again) if again == formatted => Ok(()),
+        Ok(again) => Err(format!("formatting {:?} again gave {:?}", formatted, again)),
+        Err(err) => Err(format!("formatting {:?} again failed: {}", formatted, err)),
+    }
+}
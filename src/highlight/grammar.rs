@@ -0,0 +1,376 @@
+//! Grammars for editors, which highlight liva by its tokens
+//!
+//! The keywords, operators and builtins are taken from the tables of the
+//! parser, so the grammars never disagree with it. `examples/liva.vim` and
+//! `examples/liva.tmLanguage.json` are generated by these functions.
+
+use std::fmt::Write;
+
+use crate::analysis::scope::BUILTINS;
+use crate::parser::{
+    lexer::{TokenKind, SYMBOLS},
+    tokens::{KEYWORDS, WORD_OPERATORS},
+};
+
+/// Identifiers as matched by both grammars
+const IDENTIFIER: &str = "[A-Za-z_][A-Za-z0-9_]*";
+
+/// A TextMate grammar in JSON, which is understood by VS Code, Sublime Text
+/// and most other editors
+pub fn textmate_grammar() -> String {
+    let operators: Vec<String> = symbols(TokenKind::Operator).map(regex_escape).collect();
+    let punctuation: Vec<String> = symbols(TokenKind::Punctuation).map(regex_escape).collect();
+    let declaration = |keyword: &str, scope: &str| {
+        object(vec![
+            (
+                "match",
+                string(&format!("\\b({})\\s+({})", keyword, IDENTIFIER)),
+            ),
+            (
+                "captures",
+                object(vec![
+                    ("1", object(vec![("name", string("keyword.control.liva"))])),
+                    ("2", object(vec![("name", string(scope))])),
+                ]),
+            ),
+        ])
+    };
+    let quoted = |quote: &str, scope: &str| {
+        object(vec![
+            ("name", string(scope)),
+            ("begin", string(quote)),
+            ("end", string(quote)),
+            (
+                "patterns",
+                Json::Array(vec![object(vec![
+                    ("name", string("constant.character.escape.liva")),
+                    ("match", string("\\\\.")),
+                ])]),
+            ),
+        ])
+    };
+    let include = |rule: &str| object(vec![("include", string(&format!("#{}", rule)))]);
+
+    let grammar = object(vec![
+        ("name", string("Liva")),
+        ("scopeName", string("source.liva")),
+        ("fileTypes", Json::Array(vec![string("lv")])),
+        (
+            "patterns",
+            Json::Array(
+                [
+                    "shebang",
+                    "comments",
+                    "strings",
+                    "numbers",
+                    "constants",
+                    "declarations",
+                    "keywords",
+                    "builtins",
+                    "operators",
+                    "punctuation",
+                ]
+                .iter()
+                .map(|rule| include(rule))
+                .collect(),
+            ),
+        ),
+        (
+            "repository",
+            object(vec![
+                (
+                    "shebang",
+                    object(vec![
+                        ("name", string("comment.line.shebang.liva")),
+                        ("match", string("\\A#!.*$")),
+                    ]),
+                ),
+                (
+                    "comments",
+                    object(vec![(
+                        "patterns",
+                        Json::Array(vec![
+                            object(vec![
+                                ("name", string("comment.line.double-slash.liva")),
+                                ("match", string("//.*$")),
+                            ]),
+                            include("block-comment"),
+                        ]),
+                    )]),
+                ),
+                (
+                    "block-comment",
+                    object(vec![
+                        ("name", string("comment.block.liva")),
+                        ("begin", string("/\\*")),
+                        ("end", string("\\*/")),
+                        ("patterns", Json::Array(vec![include("block-comment")])),
+                    ]),
+                ),
+                (
+                    "strings",
+                    object(vec![(
+                        "patterns",
+                        Json::Array(vec![
+                            quoted("\"", "string.quoted.double.liva"),
+                            quoted("'", "string.quoted.single.liva"),
+                        ]),
+                    )]),
+                ),
+                (
+                    "numbers",
+                    object(vec![
+                        ("name", string("constant.numeric.liva")),
+                        ("match", string("\\b\\d+(\\.\\d+)?\\b")),
+                    ]),
+                ),
+                (
+                    "constants",
+                    object(vec![
+                        ("name", string("constant.language.liva")),
+                        ("match", string("(?i)\\b(true|false|nil)\\b")),
+                    ]),
+                ),
+                (
+                    "declarations",
+                    object(vec![(
+                        "patterns",
+                        Json::Array(vec![
+                            declaration("fun", "entity.name.function.liva"),
+                            declaration("class", "entity.name.type.class.liva"),
+                        ]),
+                    )]),
+                ),
+                (
+                    "keywords",
+                    object(vec![
+                        ("name", string("keyword.control.liva")),
+                        ("match", string(&words(&KEYWORDS))),
+                    ]),
+                ),
+                (
+                    "builtins",
+                    object(vec![
+                        ("name", string("support.function.builtin.liva")),
+                        ("match", string(&words(&BUILTINS))),
+                    ]),
+                ),
+                (
+                    "operators",
+                    object(vec![(
+                        "patterns",
+                        Json::Array(vec![
+                            object(vec![
+                                ("name", string("keyword.operator.word.liva")),
+                                ("match", string(&words(&WORD_OPERATORS))),
+                            ]),
+                            object(vec![
+                                ("name", string("keyword.operator.liva")),
+                                ("match", string(&operators.join("|"))),
+                            ]),
+                        ]),
+                    )]),
+                ),
+                (
+                    "punctuation",
+                    object(vec![
+                        ("name", string("punctuation.liva")),
+                        ("match", string(&punctuation.join("|"))),
+                    ]),
+                ),
+            ]),
+        ),
+    ]);
+
+    let mut out = String::new();
+    grammar.write(&mut out, 0);
+    out.push('\n');
+    out
+}
+
+/// A Vim syntax file, to be placed in `syntax/liva.vim`
+pub fn vim_syntax() -> String {
+    // Very nomagic patterns, only the backslash and a `^` at the start of a
+    // branch are special
+    let operators: Vec<String> = symbols(TokenKind::Operator)
+        .map(|symbol| symbol.replace('\\', "\\\\").replace('^', "\\^"))
+        .collect();
+
+    let mut out = String::from(
+        r#"" Vim syntax file
+" Language: Liva
+" Generated from the token tables of liva_parser, do not edit
+
+if exists("b:current_syntax")
+    finish
+endif
+
+"#,
+    );
+    let _ = writeln!(out, "syn keyword livaKeyword {}", KEYWORDS.join(" "));
+    let _ = writeln!(
+        out,
+        "syn keyword livaOperatorWord {}",
+        WORD_OPERATORS.join(" ")
+    );
+    let _ = writeln!(out, "syn keyword livaBuiltin {}", BUILTINS.join(" "));
+    let _ = writeln!(
+        out,
+        r#"syn match livaOperator "\V{}""#,
+        operators.join("\\|")
+    );
+    let _ = writeln!(
+        out,
+        r#"syn match livaFunction "\(\<fun\s\+\)\@<={}""#,
+        IDENTIFIER
+    );
+    let _ = writeln!(
+        out,
+        r#"syn match livaClass "\(\<class\s\+\)\@<={}""#,
+        IDENTIFIER
+    );
+    out.push_str(
+        r#"syn match livaConstant "\c\<\(true\|false\|nil\)\>"
+syn match livaNumber "\<\d\+\(\.\d\+\)\=\>"
+
+syn match livaEscape +\\.+ contained
+syn region livaString start=+"+ skip=+\\.+ end=+"+ contains=livaEscape
+syn region livaString start=+'+ skip=+\\.+ end=+'+ contains=livaEscape
+
+" Comments are defined last, so they take priority over the operators
+syn keyword livaTodo TODO FIXME XXX contained
+syn match livaComment "//.*$" contains=livaTodo
+syn region livaComment start="/\*" end="\*/" contains=livaComment,livaTodo
+syn match livaComment "\%^#!.*$"
+
+hi def link livaKeyword      Keyword
+hi def link livaOperatorWord Operator
+hi def link livaOperator     Operator
+hi def link livaBuiltin      Function
+hi def link livaFunction     Function
+hi def link livaClass        Type
+hi def link livaConstant     Constant
+hi def link livaNumber       Number
+hi def link livaString       String
+hi def link livaEscape       SpecialChar
+hi def link livaTodo         Todo
+hi def link livaComment      Comment
+
+let b:current_syntax = "liva"
+"#,
+    );
+    out
+}
+
+fn symbols(kind: TokenKind) -> impl Iterator<Item = &'static str> {
+    SYMBOLS
+        .iter()
+        .filter(move |(_, symbol_kind)| *symbol_kind == kind)
+        .map(|(symbol, _)| *symbol)
+}
+
+/// Regular expression matching any of the words
+fn words(words: &[&str]) -> String {
+    format!("\\b({})\\b", words.join("|"))
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Just enough JSON to write the TextMate grammar
+enum Json {
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+fn string(text: &str) -> Json {
+    Json::String(String::from(text))
+}
+
+fn object(members: Vec<(&'static str, Json)>) -> Json {
+    Json::Object(members)
+}
+
+impl Json {
+    /// Writes the value indented by two spaces for every level
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = |out: &mut String, depth: usize| {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+        };
+        match self {
+            Json::String(text) => write_string(out, text),
+            Json::Array(values) => {
+                out.push('[');
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    indent(out, depth + 1);
+                    value.write(out, depth + 1);
+                }
+                indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(members) => {
+                out.push('{');
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    indent(out, depth + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, depth + 1);
+                }
+                indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_textmate_grammar() {
+        let grammar = textmate_grammar();
+        assert!(grammar.contains("\"scopeName\": \"source.liva\""));
+        assert!(grammar.contains("\"match\": \"\\\\b(return|class|end|fun|"));
+        assert!(grammar.contains("\"match\": \"\\\\.\\\\.=|\\\\.\\\\.|\\\\?\\\\?|"));
+        assert!(grammar.contains("\"match\": \"\\\\b(and|or|not)\\\\b\""));
+    }
+
+    #[test]
+    fn test_vim_syntax() {
+        let syntax = vim_syntax();
+        assert!(syntax.contains(&format!("syn keyword livaKeyword {}\n", KEYWORDS.join(" "))));
+        assert!(syntax.contains("syn match livaOperator \"\\V..=\\|..\\|??\\|?.\\|==\\|"));
+        assert!(syntax.ends_with("let b:current_syntax = \"liva\"\n"));
+    }
+}
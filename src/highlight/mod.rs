@@ -0,0 +1,256 @@
+//! Syntax highlighting
+//!
+//! [`grammar`] generates TextMate and Vim grammars, which highlight by the
+//! tokens alone. [`semantic_tokens`] classifies the names of a parsed
+//! program by what they refer to, like the semantic highlighting of a
+//! language server:
+//!
+//! ```rust
+//! use liva_parser::highlight::{semantic_tokens, SemanticKind};
+//! use liva_parser::{parse_source, Span};
+//!
+//! let (_, block) = parse_source(Span::new("fun twice(n)\n    return n * 2\nend")).unwrap();
+//! let kinds: Vec<(&str, SemanticKind)> = semantic_tokens(&block)
+//!     .iter()
+//!     .map(|token| (*token.span.fragment(), token.kind))
+//!     .collect();
+//!
+//! assert_eq!(
+//!     kinds,
+//!     vec![
+//!         ("twice", SemanticKind::Function),
+//!         ("n", SemanticKind::Parameter),
+//!         ("n", SemanticKind::Parameter),
+//!     ]
+//! );
+//! ```
+
+pub mod grammar;
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::analysis::{
+    scope::{analyze, Symbol, SymbolKind},
+    visit::{walk_prefix_expr, walk_statement, Visitor},
+};
+use crate::parser::{
+    expression::{call::Call, ExprSuffix, PrefixExpr},
+    position::TokenSpan,
+    statement::{Block, Statement},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SemanticKind {
+    /// Imported modules and the segments of import paths
+    Namespace,
+    Class,
+    Function,
+    Method,
+    Parameter,
+    Variable,
+    /// Names behind a dot, which are not called
+    Property,
+}
+
+impl SemanticKind {
+    /// All kinds, the index of a kind is its index in this list
+    pub const ALL: [SemanticKind; 7] = [
+        SemanticKind::Namespace,
+        SemanticKind::Class,
+        SemanticKind::Function,
+        SemanticKind::Method,
+        SemanticKind::Parameter,
+        SemanticKind::Variable,
+        SemanticKind::Property,
+    ];
+
+    /// Name of the token type in the language server protocol
+    pub fn name(&self) -> &'static str {
+        match self {
+            SemanticKind::Namespace => "namespace",
+            SemanticKind::Class => "class",
+            SemanticKind::Function => "function",
+            SemanticKind::Method => "method",
+            SemanticKind::Parameter => "parameter",
+            SemanticKind::Variable => "variable",
+            SemanticKind::Property => "property",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        SemanticKind::ALL
+            .iter()
+            .position(|kind| kind == self)
+            .unwrap_or_default()
+    }
+}
+
+/// A classified name
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SemanticToken<'a> {
+    pub span: TokenSpan<'a>,
+    pub kind: SemanticKind,
+
+    /// If the name is declared here
+    pub declaration: bool,
+}
+
+/// Classifies all names of the block ordered by their position. Names which
+/// can not be resolved are left out
+pub fn semantic_tokens<'a>(block: &Block<'a>) -> Vec<SemanticToken<'a>> {
+    let scopes = analyze(block);
+    let mut collector = Collector {
+        tokens: BTreeMap::new(),
+        import_items: HashSet::new(),
+    };
+    collector.visit_block(block);
+    let Collector {
+        mut tokens,
+        import_items,
+    } = collector;
+
+    let kind = |symbol: &Symbol| match symbol.kind {
+        SymbolKind::Builtin | SymbolKind::Function => SemanticKind::Function,
+        SymbolKind::Class => SemanticKind::Class,
+        SymbolKind::Method => SemanticKind::Method,
+        SymbolKind::Parameter => SemanticKind::Parameter,
+        // Selectively imported names can be anything declared in the module
+        SymbolKind::Import => match symbol.declaration {
+            Some(span) if import_items.contains(&span.location_offset()) => SemanticKind::Variable,
+            _ => SemanticKind::Namespace,
+        },
+        SymbolKind::Variable
+        | SymbolKind::LoopItem
+        | SymbolKind::PatternBinding
+        | SymbolKind::CatchBinding => SemanticKind::Variable,
+    };
+
+    for symbol in scopes.symbols.iter() {
+        if let Some(span) = symbol.declaration {
+            tokens.insert(
+                span.location_offset(),
+                SemanticToken {
+                    span,
+                    kind: kind(symbol),
+                    declaration: true,
+                },
+            );
+        }
+    }
+    for reference in scopes.references.iter() {
+        if let Some(symbol) = reference.symbol {
+            let span = reference.name.pos;
+            tokens.insert(
+                span.location_offset(),
+                SemanticToken {
+                    span,
+                    kind: kind(&scopes.symbols[symbol]),
+                    declaration: false,
+                },
+            );
+        }
+    }
+    tokens.into_values().collect()
+}
+
+/// Collects the names which are no symbols of the scope analysis
+struct Collector<'a> {
+    tokens: BTreeMap<usize, SemanticToken<'a>>,
+
+    /// Offsets of the selectively imported names
+    import_items: HashSet<usize>,
+}
+
+impl<'a> Collector<'a> {
+    fn add(&mut self, span: TokenSpan<'a>, kind: SemanticKind) {
+        let token = SemanticToken {
+            span,
+            kind,
+            declaration: false,
+        };
+        self.tokens.insert(span.location_offset(), token);
+    }
+}
+
+impl<'a> Visitor<'a> for Collector<'a> {
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        if let Statement::Import(import) = stmt {
+            for segment in import.path.iter() {
+                self.add(segment.pos, SemanticKind::Namespace);
+            }
+            self.import_items
+                .extend(import.items.iter().map(|item| item.pos.location_offset()));
+        }
+        walk_statement(self, stmt);
+    }
+
+    fn visit_prefix_expr(&mut self, prefix_expr: &PrefixExpr<'a>) {
+        for suffix in prefix_expr.suffix_chain.iter() {
+            match suffix {
+                ExprSuffix::TableDot(name) | ExprSuffix::SafeTableDot(name) => {
+                    self.add(name.pos, SemanticKind::Property)
+                }
+                ExprSuffix::FuncCall(Call {
+                    callee: Some(name), ..
+                })
+                | ExprSuffix::SafeFuncCall(Call {
+                    callee: Some(name), ..
+                }) => self.add(name.pos, SemanticKind::Method),
+                _ => {}
+            }
+        }
+        walk_prefix_expr(self, prefix_expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    #[test]
+    fn test_semantic_tokens() {
+        let source = "import lib.geometry as geo
+from util import double
+
+class Point
+    fun norm(self)
+        return self.x
+    end
+end
+
+let p = geo.point(1, 2)
+print(double(p.norm()), undefined)
+";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let tokens: Vec<(&str, SemanticKind, bool)> = semantic_tokens(&block)
+            .iter()
+            .map(|token| (*token.span.fragment(), token.kind, token.declaration))
+            .collect();
+
+        use SemanticKind::*;
+        assert_eq!(
+            tokens,
+            vec![
+                ("lib", Namespace, false),
+                ("geometry", Namespace, false),
+                ("geo", Namespace, true),
+                ("util", Namespace, false),
+                ("double", Variable, true),
+                ("Point", Class, true),
+                ("norm", Method, true),
+                ("self", Parameter, true),
+                ("self", Parameter, false),
+                ("x", Property, false),
+                ("p", Variable, true),
+                ("geo", Namespace, false),
+                ("point", Method, false),
+                ("print", Function, false),
+                ("double", Variable, false),
+                ("p", Variable, false),
+                ("norm", Method, false),
+            ]
+        );
+    }
+}
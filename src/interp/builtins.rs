@@ -0,0 +1,87 @@
+//! Functions, which are available in every program
+
+use std::io::Write;
+
+use crate::interp::{value::Value, RuntimeError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Builtin {
+    /// `print(a, b, ...)` writes all arguments seperated by spaces
+    Print,
+
+    /// `len(x)` returns the length of a string, array or map
+    Len,
+
+    /// `range(end)` or `range(start, end)` returns an array of integers
+    Range,
+}
+
+pub const BUILTINS: [Builtin; 3] = [Builtin::Print, Builtin::Len, Builtin::Range];
+
+impl Builtin {
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Len => "len",
+            Builtin::Range => "range",
+        }
+    }
+
+    pub(crate) fn call<'a, W: Write>(
+        self,
+        output: &mut W,
+        args: Vec<Value<'a>>,
+    ) -> Result<Value<'a>, RuntimeError> {
+        match self {
+            Builtin::Print => {
+                let line: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                writeln!(output, "{}", line.join(" "))
+                    .map_err(|err| RuntimeError::Io(err.to_string()))?;
+                Ok(Value::Nil)
+            }
+            Builtin::Len => {
+                let arg = single_arg(self, args)?;
+                let len = match &arg {
+                    Value::Str(value) => value.chars().count(),
+                    Value::Array(values) => values.borrow().len(),
+                    Value::Map(values) => values.borrow().len(),
+                    value => {
+                        return Err(RuntimeError::Type(format!(
+                            "{} has no length",
+                            value.type_name()
+                        )))
+                    }
+                };
+                Ok(Value::Int(len as i64))
+            }
+            Builtin::Range => {
+                let (start, end) = match args.as_slice() {
+                    [Value::Int(end)] => (0, *end),
+                    [Value::Int(start), Value::Int(end)] => (*start, *end),
+                    [_] | [_, _] => {
+                        return Err(RuntimeError::Type(String::from(
+                            "range expects integer bounds",
+                        )))
+                    }
+                    _ => return Err(arity(self, 2, args.len())),
+                };
+                Ok(Value::array((start..end).map(Value::Int).collect()))
+            }
+        }
+    }
+}
+
+fn single_arg(builtin: Builtin, mut args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(arity(builtin, 1, args.len()));
+    }
+    Ok(args.remove(0))
+}
+
+fn arity(builtin: Builtin, expected: usize, found: usize) -> RuntimeError {
+    RuntimeError::Arity {
+        name: builtin.name().to_string(),
+        expected,
+        found,
+    }
+}
@@ -0,0 +1,61 @@
+//! Lexical environments, which map names to values
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interp::value::Value;
+
+/// A shared handle to a scope. Closures keep the scope they were declared
+/// in alive, so that they can still access its variables later on
+pub type Env<'a> = Rc<RefCell<Scope<'a>>>;
+
+#[derive(Debug, Default)]
+pub struct Scope<'a> {
+    values: HashMap<&'a str, Value<'a>>,
+    parent: Option<Env<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    /// Creates a new environment without a parent
+    pub fn root() -> Env<'a> {
+        Rc::new(RefCell::new(Scope::default()))
+    }
+
+    /// Creates a new environment nested in `parent`
+    pub fn child(parent: &Env<'a>) -> Env<'a> {
+        Rc::new(RefCell::new(Scope {
+            values: HashMap::new(),
+            parent: Some(Rc::clone(parent)),
+        }))
+    }
+
+    /// Declares a name in this scope, hiding any declaration of the
+    /// enclosing scopes
+    pub fn define(&mut self, name: &'a str, value: Value<'a>) {
+        self.values.insert(name, value);
+    }
+
+    /// Looks up a name in this scope and all enclosing scopes
+    pub fn get(&self, name: &str) -> Option<Value<'a>> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref().and_then(|p| p.borrow().get(name)),
+        }
+    }
+
+    /// Overwrites the value of an already declared name. Returns false,
+    /// if the name is not declared in any scope
+    pub fn assign(&mut self, name: &str, value: Value<'a>) -> bool {
+        match self.values.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => match &self.parent {
+                Some(parent) => parent.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+}
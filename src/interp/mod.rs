@@ -0,0 +1,1045 @@
+//! A tree-walking interpreter, which evaluates a parsed [`Block`] directly
+//!
+//! ```
+//! use liva_parser::{interp::Interpreter, parse_source, Span};
+//!
+//! let source = "fun square(x)\n    return x * x\nend\nprint(square(4))\n";
+//! let (_, block) = parse_source(Span::new(source)).unwrap();
+//!
+//! let mut interpreter = Interpreter::with_output(Vec::new());
+//! interpreter.run(&block).unwrap();
+//! assert_eq!(interpreter.output(), b"16\n");
+//! ```
+//!
+//! Integers are 64 bit wide and overflowing them is an error. `/` always
+//! produces a float, all other arithmetic operators keep integers as integers.
+//! Only `Nil` and `False` are falsy.
+
+pub mod builtins;
+pub mod env;
+pub mod value;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr, Slice},
+    literals::{Collection, Literal},
+    statement::{
+        declaration::match_statement::{Pattern, RangePattern},
+        Block, ReturnStmt, Statement,
+    },
+    tokens::{Operator, UnOperator},
+};
+use builtins::BUILTINS;
+use env::{Env, Scope};
+use value::{ClassValue, Closure, Instance, Value};
+
+/// Maximum number of nested function calls of the interpreter and the VM,
+/// deeper recursion is reported as [`RuntimeError::StackOverflow`]
+pub const MAX_CALL_DEPTH: usize = 4096;
+
+/// The interpreter recurses natively for every call. Before a call, the stack
+/// is grown by [`STACK_GROWTH`] bytes, if less than [`STACK_RED_ZONE`] bytes
+/// are left, so deep recursion does not depend on the size of the thread stack
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
+
+/// Errors, which abort the execution of a program
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeError {
+    /// A name was used, which is not declared in any scope
+    Undefined(String),
+
+    /// An operation was applied to a value of the wrong type
+    Type(String),
+
+    /// A field or method does not exist on a value
+    Attribute {
+        type_name: String,
+        name: String,
+    },
+
+    /// A function was called with the wrong number of arguments
+    Arity {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+
+    /// An array or string was indexed outside of its bounds
+    Index {
+        index: i64,
+        len: usize,
+    },
+
+    DivisionByZero,
+    Overflow,
+    StackOverflow,
+
+    /// The left hand side of an assignment can not be assigned to
+    InvalidTarget,
+
+    /// A statement, which is not supported by the interpreter
+    Unsupported(String),
+
+    /// A raised value, which was not caught by any `try`
+    Uncaught(String),
+
+    /// Writing the output failed
+    Io(String),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::Undefined(name) => write!(f, "`{}` is not defined", name),
+            RuntimeError::Type(message) => write!(f, "type error: {}", message),
+            RuntimeError::Attribute { type_name, name } => {
+                write!(f, "{} has no attribute `{}`", type_name, name)
+            }
+            RuntimeError::Arity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` expects {} arguments, but got {}",
+                name, expected, found
+            ),
+            RuntimeError::Index { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::Overflow => write!(f, "integer overflow"),
+            RuntimeError::StackOverflow => {
+                write!(f, "maximum call depth of {} exceeded", MAX_CALL_DEPTH)
+            }
+            RuntimeError::InvalidTarget => write!(f, "invalid assignment target"),
+            RuntimeError::Unsupported(what) => write!(f, "{} is not supported", what),
+            RuntimeError::Uncaught(value) => write!(f, "uncaught error: {}", value),
+            RuntimeError::Io(message) => write!(f, "could not write output: {}", message),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+/// Everything, which leaves a block early
+#[derive(Debug)]
+enum Unwind<'a> {
+    Return(Value<'a>),
+    Raise(Value<'a>),
+    Error(RuntimeError),
+}
+
+impl<'a> From<RuntimeError> for Unwind<'a> {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+type Exec<'a, T> = Result<T, Unwind<'a>>;
+
+pub struct Interpreter<'a, W: Write = Stdout> {
+    globals: Env<'a>,
+    output: W,
+    depth: usize,
+}
+
+impl<'a> Interpreter<'a, Stdout> {
+    /// Creates an interpreter, which prints to stdout
+    pub fn new() -> Self {
+        Interpreter::with_output(io::stdout())
+    }
+}
+
+impl<'a> Default for Interpreter<'a, Stdout> {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    /// Creates an interpreter, which writes everything printed to `output`
+    pub fn with_output(output: W) -> Self {
+        let globals = Scope::root();
+        for builtin in BUILTINS.iter() {
+            globals
+                .borrow_mut()
+                .define(builtin.name(), Value::Builtin(*builtin));
+        }
+
+        Interpreter {
+            globals,
+            output,
+            depth: 0,
+        }
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// Executes a top level block. Declarations are kept, so the block can
+    /// be followed up by further calls to `run` or [`Interpreter::call`].
+    /// Returns the value of a top level `return`, `Nil` otherwise
+    pub fn run(&mut self, block: &Block<'a>) -> Result<Value<'a>, RuntimeError> {
+        let globals = Rc::clone(&self.globals);
+        match self.exec_block(block, &globals) {
+            Ok(()) => Ok(Value::Nil),
+            Err(unwind) => Self::finish(unwind),
+        }
+    }
+
+    /// Looks up a global variable
+    pub fn get(&self, name: &str) -> Option<Value<'a>> {
+        self.globals.borrow().get(name)
+    }
+
+    /// Calls a function, builtin, method or class with the given arguments
+    pub fn call(
+        &mut self,
+        callee: &Value<'a>,
+        args: Vec<Value<'a>>,
+    ) -> Result<Value<'a>, RuntimeError> {
+        self.call_value(callee, args).or_else(Self::finish)
+    }
+
+    fn finish(unwind: Unwind<'a>) -> Result<Value<'a>, RuntimeError> {
+        match unwind {
+            Unwind::Return(value) => Ok(value),
+            Unwind::Raise(value) => Err(RuntimeError::Uncaught(value.to_string())),
+            Unwind::Error(err) => Err(err),
+        }
+    }
+
+    fn exec_block(&mut self, block: &Block<'a>, env: &Env<'a>) -> Exec<'a, ()> {
+        self.define_declarations(block, env);
+        for stmt in block.statements.iter() {
+            self.exec_statement(stmt, env)?;
+        }
+        match &block.return_stmt {
+            Some(return_stmt) => Err(Unwind::Return(self.eval_return(return_stmt, env)?)),
+            None => Ok(()),
+        }
+    }
+
+    /// Functions and classes are visible in the whole block, the same as in
+    /// the scope analysis, so they are defined before its first statement
+    fn define_declarations(&mut self, block: &Block<'a>, env: &Env<'a>) {
+        for stmt in block.statements.iter() {
+            match stmt {
+                Statement::Fun(function) => {
+                    let closure = Closure {
+                        function: function.clone(),
+                        env: Rc::clone(env),
+                    };
+                    env.borrow_mut()
+                        .define(function.name.value, Value::Function(Rc::new(closure)));
+                }
+                Statement::Class(class) => {
+                    // Methods do not see the class scope, the same as in the
+                    // scope analysis
+                    let methods = class
+                        .methods
+                        .iter()
+                        .map(|method| {
+                            let closure = Closure {
+                                function: method.clone(),
+                                env: Rc::clone(env),
+                            };
+                            (method.name.value, Rc::new(closure))
+                        })
+                        .collect();
+                    let class_value = ClassValue {
+                        name: class.name.value,
+                        methods,
+                    };
+                    env.borrow_mut()
+                        .define(class.name.value, Value::Class(Rc::new(class_value)));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn exec_statement(&mut self, stmt: &Statement<'a>, env: &Env<'a>) -> Exec<'a, ()> {
+        match stmt {
+            Statement::LAssignment(assignment) => {
+                let value = self.eval(&assignment.expression, env)?;
+                env.borrow_mut().define(assignment.variable.value, value);
+            }
+            Statement::Assignment(assignment) => {
+                let value = self.eval(&assignment.expression, env)?;
+                self.assign(&assignment.variable, value, env)?;
+            }
+            Statement::FuncCall(prefix_expr) => {
+                self.eval_prefix(prefix_expr, env)?;
+            }
+            Statement::While(while_stmt) => {
+                while self.eval(&while_stmt.cond, env)?.is_truthy() {
+                    self.exec_block(&while_stmt.block, &Scope::child(env))?;
+                }
+            }
+            Statement::For(for_stmt) => {
+                let items = iter_items(self.eval(&for_stmt.iterator, env)?)?;
+
+                for item in items {
+                    let scope = Scope::child(env);
+                    scope.borrow_mut().define(for_stmt.iter_item.value, item);
+                    self.exec_block(&for_stmt.block, &scope)?;
+                }
+            }
+            Statement::If(if_stmt) => {
+                if self.eval(&if_stmt.cond, env)?.is_truthy() {
+                    self.exec_block(&if_stmt.stmts, &Scope::child(env))?;
+                } else if let Some(else_statements) = &if_stmt.else_statements {
+                    self.exec_block(else_statements, &Scope::child(env))?;
+                }
+            }
+            Statement::Match(match_stmt) => {
+                let value = self.eval(&match_stmt.expr, env)?;
+                for arm in match_stmt.arms.iter() {
+                    let mut bindings = vec![];
+                    if !matches_pattern(&arm.pattern, &value, &mut bindings) {
+                        continue;
+                    }
+
+                    let scope = Scope::child(env);
+                    for (name, value) in bindings {
+                        scope.borrow_mut().define(name, value);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        if !self.eval(guard, &scope)?.is_truthy() {
+                            continue;
+                        }
+                    }
+                    return self.exec_block(&arm.block, &scope);
+                }
+            }
+            Statement::Try(try_stmt) => {
+                let result = self.exec_block(&try_stmt.block, &Scope::child(env));
+                let raised = match &result {
+                    Err(Unwind::Raise(value)) => Some(value.clone()),
+                    Err(Unwind::Error(err)) => Some(Value::Str(Rc::from(err.to_string()))),
+                    _ => None,
+                };
+
+                let result = match (raised, &try_stmt.catch) {
+                    (Some(value), Some(catch)) => {
+                        let scope = Scope::child(env);
+                        if let Some(binding) = catch.binding {
+                            scope.borrow_mut().define(binding.value, value);
+                        }
+                        self.exec_block(&catch.block, &scope)
+                    }
+                    _ => result,
+                };
+
+                if let Some(finally) = &try_stmt.finally {
+                    self.exec_block(finally, &Scope::child(env))?;
+                }
+                return result;
+            }
+            // Defined when the block is entered
+            Statement::Fun(_) | Statement::Class(_) => {}
+            Statement::Return(return_stmt) => {
+                return Err(Unwind::Return(self.eval_return(return_stmt, env)?));
+            }
+            Statement::Raise(raise) => return Err(Unwind::Raise(self.eval(&raise.value, env)?)),
+            Statement::Import(_) => {
+                return Err(RuntimeError::Unsupported(String::from("import")).into())
+            }
+        }
+        Ok(())
+    }
+
+    /// Multiple returned values are returned as an array
+    fn eval_return(&mut self, return_stmt: &ReturnStmt<'a>, env: &Env<'a>) -> Exec<'a, Value<'a>> {
+        let mut values = return_stmt
+            .values
+            .iter()
+            .map(|value| self.eval(value, env))
+            .collect::<Exec<Vec<Value>>>()?;
+
+        Ok(match values.len() {
+            0 => Value::Nil,
+            1 => values.remove(0),
+            _ => Value::array(values),
+        })
+    }
+
+    fn assign(&mut self, target: &PrefixExpr<'a>, value: Value<'a>, env: &Env<'a>) -> Exec<'a, ()> {
+        match target.suffix_chain.split_last() {
+            None => match &target.prefix {
+                ExprOrVarname::Varname(name) => {
+                    if env.borrow_mut().assign(name.value, value) {
+                        Ok(())
+                    } else {
+                        Err(RuntimeError::Undefined(name.value.to_string()).into())
+                    }
+                }
+                ExprOrVarname::Exp(_) => Err(RuntimeError::InvalidTarget.into()),
+            },
+            Some((last, rest)) => {
+                let object = self.eval_chain(&target.prefix, rest, env)?;
+                match last {
+                    ExprSuffix::TableDot(field) => set_field(&object, field.value, value),
+                    ExprSuffix::TableIdx(index) => {
+                        let index = self.eval(index, env)?;
+                        set_index(&object, &index, value)
+                    }
+                    _ => Err(RuntimeError::InvalidTarget),
+                }
+                .map_err(Unwind::from)
+            }
+        }
+    }
+
+    fn eval(&mut self, expr: &Expression<'a>, env: &Env<'a>) -> Exec<'a, Value<'a>> {
+        match expr {
+            Expression::Literal(literal) => Ok(literal_value(literal)),
+            Expression::Call(call) => {
+                let callee = match call.callee {
+                    Some(name) => lookup(name.value, env)?,
+                    None => return Err(type_error(String::from("call without callee"))),
+                };
+                let args = self.eval_args(&call.args, env)?;
+                self.call_value(&callee, args)
+            }
+            Expression::BinaryOp(op) => {
+                let left = self.eval(&op.left, env)?;
+                match op.op {
+                    Operator::And if !left.is_truthy() => Ok(left),
+                    Operator::Or if left.is_truthy() => Ok(left),
+                    Operator::And | Operator::Or => self.eval(&op.right, env),
+                    _ => {
+                        let right = self.eval(&op.right, env)?;
+                        binary(&op.op, left, right).map_err(Unwind::from)
+                    }
+                }
+            }
+            Expression::UnaryOp(op) => {
+                let operand = self.eval(&op.operand, env)?;
+                unary(&op.op, operand).map_err(Unwind::from)
+            }
+            Expression::PrefixExpr(prefix_expr) => self.eval_prefix(prefix_expr, env),
+            Expression::Collection(Collection::Array(values)) => {
+                Ok(Value::array(self.eval_args(values, env)?))
+            }
+            Expression::Collection(Collection::Map(values)) => {
+                let mut map = HashMap::new();
+                for (key, value) in values.iter() {
+                    map.insert(key.clone(), self.eval(value, env)?);
+                }
+                Ok(Value::map(map))
+            }
+            Expression::Conditional(conditional) => {
+                if self.eval(&conditional.cond, env)?.is_truthy() {
+                    self.eval(&conditional.then_expr, env)
+                } else {
+                    self.eval(&conditional.else_expr, env)
+                }
+            }
+            Expression::NilCoalesce(coalesce) => match self.eval(&coalesce.left, env)? {
+                Value::Nil => self.eval(&coalesce.right, env),
+                value => Ok(value),
+            },
+        }
+    }
+
+    fn eval_args(&mut self, args: &[Expression<'a>], env: &Env<'a>) -> Exec<'a, Vec<Value<'a>>> {
+        args.iter().map(|arg| self.eval(arg, env)).collect()
+    }
+
+    fn eval_prefix(&mut self, prefix_expr: &PrefixExpr<'a>, env: &Env<'a>) -> Exec<'a, Value<'a>> {
+        self.eval_chain(&prefix_expr.prefix, &prefix_expr.suffix_chain, env)
+    }
+
+    /// Applies all suffixes from left to right. A safe suffix on `Nil`
+    /// short-circuits the remaining chain to `Nil`
+    fn eval_chain(
+        &mut self,
+        prefix: &ExprOrVarname<'a>,
+        suffixes: &[ExprSuffix<'a>],
+        env: &Env<'a>,
+    ) -> Exec<'a, Value<'a>> {
+        let mut value = match prefix {
+            ExprOrVarname::Exp(expr) => self.eval(expr, env)?,
+            ExprOrVarname::Varname(name) => lookup(name.value, env)?,
+        };
+
+        for suffix in suffixes.iter() {
+            value = match suffix {
+                ExprSuffix::SafeTableDot(_)
+                | ExprSuffix::SafeTableIdx(_)
+                | ExprSuffix::SafeFuncCall(_)
+                    if value == Value::Nil =>
+                {
+                    return Ok(Value::Nil)
+                }
+                ExprSuffix::TableDot(field) | ExprSuffix::SafeTableDot(field) => {
+                    get_attribute(&value, field.value)?
+                }
+                ExprSuffix::TableIdx(index) | ExprSuffix::SafeTableIdx(index) => {
+                    let index = self.eval(index, env)?;
+                    get_index(&value, &index)?
+                }
+                ExprSuffix::TableSlice(slice) => self.eval_slice(&value, slice, env)?,
+                ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                    let callee = match call.callee {
+                        Some(method) => get_attribute(&value, method.value)?,
+                        None => value,
+                    };
+                    let args = self.eval_args(&call.args, env)?;
+                    self.call_value(&callee, args)?
+                }
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn eval_slice(
+        &mut self,
+        value: &Value<'a>,
+        slice: &Slice<'a>,
+        env: &Env<'a>,
+    ) -> Exec<'a, Value<'a>> {
+        let start = match &slice.start {
+            Some(start) => self.eval(start, env)?,
+            None => Value::Nil,
+        };
+        let end = match &slice.end {
+            Some(end) => self.eval(end, env)?,
+            None => Value::Nil,
+        };
+        Ok(slice_value(value, &start, &end)?)
+    }
+
+    fn call_value(&mut self, callee: &Value<'a>, mut args: Vec<Value<'a>>) -> Exec<'a, Value<'a>> {
+        match callee {
+            Value::Builtin(builtin) => Ok(builtin.call(&mut self.output, args)?),
+            Value::Function(closure) => self.call_closure(closure, args),
+            Value::BoundMethod(instance, closure) => {
+                args.insert(0, Value::Instance(Rc::clone(instance)));
+                self.call_closure(closure, args)
+            }
+            Value::Class(class) => {
+                let instance = Rc::new(Instance {
+                    class: Rc::clone(class),
+                    fields: Default::default(),
+                });
+
+                match class.methods.get("init") {
+                    Some(init) => {
+                        args.insert(0, Value::Instance(Rc::clone(&instance)));
+                        self.call_closure(init, args)?;
+                    }
+                    None if !args.is_empty() => {
+                        return Err(RuntimeError::Arity {
+                            name: class.name.to_string(),
+                            expected: 0,
+                            found: args.len(),
+                        }
+                        .into())
+                    }
+                    None => {}
+                }
+                Ok(Value::Instance(instance))
+            }
+            value => Err(type_error(format!("{} is not callable", value.type_name()))),
+        }
+    }
+
+    fn call_closure(&mut self, closure: &Closure<'a>, args: Vec<Value<'a>>) -> Exec<'a, Value<'a>> {
+        let function = &closure.function;
+        if function.parameters.len() != args.len() {
+            return Err(RuntimeError::Arity {
+                name: function.name.value.to_string(),
+                expected: function.parameters.len(),
+                found: args.len(),
+            }
+            .into());
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow.into());
+        }
+
+        let scope = Scope::child(&closure.env);
+        for (parameter, arg) in function.parameters.iter().zip(args) {
+            scope.borrow_mut().define(parameter.value, arg);
+        }
+
+        self.depth += 1;
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || {
+            self.exec_block(&function.block, &scope)
+        });
+        self.depth -= 1;
+
+        match result {
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(unwind) => Err(unwind),
+        }
+    }
+}
+
+/// The items a `for` loop iterates over. Maps are iterated by their sorted keys
+pub(crate) fn iter_items<'a>(value: Value<'a>) -> Result<Vec<Value<'a>>, RuntimeError> {
+    match value {
+        Value::Array(values) => Ok(values.borrow().clone()),
+        Value::Str(value) => Ok(value
+            .chars()
+            .map(|c| Value::Str(Rc::from(c.to_string())))
+            .collect()),
+        Value::Map(values) => {
+            let mut keys: Vec<String> = values.borrow().keys().cloned().collect();
+            keys.sort();
+            Ok(keys
+                .into_iter()
+                .map(|key| Value::Str(Rc::from(key)))
+                .collect())
+        }
+        value => Err(RuntimeError::Type(format!(
+            "{} is not iterable",
+            value.type_name()
+        ))),
+    }
+}
+
+/// Slices an array or string. `Nil` bounds default to the start or end, all
+/// bounds are clamped to the length of the value
+pub(crate) fn slice_value<'a>(
+    value: &Value<'a>,
+    start: &Value<'a>,
+    end: &Value<'a>,
+) -> Result<Value<'a>, RuntimeError> {
+    let len = match value {
+        Value::Array(values) => values.borrow().len(),
+        Value::Str(value) => value.chars().count(),
+        value => {
+            return Err(RuntimeError::Type(format!(
+                "{} can not be sliced",
+                value.type_name()
+            )))
+        }
+    };
+
+    let bound = |bound: &Value, default: usize| match bound {
+        Value::Nil => Ok(default),
+        Value::Int(i) => Ok((*i).max(0).min(len as i64) as usize),
+        value => Err(RuntimeError::Type(format!(
+            "slice bounds must be int, not {}",
+            value.type_name()
+        ))),
+    };
+    let start = bound(start, 0)?;
+    let end = bound(end, len)?.max(start);
+
+    Ok(match value {
+        Value::Array(values) => Value::array(values.borrow()[start..end].to_vec()),
+        Value::Str(value) => {
+            let sliced: String = value.chars().skip(start).take(end - start).collect();
+            Value::Str(Rc::from(sliced))
+        }
+        _ => unreachable!(),
+    })
+}
+
+fn type_error<'a>(message: String) -> Unwind<'a> {
+    RuntimeError::Type(message).into()
+}
+
+fn lookup<'a>(name: &str, env: &Env<'a>) -> Exec<'a, Value<'a>> {
+    env.borrow()
+        .get(name)
+        .ok_or_else(|| RuntimeError::Undefined(name.to_string()).into())
+}
+
+pub(crate) fn literal_value<'a>(literal: &Literal) -> Value<'a> {
+    match literal {
+        Literal::Str(value) => Value::Str(Rc::from(value.value.as_str())),
+        Literal::Boolean(value) => Value::Bool(value.value),
+        Literal::Nil(_) => Value::Nil,
+        Literal::Float(value) => Value::Float(value.value),
+        Literal::Int(value) => Value::Int(value.value as i64),
+    }
+}
+
+/// Fields of an instance take precedence over its methods
+pub(crate) fn get_attribute<'a>(value: &Value<'a>, name: &str) -> Result<Value<'a>, RuntimeError> {
+    match value {
+        Value::Instance(instance) => {
+            if let Some(value) = instance.fields.borrow().get(name) {
+                return Ok(value.clone());
+            }
+            if let Some(method) = instance.class.methods.get(name) {
+                return Ok(Value::BoundMethod(Rc::clone(instance), Rc::clone(method)));
+            }
+        }
+        Value::Map(values) => {
+            return Ok(values.borrow().get(name).cloned().unwrap_or(Value::Nil));
+        }
+        _ => {}
+    }
+
+    Err(RuntimeError::Attribute {
+        type_name: value.type_name().to_string(),
+        name: name.to_string(),
+    })
+}
+
+fn set_field<'a>(object: &Value<'a>, name: &'a str, value: Value<'a>) -> Result<(), RuntimeError> {
+    match object {
+        Value::Instance(instance) => {
+            instance.fields.borrow_mut().insert(name, value);
+            Ok(())
+        }
+        Value::Map(values) => {
+            values.borrow_mut().insert(name.to_string(), value);
+            Ok(())
+        }
+        object => Err(RuntimeError::Attribute {
+            type_name: object.type_name().to_string(),
+            name: name.to_string(),
+        }),
+    }
+}
+
+fn array_index(index: i64, len: usize) -> Result<usize, RuntimeError> {
+    if index >= 0 && (index as usize) < len {
+        Ok(index as usize)
+    } else {
+        Err(RuntimeError::Index { index, len })
+    }
+}
+
+pub(crate) fn get_index<'a>(
+    value: &Value<'a>,
+    index: &Value<'a>,
+) -> Result<Value<'a>, RuntimeError> {
+    match (value, index) {
+        (Value::Array(values), Value::Int(index)) => {
+            let values = values.borrow();
+            Ok(values[array_index(*index, values.len())?].clone())
+        }
+        (Value::Str(value), Value::Int(index)) => {
+            let len = value.chars().count();
+            let c = value
+                .chars()
+                .nth(array_index(*index, len)?)
+                .unwrap_or_default();
+            Ok(Value::Str(Rc::from(c.to_string())))
+        }
+        (Value::Map(values), Value::Str(key)) => {
+            Ok(values.borrow().get(&**key).cloned().unwrap_or(Value::Nil))
+        }
+        (value, index) => Err(RuntimeError::Type(format!(
+            "{} can not be indexed by {}",
+            value.type_name(),
+            index.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn set_index<'a>(
+    object: &Value<'a>,
+    index: &Value<'a>,
+    value: Value<'a>,
+) -> Result<(), RuntimeError> {
+    match (object, index) {
+        (Value::Array(values), Value::Int(index)) => {
+            let mut values = values.borrow_mut();
+            let index = array_index(*index, values.len())?;
+            values[index] = value;
+            Ok(())
+        }
+        (Value::Map(values), Value::Str(key)) => {
+            values.borrow_mut().insert(key.to_string(), value);
+            Ok(())
+        }
+        (object, index) => Err(RuntimeError::Type(format!(
+            "{} can not be indexed by {}",
+            object.type_name(),
+            index.type_name()
+        ))),
+    }
+}
+
+fn matches_pattern<'a>(
+    pattern: &Pattern<'a>,
+    value: &Value<'a>,
+    bindings: &mut Vec<(&'a str, Value<'a>)>,
+) -> bool {
+    match pattern {
+        Pattern::Literal(literal) => literal_value(literal) == *value,
+        Pattern::Range(range) => matches_range(range, value),
+        Pattern::Wildcard(_) => true,
+        Pattern::Binding(name) => {
+            bindings.push((name.value, value.clone()));
+            true
+        }
+        Pattern::Array(patterns) => match value {
+            Value::Array(values) => {
+                let values = values.borrow();
+                values.len() == patterns.len()
+                    && patterns
+                        .iter()
+                        .zip(values.iter())
+                        .all(|(pattern, value)| matches_pattern(pattern, value, bindings))
+            }
+            _ => false,
+        },
+        Pattern::Map(patterns) => match value {
+            Value::Map(values) => {
+                let values = values.borrow();
+                patterns.iter().all(|(key, pattern)| match values.get(key) {
+                    Some(value) => matches_pattern(pattern, value, bindings),
+                    None => false,
+                })
+            }
+            _ => false,
+        },
+    }
+}
+
+fn matches_range(range: &RangePattern, value: &Value) -> bool {
+    let start = compare(value, &literal_value(&range.start));
+    let end = compare(value, &literal_value(&range.end));
+
+    let above_start = matches!(start, Some(Ordering::Greater) | Some(Ordering::Equal));
+    let below_end = match end {
+        Some(Ordering::Less) => true,
+        Some(Ordering::Equal) => range.inclusive,
+        _ => false,
+    };
+    above_start && below_end
+}
+
+/// Numbers are compared with each other, strings lexicographically
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Int(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+        (Value::Float(a), Value::Int(b)) => a.partial_cmp(&(*b as f64)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+pub(crate) fn binary<'a>(
+    op: &Operator,
+    left: Value<'a>,
+    right: Value<'a>,
+) -> Result<Value<'a>, RuntimeError> {
+    let unsupported = |left: &Value, right: &Value| {
+        Err(RuntimeError::Type(format!(
+            "unsupported operands for {:?}: {} and {}",
+            op,
+            left.type_name(),
+            right.type_name()
+        )))
+    };
+
+    match op {
+        Operator::EQ => return Ok(Value::Bool(left == right)),
+        Operator::Neq => return Ok(Value::Bool(left != right)),
+        Operator::Lt | Operator::Gt | Operator::Leq | Operator::Geq => {
+            let ordering = match compare(&left, &right) {
+                Some(ordering) => ordering,
+                None => return unsupported(&left, &right),
+            };
+            let result = match op {
+                Operator::Lt => ordering == Ordering::Less,
+                Operator::Gt => ordering == Ordering::Greater,
+                Operator::Leq => ordering != Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            };
+            return Ok(Value::Bool(result));
+        }
+        _ => {}
+    }
+
+    match (op, &left, &right) {
+        (Operator::Add, Value::Str(a), Value::Str(b)) => {
+            Ok(Value::Str(Rc::from(format!("{}{}", a, b))))
+        }
+        (Operator::Add, Value::Array(a), Value::Array(b)) => {
+            let mut values = a.borrow().clone();
+            values.extend(b.borrow().iter().cloned());
+            Ok(Value::array(values))
+        }
+        (Operator::Div, _, _) | (Operator::Mod, _, _) if as_float(&right) == Some(0.0) => {
+            Err(RuntimeError::DivisionByZero)
+        }
+        (_, Value::Int(a), Value::Int(b)) if *op != Operator::Div => {
+            let (a, b) = (*a, *b);
+            let result = match op {
+                Operator::Add => a.checked_add(b),
+                Operator::Sub => a.checked_sub(b),
+                Operator::Mul => a.checked_mul(b),
+                Operator::Mod => a.checked_rem(b),
+                Operator::Pow if b < 0 => return Ok(Value::Float((a as f64).powf(b as f64))),
+                Operator::Pow => {
+                    if b > u32::MAX as i64 {
+                        None
+                    } else {
+                        a.checked_pow(b as u32)
+                    }
+                }
+                _ => return unsupported(&left, &right),
+            };
+            result.map(Value::Int).ok_or(RuntimeError::Overflow)
+        }
+        _ => {
+            let (a, b) = match (as_float(&left), as_float(&right)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return unsupported(&left, &right),
+            };
+            let result = match op {
+                Operator::Add => a + b,
+                Operator::Sub => a - b,
+                Operator::Mul => a * b,
+                Operator::Div => a / b,
+                Operator::Mod => a % b,
+                Operator::Pow => a.powf(b),
+                _ => return unsupported(&left, &right),
+            };
+            Ok(Value::Float(result))
+        }
+    }
+}
+
+pub(crate) fn unary<'a>(op: &UnOperator, operand: Value<'a>) -> Result<Value<'a>, RuntimeError> {
+    match (op, operand) {
+        (UnOperator::Not, operand) => Ok(Value::Bool(!operand.is_truthy())),
+        (UnOperator::Add, Value::Int(i)) => Ok(Value::Int(i)),
+        (UnOperator::Add, Value::Float(f)) => Ok(Value::Float(f)),
+        (UnOperator::Sub, Value::Int(i)) => i
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or(RuntimeError::Overflow),
+        (UnOperator::Sub, Value::Float(f)) => Ok(Value::Float(-f)),
+        (op, operand) => Err(RuntimeError::Type(format!(
+            "unsupported operand for {:?}: {}",
+            op,
+            operand.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    /// Runs the source and returns everything, which was printed
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(
+            rest.fragment().trim(),
+            "",
+            "Source was not parsed completely"
+        );
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&block)?;
+        Ok(String::from_utf8(interpreter.output().clone()).unwrap())
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let source = "print(1 + 2 * 3, 7 / 2, 7 % 3, 1.5 + 1, \"a\" + \"b\", -4)";
+        assert_eq!(run(source).unwrap(), "7 3.5 1 2.5 ab -4\n");
+        assert_eq!(run("print(1 / 0)"), Err(RuntimeError::DivisionByZero));
+        assert_eq!(
+            run("print(2147483647 * 2147483647 * 2147483647)"),
+            Err(RuntimeError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_comparison_and_logic() {
+        let source =
+            "print(1 < 2, 2 > 1.5, \"a\" == \"a\", [1, 2] == [1, 2], 1 == 1.0, Nil and 1, 1 and 2)";
+        assert_eq!(run(source).unwrap(), "True True True True True Nil 2\n");
+        assert_eq!(run("print(not Nil, not 1 == 1)").unwrap(), "True False\n");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "let total = 0\nlet i = 0\nwhile i < 5 do\n    i = i + 1\n    if i == 3 do\n        total = total + 100\n    else\n        total = total + i\n    end\nend\nfor c in \"ab\" do\n    print(c)\nend\nprint(total)";
+        assert_eq!(run(source).unwrap(), "a\nb\n112\n");
+    }
+
+    #[test]
+    fn test_closures() {
+        let source = "fun counter()\n    let count = 0\n    fun inc()\n        count = count + 1\n        return count\n    end\n    return inc\nend\nlet c = counter()\nc()\nprint(c(), counter()())";
+        assert_eq!(run(source).unwrap(), "2 1\n");
+    }
+
+    #[test]
+    fn test_declarations_before_use() {
+        let source = "let p = Point()\nprint(p.x(), twice(2))\nif True do\n    print(half(4))\n    fun half(x)\n        return x / 2\n    end\nend\nclass Point\n    fun x(self)\n        return twice(1)\n    end\nend\nfun twice(x)\n    return x * 2\nend";
+        assert_eq!(run(source).unwrap(), "2 4\n2.0\n");
+    }
+
+    #[test]
+    fn test_collections() {
+        let source = "let a = [1, 2, 3]\na[0] = 5\nlet m = {\"x\": a[1:]}\nm.y = \"z\"\nprint(a, m, len(a), range(2), m?.x?[0], m.missing ?? 0)";
+        assert_eq!(
+            run(source).unwrap(),
+            "[5, 2, 3] {\"x\": [2, 3], \"y\": \"z\"} 3 [0, 1] 2 0\n"
+        );
+        assert_eq!(
+            run("let a = [1]\nprint(a[1])"),
+            Err(RuntimeError::Index { index: 1, len: 1 })
+        );
+    }
+
+    #[test]
+    fn test_match_and_try() {
+        let source = "fun describe(x)\n    match x do\n        case 0..10 do\n            return \"small\"\n        end\n        case [a, _] if a > 1 do\n            return a\n        end\n        case _ do\n            raise \"unknown\"\n        end\n    end\nend\ntry\n    print(describe(3), describe([2, 0]))\n    describe(Nil)\ncatch err do\n    print(err)\nfinally\n    print(\"done\")\nend";
+        assert_eq!(run(source).unwrap(), "small 2\nunknown\ndone\n");
+        assert_eq!(
+            run("raise \"oops\""),
+            Err(RuntimeError::Uncaught(String::from("oops")))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("print(x)"),
+            Err(RuntimeError::Undefined(String::from("x")))
+        );
+        assert_eq!(
+            run("fun f(a) end\nf()"),
+            Err(RuntimeError::Arity {
+                name: String::from("f"),
+                expected: 1,
+                found: 0
+            })
+        );
+        assert_eq!(
+            run("fun f()\n    return f()\nend\nf()"),
+            Err(RuntimeError::StackOverflow)
+        );
+    }
+}
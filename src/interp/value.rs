@@ -0,0 +1,173 @@
+//! Runtime values of the interpreter
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::interp::{builtins::Builtin, env::Env};
+use crate::parser::statement::declaration::function::Function;
+
+/// A value, which is produced by evaluating an expression
+#[derive(Clone, Debug)]
+pub enum Value<'a> {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    Array(Rc<RefCell<Vec<Value<'a>>>>),
+    Map(Rc<RefCell<HashMap<String, Value<'a>>>>),
+    Function(Rc<Closure<'a>>),
+    Builtin(Builtin),
+    Class(Rc<ClassValue<'a>>),
+    Instance(Rc<Instance<'a>>),
+
+    /// A method together with the instance it was looked up on,
+    /// which is passed as the first argument
+    BoundMethod(Rc<Instance<'a>>, Rc<Closure<'a>>),
+
+    /// A function compiled to bytecode, which can only be called by the VM
+    Compiled(Rc<dyn CompiledFunction>),
+}
+
+/// A function of the [`compile`](crate::compile) module. The interpreter
+/// only shows its name, the VM gets its prototype back with [`Self::into_any`]
+pub trait CompiledFunction: fmt::Debug {
+    fn name(&self) -> &str;
+
+    fn into_any(self: Rc<Self>) -> Rc<dyn Any>;
+}
+
+/// A function together with the environment it was declared in
+#[derive(Debug)]
+pub struct Closure<'a> {
+    pub function: Function<'a>,
+    pub env: Env<'a>,
+}
+
+#[derive(Debug)]
+pub struct ClassValue<'a> {
+    pub name: &'a str,
+    pub methods: HashMap<&'a str, Rc<Closure<'a>>>,
+}
+
+#[derive(Debug)]
+pub struct Instance<'a> {
+    pub class: Rc<ClassValue<'a>>,
+    pub fields: RefCell<HashMap<&'a str, Value<'a>>>,
+}
+
+impl<'a> Value<'a> {
+    pub fn array(values: Vec<Value<'a>>) -> Value<'a> {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(values: HashMap<String, Value<'a>>) -> Value<'a> {
+        Value::Map(Rc::new(RefCell::new(values)))
+    }
+
+    /// Only `Nil` and `False` are falsy, every other value is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// Name of the type of the value, used in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Function(_)
+            | Value::Builtin(_)
+            | Value::BoundMethod(..)
+            | Value::Compiled(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+        }
+    }
+}
+
+/// Primitives and collections are compared by value, functions, classes
+/// and instances by identity
+impl<'a> PartialEq for Value<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => *a as f64 == *b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Builtin(a), Value::Builtin(b)) => a == b,
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::BoundMethod(a, f), Value::BoundMethod(b, g)) => {
+                Rc::ptr_eq(a, b) && Rc::ptr_eq(f, g)
+            }
+            (Value::Compiled(a), Value::Compiled(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "Nil"),
+            Value::Bool(true) => write!(f, "True"),
+            Value::Bool(false) => write!(f, "False"),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(values) => {
+                let values = values.borrow();
+                let mut keys: Vec<&String> = values.keys().collect();
+                keys.sort();
+
+                write!(f, "{{")?;
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    write_nested(f, &values[key])?;
+                }
+                write!(f, "}}")
+            }
+            Value::Function(closure) | Value::BoundMethod(_, closure) => {
+                write!(f, "<function {}>", closure.function.name.value)
+            }
+            Value::Compiled(function) => write!(f, "<function {}>", function.name()),
+            Value::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name()),
+            Value::Class(class) => write!(f, "<class {}>", class.name),
+            Value::Instance(instance) => write!(f, "<{} instance>", instance.class.name),
+        }
+    }
+}
+
+/// Strings inside of collections are quoted
+fn write_nested(f: &mut fmt::Formatter, value: &Value) -> fmt::Result {
+    match value {
+        Value::Str(value) => write!(f, "{:?}", value),
+        value => write!(f, "{}", value),
+    }
+}
@@ -15,7 +15,22 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod analysis;
+pub mod compile;
+pub mod formatter;
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod highlight;
+pub mod interp;
+pub mod lint;
+pub mod livadoc;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod optimize;
+pub mod outline;
 mod parser;
+pub mod resolver;
+pub mod sexp;
 
 pub use parser::*;
 
@@ -33,6 +48,16 @@ pub fn print_ast(filename: &str) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Prints the parsed liva source code as S-expressions, see [`sexp`]
+pub fn print_sexp(filename: &str) -> Result<(), Box<dyn Error>> {
+    use sexp::ToSexp;
+
+    let source: String = fs::read_to_string(filename)?;
+    let (_, block) = parse_source(Span::new(source.as_str())).map_err(|err| err.to_string())?;
+    print!("{}", block.to_sexp());
+    Ok(())
+}
+
 #[test]
 fn test_parser() -> Result<(), Box<dyn Error>> {
     let source: String = fs::read_to_string("examples/class.lv")?;
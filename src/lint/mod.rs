@@ -0,0 +1,280 @@
+//! Linter for liva source code
+//!
+//! The linter runs a set of [`Rule`]s over the parsed AST. Every rule has a
+//! [`Severity`], which can be changed per linter. A rule can be disabled for
+//! a whole file with a line or block comment anywhere in the file:
+//!
+//! ```code
+//! // liva:allow(unused-variable, empty-block)
+//! ```
+//!
+//! ```rust
+//! use liva_parser::{lint::Linter, parse_source, Span};
+//!
+//! let source = "let x = 3\nif True do\n    print(1)\nend\n";
+//! let (_, block) = parse_source(Span::new(source)).unwrap();
+//!
+//! let diagnostics = Linter::default().lint(source, &block);
+//! let rules: Vec<&str> = diagnostics.iter().map(|d| d.rule).collect();
+//! assert_eq!(rules, vec!["unused-variable", "constant-condition"]);
+//! ```
+
+pub mod rules;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::analysis::scope::{analyze, ScopeTree};
+use crate::parser::{
+    lexer::{tokenize, TokenKind},
+    position::{Position, TokenSpan},
+    statement::Block,
+};
+
+/// How serious a violation of a rule is
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Severity {
+    /// The rule is not checked at all
+    Allow,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Everything a rule can inspect
+pub struct LintContext<'a, 's> {
+    pub source: &'a str,
+    pub block: &'s Block<'a>,
+    pub scopes: &'s ScopeTree<'a>,
+}
+
+/// A single violation found by a rule
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding<'a> {
+    pub span: TokenSpan<'a>,
+    pub message: String,
+}
+
+impl<'a> Finding<'a> {
+    pub fn new(span: TokenSpan<'a>, message: impl Into<String>) -> Self {
+        Finding {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// A check of the linter
+pub trait Rule {
+    /// Name used in the configuration and in `liva:allow` comments
+    fn name(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>>;
+}
+
+/// A finding of a rule with the configured severity
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic<'a> {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: TokenSpan<'a>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn position(&self) -> Position {
+        Position::from(self.span)
+    }
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {} [{}]",
+            self.position(),
+            self.severity,
+            self.message,
+            self.rule
+        )
+    }
+}
+
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl Default for Linter {
+    /// A linter with all rules of [`rules::default_rules`]
+    fn default() -> Self {
+        let mut linter = Linter::new();
+        for rule in rules::default_rules() {
+            linter.add_rule(rule);
+        }
+        linter
+    }
+}
+
+impl Linter {
+    /// A linter without any rules
+    pub fn new() -> Self {
+        Linter {
+            rules: vec![],
+            severities: HashMap::new(),
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    /// Overrides the default severity of a rule
+    pub fn set_severity(&mut self, rule: &'static str, severity: Severity) {
+        self.severities.insert(rule, severity);
+    }
+
+    pub fn severity(&self, rule: &dyn Rule) -> Severity {
+        self.severities
+            .get(rule.name())
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+
+    /// Lints the block, which was parsed from the source.
+    /// Diagnostics are ordered by their position
+    pub fn lint<'a>(&self, source: &'a str, block: &Block<'a>) -> Vec<Diagnostic<'a>> {
+        let scopes = analyze(block);
+        let context = LintContext {
+            source,
+            block,
+            scopes: &scopes,
+        };
+        let allowed = allowed_rules(source);
+
+        let mut diagnostics = vec![];
+        for rule in self.rules.iter() {
+            let severity = self.severity(rule.as_ref());
+            if severity == Severity::Allow || allowed.contains(rule.name()) {
+                continue;
+            }
+
+            diagnostics.extend(rule.check(&context).into_iter().map(|finding| Diagnostic {
+                rule: rule.name(),
+                severity,
+                message: finding.message,
+                span: finding.span,
+            }));
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.location_offset());
+        diagnostics
+    }
+}
+
+/// Collects the rules of all `// liva:allow(rule, ...)` and
+/// `/* liva:allow(rule, ...) */` comments
+fn allowed_rules(source: &str) -> HashSet<&str> {
+    tokenize(source)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|lexeme| lexeme.kind == TokenKind::Comment)
+        .filter_map(|lexeme| {
+            let text = lexeme.text();
+            text.strip_prefix("//")
+                .or_else(|| text.strip_prefix("/*")?.strip_suffix("*/"))
+        })
+        .filter_map(|comment| comment.trim().strip_prefix("liva:allow("))
+        .filter_map(|rest| rest.find(')').map(|end| &rest[..end]))
+        .flat_map(|rules| rules.split(','))
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    fn lint(linter: &Linter, source: &str) -> Vec<(&'static str, Severity)> {
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        linter
+            .lint(source, &block)
+            .iter()
+            .map(|d| (d.rule, d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_allowed_rules() {
+        let source = "// liva:allow(unused-variable, empty-block)\nlet x = 1 // liva:allow(shadowed-name)\n// liva:allow()";
+        let mut allowed: Vec<&str> = allowed_rules(source).into_iter().collect();
+        allowed.sort();
+        assert_eq!(
+            allowed,
+            vec!["empty-block", "shadowed-name", "unused-variable"]
+        );
+
+        // Only comments count, not a `//` inside of another comment
+        let source = "/* not // liva:allow(unused-variable) */\n/* liva:allow(empty-block) */";
+        assert_eq!(
+            allowed_rules(source).into_iter().collect::<Vec<_>>(),
+            vec!["empty-block"]
+        );
+    }
+
+    #[test]
+    fn test_suppression_comment() {
+        let linter = Linter::default();
+        let source = "let x = 1\n";
+        assert_eq!(
+            lint(&linter, source),
+            vec![("unused-variable", Severity::Warning)]
+        );
+
+        let source = "// liva:allow(unused-variable)\nlet x = 1\n";
+        assert!(lint(&linter, source).is_empty());
+    }
+
+    #[test]
+    fn test_configured_severity() {
+        let mut linter = Linter::default();
+        linter.set_severity("unused-variable", Severity::Error);
+        linter.set_severity("empty-block", Severity::Allow);
+
+        let source = "let x = 1\nwhile False do\nend\n";
+        assert_eq!(
+            lint(&linter, source),
+            vec![
+                ("unused-variable", Severity::Error),
+                ("constant-condition", Severity::Warning)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let source = "\nlet unused = 1\n";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+        let diagnostics = Linter::default().lint(source, &block);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "2:5: warning: variable `unused` is never used [unused-variable]"
+        );
+    }
+}
@@ -0,0 +1,479 @@
+//! The rules which are shipped with the linter
+
+use std::collections::HashMap;
+
+use crate::analysis::{
+    scope::{ScopeKind, SymbolKind},
+    visit::{walk_class, walk_statement, Visitor},
+};
+use crate::lint::{Finding, LintContext, Rule};
+use crate::parser::{
+    expression::{ExprOrVarname, Expression},
+    literals::Collection,
+    position::{Spanned, TokenSpan},
+    statement::{declaration::class::Class, Block, Statement},
+};
+
+/// All rules of this module
+pub fn default_rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(UnusedVariable),
+        Box::new(UnusedImport),
+        Box::new(ShadowedName),
+        Box::new(UnreachableCode),
+        Box::new(EmptyBlock),
+        Box::new(ConstantCondition),
+        Box::new(DuplicateMethod),
+    ]
+}
+
+/// Variables, loop items and pattern bindings, which are never read.
+/// Names starting with `_` and exported variables are ignored
+pub struct UnusedVariable;
+
+impl Rule for UnusedVariable {
+    fn name(&self) -> &'static str {
+        "unused-variable"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        let scopes = context.scopes;
+        let exported: Vec<usize> = context
+            .block
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::LAssignment(assignment) if assignment.exported => {
+                    Some(assignment.variable.pos.location_offset())
+                }
+                _ => None,
+            })
+            .collect();
+
+        scopes
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| {
+                matches!(
+                    symbol.kind,
+                    SymbolKind::Variable | SymbolKind::LoopItem | SymbolKind::PatternBinding
+                )
+            })
+            .filter(|(_, symbol)| !symbol.name.starts_with('_'))
+            .filter(|(id, _)| {
+                scopes
+                    .references_to(*id)
+                    .iter()
+                    .all(|reference| reference.write)
+            })
+            .filter_map(|(_, symbol)| symbol.declaration.map(|span| (symbol, span)))
+            .filter(|(_, span)| !exported.contains(&span.location_offset()))
+            .map(|(symbol, span)| {
+                Finding::new(span, format!("variable `{}` is never used", symbol.name))
+            })
+            .collect()
+    }
+}
+
+/// Imported names, which are never used
+pub struct UnusedImport;
+
+impl Rule for UnusedImport {
+    fn name(&self) -> &'static str {
+        "unused-import"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        let scopes = context.scopes;
+        scopes
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.kind == SymbolKind::Import)
+            .filter(|(id, _)| scopes.references_to(*id).is_empty())
+            .filter_map(|(_, symbol)| {
+                symbol.declaration.map(|span| {
+                    Finding::new(span, format!("import `{}` is never used", symbol.name))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Declarations, which hide a name of an enclosing scope
+pub struct ShadowedName;
+
+impl Rule for ShadowedName {
+    fn name(&self) -> &'static str {
+        "shadowed-name"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        let scopes = context.scopes;
+        let mut findings = vec![];
+
+        for symbol in scopes.symbols.iter() {
+            let span = match symbol.declaration {
+                Some(span) if symbol.kind != SymbolKind::Method => span,
+                _ => continue,
+            };
+
+            // Methods do not see the names of their class
+            let mut parent = scopes.scopes[symbol.scope].parent;
+            if let Some(id) = parent {
+                if scopes.scopes[id].kind == ScopeKind::Class {
+                    parent = scopes.scopes[id].parent;
+                }
+            }
+
+            let shadowed = parent
+                .and_then(|parent| scopes.lookup(symbol.name, parent))
+                .map(|id| &scopes.symbols[id]);
+
+            if let Some(shadowed) = shadowed {
+                if shadowed.kind != SymbolKind::Builtin {
+                    findings.push(Finding::new(
+                        span,
+                        format!(
+                            "`{}` shadows a declaration of an enclosing scope",
+                            symbol.name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+/// Statements after a `return` or `raise` in the same block
+pub struct UnreachableCode;
+
+impl Rule for UnreachableCode {
+    fn name(&self) -> &'static str {
+        "unreachable-code"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        struct Check<'a>(Vec<Finding<'a>>);
+
+        impl<'a> Visitor<'a> for Check<'a> {
+            fn visit_block(&mut self, block: &Block<'a>) {
+                let exit = block
+                    .statements
+                    .iter()
+                    .position(|stmt| matches!(stmt, Statement::Return(_) | Statement::Raise(_)));
+
+                if let Some(idx) = exit {
+                    let keyword = match block.statements[idx] {
+                        Statement::Raise(_) => "raise",
+                        _ => "return",
+                    };
+                    let next = match block.statements.get(idx + 1) {
+                        Some(stmt) => stmt.span(),
+                        None => block.return_stmt.as_ref().and_then(|return_stmt| {
+                            return_stmt.values.iter().find_map(Spanned::span)
+                        }),
+                    };
+                    if let Some(span) = next {
+                        self.0.push(Finding::new(
+                            span,
+                            format!("unreachable code after `{}`", keyword),
+                        ));
+                    }
+                }
+
+                crate::analysis::visit::walk_block(self, block);
+            }
+        }
+
+        let mut check = Check(vec![]);
+        check.visit_block(context.block);
+        check.0
+    }
+}
+
+/// Control flow blocks without any statement. Empty function bodies are
+/// allowed, as they are commonly used as stubs
+pub struct EmptyBlock;
+
+impl Rule for EmptyBlock {
+    fn name(&self) -> &'static str {
+        "empty-block"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        struct Check<'a>(Vec<Finding<'a>>);
+
+        impl<'a> Check<'a> {
+            fn check(&mut self, block: &Block<'a>, span: Option<TokenSpan<'a>>, kind: &str) {
+                if block.statements.is_empty() && block.return_stmt.is_none() {
+                    if let Some(span) = span {
+                        self.0
+                            .push(Finding::new(span, format!("empty `{}` block", kind)));
+                    }
+                }
+            }
+        }
+
+        impl<'a> Visitor<'a> for Check<'a> {
+            fn visit_statement(&mut self, stmt: &Statement<'a>) {
+                match stmt {
+                    Statement::If(if_stmt) => {
+                        self.check(&if_stmt.stmts, if_stmt.cond.span(), "if");
+                        if let Some(else_statements) = &if_stmt.else_statements {
+                            self.check(else_statements, if_stmt.cond.span(), "else");
+                        }
+                    }
+                    Statement::While(while_stmt) => {
+                        self.check(&while_stmt.block, while_stmt.cond.span(), "while")
+                    }
+                    Statement::For(for_stmt) => {
+                        self.check(&for_stmt.block, Some(for_stmt.iter_item.pos), "for")
+                    }
+                    Statement::Match(match_stmt) => {
+                        for arm in match_stmt.arms.iter() {
+                            self.check(&arm.block, arm.pattern.span(), "case");
+                        }
+                    }
+                    Statement::Try(try_stmt) => {
+                        if let Some(catch) = &try_stmt.catch {
+                            let span = catch.binding.map(|binding| binding.pos);
+                            self.check(&catch.block, span, "catch");
+                        }
+                        if let Some(finally) = &try_stmt.finally {
+                            self.check(finally, stmt.span(), "finally");
+                        }
+                    }
+                    _ => {}
+                }
+                walk_statement(self, stmt);
+            }
+        }
+
+        let mut check = Check(vec![]);
+        check.visit_block(context.block);
+        check.0
+    }
+}
+
+/// Conditions of `if` and `while` statements, which do not depend on any variable
+pub struct ConstantCondition;
+
+impl ConstantCondition {
+    fn is_constant(expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(_) => true,
+            Expression::UnaryOp(op) => Self::is_constant(&op.operand),
+            Expression::BinaryOp(op) => Self::is_constant(&op.left) && Self::is_constant(&op.right),
+            Expression::Collection(Collection::Array(values)) => {
+                values.iter().all(Self::is_constant)
+            }
+            Expression::Collection(Collection::Map(values)) => {
+                values.values().all(Self::is_constant)
+            }
+            Expression::Conditional(conditional) => {
+                Self::is_constant(&conditional.cond)
+                    && Self::is_constant(&conditional.then_expr)
+                    && Self::is_constant(&conditional.else_expr)
+            }
+            Expression::NilCoalesce(coalesce) => {
+                Self::is_constant(&coalesce.left) && Self::is_constant(&coalesce.right)
+            }
+            Expression::PrefixExpr(prefix_expr) => match &prefix_expr.prefix {
+                ExprOrVarname::Exp(expr) => {
+                    prefix_expr.suffix_chain.is_empty() && Self::is_constant(expr)
+                }
+                ExprOrVarname::Varname(_) => false,
+            },
+            Expression::Call(_) => false,
+        }
+    }
+}
+
+impl Rule for ConstantCondition {
+    fn name(&self) -> &'static str {
+        "constant-condition"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        struct Check<'a>(Vec<Finding<'a>>);
+
+        impl<'a> Visitor<'a> for Check<'a> {
+            fn visit_statement(&mut self, stmt: &Statement<'a>) {
+                let cond = match stmt {
+                    Statement::If(if_stmt) => Some((&if_stmt.cond, "if")),
+                    Statement::While(while_stmt) => Some((&while_stmt.cond, "while")),
+                    _ => None,
+                };
+
+                if let Some((cond, kind)) = cond {
+                    if ConstantCondition::is_constant(cond) {
+                        if let Some(span) = cond.span() {
+                            self.0.push(Finding::new(
+                                span,
+                                format!("condition of `{}` is constant", kind),
+                            ));
+                        }
+                    }
+                }
+                walk_statement(self, stmt);
+            }
+        }
+
+        let mut check = Check(vec![]);
+        check.visit_block(context.block);
+        check.0
+    }
+}
+
+/// Methods, which are declared multiple times in the same class
+pub struct DuplicateMethod;
+
+impl Rule for DuplicateMethod {
+    fn name(&self) -> &'static str {
+        "duplicate-method"
+    }
+
+    fn check<'a>(&self, context: &LintContext<'a, '_>) -> Vec<Finding<'a>> {
+        struct Check<'a>(Vec<Finding<'a>>);
+
+        impl<'a> Visitor<'a> for Check<'a> {
+            fn visit_class(&mut self, class: &Class<'a>) {
+                let mut seen = HashMap::new();
+                for method in class.methods.iter() {
+                    if seen.insert(method.name.value, method.name.pos).is_some() {
+                        self.0.push(Finding::new(
+                            method.name.pos,
+                            format!(
+                                "method `{}` is already declared in class `{}`",
+                                method.name.value, class.name.value
+                            ),
+                        ));
+                    }
+                }
+                walk_class(self, class);
+            }
+        }
+
+        let mut check = Check(vec![]);
+        check.visit_block(context.block);
+        check.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::analysis::scope::analyze;
+    use crate::parser::{parse_source, Span};
+
+    /// Runs a single rule and returns the line and message of all findings
+    fn check(rule: &dyn Rule, source: &str) -> Vec<(u32, String)> {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(
+            rest.fragment().trim(),
+            "",
+            "Source was not parsed completely"
+        );
+
+        let scopes = analyze(&block);
+        let context = LintContext {
+            source,
+            block: &block,
+            scopes: &scopes,
+        };
+        rule.check(&context)
+            .into_iter()
+            .map(|finding| (finding.span.location_line(), finding.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_variable() {
+        let source = "let x = 1\nlet _y = 2\npub let z = 3\nlet w = 4\nw = 5\nfor i in [1] do\n    print(x)\nend";
+        assert_eq!(
+            check(&UnusedVariable, source),
+            vec![
+                (4, String::from("variable `w` is never used")),
+                (6, String::from("variable `i` is never used"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_import() {
+        let source = "import math\nimport lib.util\nfrom os import path, env\nprint(math.pi, env)";
+        assert_eq!(
+            check(&UnusedImport, source),
+            vec![
+                (2, String::from("import `util` is never used")),
+                (3, String::from("import `path` is never used"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed_name() {
+        let source = "let x = 1\nfun f(x)\n    return x\nend\nclass A\n    fun f(self)\n        let len = 1\n        return len\n    end\nend";
+        assert_eq!(
+            check(&ShadowedName, source),
+            vec![(
+                2,
+                String::from("`x` shadows a declaration of an enclosing scope")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let source =
+            "fun f(x)\n    return x\n    print(x)\nend\nfun g(x)\n    raise x\n    return x\nend";
+        assert_eq!(
+            check(&UnreachableCode, source),
+            vec![
+                (3, String::from("unreachable code after `return`")),
+                (7, String::from("unreachable code after `raise`"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_block() {
+        let source = "let x = 1\nif x do\nelse\n    print(x)\nend\nwhile x do end\nfun stub() end";
+        assert_eq!(
+            check(&EmptyBlock, source),
+            vec![
+                (2, String::from("empty `if` block")),
+                (6, String::from("empty `while` block"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_condition() {
+        let source = "let x = 1\nif 1 < 2 do\n    print(x)\nend\nwhile -1 do\n    print(x)\nend\nif x < 2 do\n    print(x)\nend";
+        assert_eq!(
+            check(&ConstantCondition, source),
+            vec![
+                (2, String::from("condition of `if` is constant")),
+                (5, String::from("condition of `while` is constant"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_method() {
+        let source = "class A\n    fun f(self) end\n    fun g(self) end\n    fun f(self) end\nend";
+        assert_eq!(
+            check(&DuplicateMethod, source),
+            vec![(
+                4,
+                String::from("method `f` is already declared in class `A`")
+            )]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! API documentation of liva modules
+//!
+//! The documentation of a module lists its exported functions, classes and
+//! variables with their doc comments, classes together with their methods.
+//! It is rendered as Markdown or as a standalone HTML page:
+//!
+//! ```rust
+//! use liva_parser::livadoc::{render, Format, ModuleDoc};
+//!
+//! let source = "/// Squares a number\npub fun square(x)\n    return x * x\nend\n";
+//! let module = ModuleDoc::new("math", source).unwrap();
+//! let markdown = render(&module, Format::Markdown);
+//!
+//! assert!(markdown.starts_with("# Module `math`\n"));
+//! assert!(markdown.contains("## `fun square(x)`\n\nSquares a number\n"));
+//! ```
+//!
+//! The `livadoc` binary writes the documentation of every module of a
+//! project into a directory.
+
+use std::fmt::Write;
+
+use crate::outline::{outline, Item, ItemKind};
+use crate::parser::{literals::Variable, SyntaxError};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    /// Extension of the documentation files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+        }
+    }
+}
+
+/// The documented items of a module
+#[derive(Debug, Clone)]
+pub struct ModuleDoc<'a> {
+    /// Path of the module as used by imports, like `lib.geometry`
+    pub name: String,
+    pub items: Vec<Item<'a>>,
+}
+
+impl<'a> ModuleDoc<'a> {
+    /// Documents the exported declarations of the module
+    pub fn new(name: &str, source: &'a str) -> Result<Self, SyntaxError> {
+        let items = outline(source)?
+            .into_iter()
+            .filter(|item| item.exported)
+            .collect();
+        Ok(ModuleDoc {
+            name: String::from(name),
+            items,
+        })
+    }
+
+    /// Documents all declarations of the module
+    pub fn with_private(name: &str, source: &'a str) -> Result<Self, SyntaxError> {
+        let items = outline(source)?
+            .into_iter()
+            .filter(|item| !matches!(item.kind, ItemKind::Import { .. }))
+            .collect();
+        Ok(ModuleDoc {
+            name: String::from(name),
+            items,
+        })
+    }
+}
+
+pub fn render(module: &ModuleDoc, format: Format) -> String {
+    match format {
+        Format::Markdown => markdown(module),
+        Format::Html => html(module),
+    }
+}
+
+/// Index of the documented modules, linking to their files
+pub fn render_index(modules: &[&str], format: Format) -> String {
+    let extension = format.extension();
+    match format {
+        Format::Markdown => {
+            let mut out = String::from("# Modules\n\n");
+            for module in modules {
+                let _ = writeln!(out, "* [`{0}`]({0}.{1})", module, extension);
+            }
+            out
+        }
+        Format::Html => {
+            let mut body = String::from("<h1>Modules</h1>\n<ul>\n");
+            for module in modules {
+                let module = escape(module);
+                let _ = writeln!(
+                    body,
+                    "<li><a href=\"{0}.{1}\"><code>{0}</code></a></li>",
+                    module, extension
+                );
+            }
+            body.push_str("</ul>\n");
+            page("Modules", &body)
+        }
+    }
+}
+
+/// Declaration of the item as written in the source code, without its body
+fn signature(item: &Item) -> String {
+    let parameters = |parameters: &[Variable]| {
+        let names: Vec<&str> = parameters.iter().map(|p| p.value).collect();
+        names.join(", ")
+    };
+    match &item.kind {
+        ItemKind::Function { parameters: p } | ItemKind::Method { parameters: p } => {
+            format!("fun {}({})", item.name(), parameters(p))
+        }
+        ItemKind::Class => format!("class {}", item.name()),
+        ItemKind::Variable => format!("let {}", item.name()),
+        ItemKind::Import { .. } => format!("import {}", item.name()),
+    }
+}
+
+fn markdown(module: &ModuleDoc) -> String {
+    let mut out = format!("# Module `{}`\n", module.name);
+    for item in module.items.iter() {
+        markdown_item(&mut out, item, 2);
+        for method in item.children.iter() {
+            if matches!(method.kind, ItemKind::Method { .. }) {
+                markdown_item(&mut out, method, 3);
+            }
+        }
+    }
+    out
+}
+
+fn markdown_item(out: &mut String, item: &Item, level: usize) {
+    let _ = write!(out, "\n{} `{}`\n", "#".repeat(level), signature(item));
+    if let Some(doc) = &item.doc {
+        let _ = write!(out, "\n{}\n", doc);
+    }
+}
+
+fn html(module: &ModuleDoc) -> String {
+    let mut body = format!("<h1>Module <code>{}</code></h1>\n", escape(&module.name));
+    for item in module.items.iter() {
+        html_item(&mut body, item, 2);
+        for method in item.children.iter() {
+            if matches!(method.kind, ItemKind::Method { .. }) {
+                html_item(&mut body, method, 3);
+            }
+        }
+    }
+    page(&module.name, &body)
+}
+
+fn html_item(out: &mut String, item: &Item, level: usize) {
+    let _ = writeln!(
+        out,
+        "<h{0} id=\"{1}\"><code>{2}</code></h{0}>",
+        level,
+        escape(item.name()),
+        escape(&signature(item))
+    );
+    if let Some(doc) = &item.doc {
+        // Paragraphs are separated by empty comment lines
+        for paragraph in doc.split("\n\n") {
+            let _ = writeln!(out, "<p>{}</p>", escape(paragraph.trim()));
+        }
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        body
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const SOURCE: &str = "import math
+
+/// A point in the plane
+pub class Point
+    /// Creates a point
+    fun new(x, y)
+        return x
+    end
+end
+
+/// The origin
+export let origin = 0
+
+// Not exported
+fun helper()
+end
+";
+
+    #[test]
+    fn test_markdown() {
+        let module = ModuleDoc::new("lib.geometry", SOURCE).unwrap();
+        assert_eq!(
+            render(&module, Format::Markdown),
+            "# Module `lib.geometry`
+
+## `class Point`
+
+A point in the plane
+
+### `fun new(x, y)`
+
+Creates a point
+
+## `let origin`
+
+The origin
+"
+        );
+
+        let module = ModuleDoc::with_private("lib.geometry", SOURCE).unwrap();
+        let names: Vec<&str> = module.items.iter().map(|item| item.name()).collect();
+        assert_eq!(names, vec!["Point", "origin", "helper"]);
+        assert_eq!(module.items[2].doc.as_deref(), Some("Not exported"));
+    }
+
+    #[test]
+    fn test_html() {
+        let source = "/// Compares `a < b`\n///\n/// Returns a boolean\npub fun less(a, b)\n    return a < b\nend\n";
+        let module = ModuleDoc::new("cmp", source).unwrap();
+        let html = render(&module, Format::Html);
+        assert!(html.contains("<title>cmp</title>"));
+        assert!(html.contains(
+            "<h2 id=\"less\"><code>fun less(a, b)</code></h2>\n<p>Compares `a &lt; b`</p>\n<p>Returns a boolean</p>\n"
+        ));
+
+        let index = render_index(&["cmp"], Format::Html);
+        assert!(index.contains("<a href=\"cmp.html\"><code>cmp</code></a>"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Open text documents
+//!
+//! The parser counts lines from 1 and columns in characters from 1, the
+//! language server protocol counts both from 0 and columns in UTF-16 code
+//! units. All conversions go through byte offsets into the text.
+
+use serde_json::{json, Value};
+
+use crate::parser::position::{Position, TokenSpan};
+
+pub struct Document {
+    pub text: String,
+
+    /// Byte offset of the first character of every line
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Document { text, line_starts }
+    }
+
+    /// Zero based line and UTF-16 column of the byte offset
+    pub fn lsp_position(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line as u32, character as u32)
+    }
+
+    /// Byte offset of a zero based line and UTF-16 column,
+    /// positions behind the end of a line are moved to its end
+    pub fn offset(&self, line: u32, character: u32) -> usize {
+        let start = match self.line_starts.get(line as usize) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+
+        let mut units = 0;
+        for (idx, c) in self.text[start..].char_indices() {
+            if c == '\n' || units >= character as usize {
+                return start + idx;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    /// Byte offset of a position of the parser
+    pub fn offset_of(&self, position: Position) -> usize {
+        let start = match self.line_starts.get(position.line as usize - 1) {
+            Some(&start) => start,
+            None => return self.text.len(),
+        };
+        self.text[start..]
+            .char_indices()
+            .take_while(|&(_, c)| c != '\n')
+            .nth(position.column - 1)
+            .map_or_else(|| self.line_end(start), |(idx, _)| start + idx)
+    }
+
+    /// Line and character column of the parser for a byte offset
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset].chars().count() + 1;
+        Position::new(line as u32 + 1, column)
+    }
+
+    /// Byte offset of the end of the line containing `offset`, without the newline
+    pub fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |idx| offset + idx)
+    }
+
+    /// A LSP range between two byte offsets
+    pub fn range(&self, start: usize, end: usize) -> Value {
+        let (start_line, start_character) = self.lsp_position(start);
+        let (end_line, end_character) = self.lsp_position(end);
+        json!({
+            "start": {"line": start_line, "character": start_character},
+            "end": {"line": end_line, "character": end_character},
+        })
+    }
+
+    /// The LSP range of a span of this document
+    pub fn span_range(&self, span: TokenSpan) -> Value {
+        let start = span.location_offset();
+        self.range(start, start + span.fragment().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let document = Document::new(String::from("let a = 1\nlet ö = \"𝄞x\"\n"));
+
+        // `x` is behind a character with two UTF-16 code units
+        let x = document.text.find('x').unwrap();
+        assert_eq!(document.lsp_position(x), (1, 11));
+        assert_eq!(document.offset(1, 11), x);
+        assert_eq!(document.position(x), Position::new(2, 11));
+        assert_eq!(document.offset_of(Position::new(2, 11)), x);
+
+        assert_eq!(document.offset(0, 100), 9);
+        assert_eq!(document.offset(5, 0), document.text.len());
+        assert_eq!(document.offset_of(Position::new(1, 100)), 9);
+        assert_eq!(document.line_end(x), x + 2);
+    }
+}
@@ -0,0 +1,434 @@
+//! The language features of the server, each computes the result of a
+//! request for a document
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::analysis::{
+    scope::{analyze, SymbolKind},
+    semantic_errors,
+    visit::{walk_function, walk_statement, Visitor},
+};
+use crate::highlight::{self, SemanticKind};
+use crate::lint::{Linter, Severity};
+use crate::lsp::document::Document;
+use crate::outline::{end_blocks, outline, Item, ItemKind};
+use crate::parser::{
+    parse_complete,
+    position::TokenSpan,
+    statement::{declaration::function::Function, import::Import, Block, Statement},
+    Span,
+};
+use crate::resolver::{Resolver, SearchPaths};
+
+// Values of the LSP enums
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const SYMBOL_MODULE: u8 = 2;
+const SYMBOL_CLASS: u8 = 5;
+const SYMBOL_METHOD: u8 = 6;
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+
+/// Syntax errors, semantic errors and lint findings of the document
+pub fn diagnostics(document: &Document) -> Vec<Value> {
+    let block = match parse_complete(Span::new(&document.text)) {
+        Ok(block) => block,
+        Err(err) => {
+            let start = document.offset_of(err.position);
+            return vec![diagnostic(
+                document.range(start, document.line_end(start)),
+                SEVERITY_ERROR,
+                None,
+                &err.message,
+            )];
+        }
+    };
+
+    let mut diagnostics: Vec<Value> = semantic_errors(&block)
+        .iter()
+        .map(|(span, message)| {
+            diagnostic(document.span_range(*span), SEVERITY_ERROR, None, message)
+        })
+        .collect();
+    for lint in Linter::default().lint(&document.text, &block) {
+        let severity = match lint.severity {
+            Severity::Error => SEVERITY_ERROR,
+            Severity::Warning | Severity::Allow => SEVERITY_WARNING,
+        };
+        diagnostics.push(diagnostic(
+            document.span_range(lint.span),
+            severity,
+            Some(lint.rule),
+            &lint.message,
+        ));
+    }
+    diagnostics
+}
+
+fn diagnostic(range: Value, severity: u8, code: Option<&str>, message: &str) -> Value {
+    let mut diagnostic = json!({
+        "range": range,
+        "severity": severity,
+        "source": "liva",
+        "message": message,
+    });
+    if let Some(code) = code {
+        diagnostic["code"] = json!(code);
+    }
+    diagnostic
+}
+
+/// The outline of the document as a tree of `DocumentSymbol`s
+pub fn document_symbols(document: &Document) -> Vec<Value> {
+    match outline(&document.text) {
+        Ok(items) => items.iter().map(|item| symbol(document, item)).collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn symbol(document: &Document, item: &Item) -> Value {
+    let (kind, detail) = match &item.kind {
+        ItemKind::Import { .. } => (SYMBOL_MODULE, None),
+        ItemKind::Variable => (SYMBOL_VARIABLE, None),
+        ItemKind::Function { parameters } => (SYMBOL_FUNCTION, Some(parameters)),
+        ItemKind::Class => (SYMBOL_CLASS, None),
+        ItemKind::Method { parameters } => (SYMBOL_METHOD, Some(parameters)),
+    };
+    let children: Vec<Value> = item
+        .children
+        .iter()
+        .map(|child| symbol(document, child))
+        .collect();
+
+    let mut symbol = json!({
+        "name": item.name(),
+        "kind": kind,
+        "range": document.span_range(item.range),
+        "selectionRange": document.span_range(item.name),
+        "children": children,
+    });
+    if let Some(parameters) = detail {
+        let parameters: Vec<&str> = parameters.iter().map(|p| p.value).collect();
+        symbol["detail"] = json!(format!("({})", parameters.join(", ")));
+    }
+    symbol
+}
+
+fn parameter_list(function: &Function) -> String {
+    let parameters: Vec<&str> = function.parameters.iter().map(|p| p.value).collect();
+    format!("({})", parameters.join(", "))
+}
+
+/// Signature of functions and methods, the kind of every other name
+pub fn hover(document: &Document, offset: usize) -> Option<Value> {
+    let block = parse_complete(Span::new(&document.text)).ok()?;
+    let scopes = analyze(&block);
+    let position = document.position(offset);
+    let symbol = scopes.symbol_at(position.line, position.column)?;
+
+    let contents = match (symbol.kind, symbol.declaration) {
+        (SymbolKind::Function, Some(declaration)) | (SymbolKind::Method, Some(declaration)) => {
+            format!("```liva\n{}\n```", signature(&block, declaration)?)
+        }
+        (SymbolKind::Class, _) => format!("```liva\nclass {}\n```", symbol.name),
+        (kind, _) => format!("({}) {}", kind_label(kind), symbol.name),
+    };
+    Some(json!({"contents": {"kind": "markdown", "value": contents}}))
+}
+
+fn kind_label(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Builtin => "builtin",
+        SymbolKind::Variable => "variable",
+        SymbolKind::Parameter => "parameter",
+        SymbolKind::LoopItem => "loop item",
+        SymbolKind::Import => "import",
+        SymbolKind::Function => "function",
+        SymbolKind::Class => "class",
+        SymbolKind::Method => "method",
+        SymbolKind::PatternBinding => "pattern binding",
+        SymbolKind::CatchBinding => "catch binding",
+    }
+}
+
+/// Signature of the function or method declared by the name
+fn signature(block: &Block, name: TokenSpan) -> Option<String> {
+    struct FindFunction {
+        offset: usize,
+        signature: Option<String>,
+    }
+
+    impl<'a> Visitor<'a> for FindFunction {
+        fn visit_function(&mut self, function: &Function<'a>) {
+            if function.name.pos.location_offset() == self.offset {
+                self.signature = Some(format!(
+                    "fun {}{}",
+                    function.name.value,
+                    parameter_list(function)
+                ));
+            }
+            walk_function(self, function);
+        }
+    }
+
+    let mut finder = FindFunction {
+        offset: name.location_offset(),
+        signature: None,
+    };
+    finder.visit_block(block);
+    finder.signature
+}
+
+/// Declaration of the name at the offset as `(uri, range)`. Names bound by
+/// an import are resolved to the imported module, if it can be found
+pub fn definition(
+    document: &Document,
+    offset: usize,
+    search_paths: &SearchPaths,
+) -> Option<(Option<String>, Value)> {
+    let block = parse_complete(Span::new(&document.text)).ok()?;
+    let scopes = analyze(&block);
+    let position = document.position(offset);
+    let symbol = scopes.symbol_at(position.line, position.column)?;
+    let declaration = symbol.declaration?;
+
+    if symbol.kind == SymbolKind::Import {
+        if let Some(location) = import_definition(&block, declaration, search_paths) {
+            return Some(location);
+        }
+    }
+    Some((None, document.span_range(declaration)))
+}
+
+fn import_definition(
+    block: &Block,
+    declaration: TokenSpan,
+    search_paths: &SearchPaths,
+) -> Option<(Option<String>, Value)> {
+    let import = find_import(block, declaration)?;
+    let path: Vec<String> = import
+        .path
+        .iter()
+        .map(|name| name.value.to_string())
+        .collect();
+    let (module, _) = Resolver::new(search_paths.clone()).locate(&path, import.external)?;
+    if !module.is_file() {
+        return None;
+    }
+
+    let uri = super::path_to_uri(&module);
+    let item = import
+        .items
+        .iter()
+        .find(|item| item.pos.location_offset() == declaration.location_offset());
+    let range = item
+        .and_then(|item| exported_declaration(&module, item.value))
+        .unwrap_or_else(|| Document::new(String::new()).range(0, 0));
+    Some((Some(uri), range))
+}
+
+/// Range of the top level declaration of the name in the module file
+fn exported_declaration(module: &Path, name: &str) -> Option<Value> {
+    let document = Document::new(fs::read_to_string(module).ok()?);
+    let block = parse_complete(Span::new(&document.text)).ok()?;
+    block.statements.iter().find_map(|stmt| {
+        let declared = match stmt {
+            Statement::Fun(function) => function.name,
+            Statement::Class(class) => class.name,
+            Statement::LAssignment(assignment) => assignment.variable,
+            _ => return None,
+        };
+        if declared.value == name {
+            Some(document.span_range(declared.pos))
+        } else {
+            None
+        }
+    })
+}
+
+/// The import statement which declares the name
+fn find_import<'a>(block: &Block<'a>, declaration: TokenSpan) -> Option<Import<'a>> {
+    struct FindImport<'a> {
+        offset: usize,
+        found: Option<Import<'a>>,
+    }
+
+    impl<'a> Visitor<'a> for FindImport<'a> {
+        fn visit_statement(&mut self, stmt: &Statement<'a>) {
+            if let Statement::Import(import) = stmt {
+                let declares = import
+                    .alias
+                    .iter()
+                    .chain(import.items.iter())
+                    .chain(import.path.last())
+                    .any(|name| name.pos.location_offset() == self.offset);
+                if declares {
+                    self.found = Some(import.clone());
+                }
+            }
+            walk_statement(self, stmt);
+        }
+    }
+
+    let mut finder = FindImport {
+        offset: declaration.location_offset(),
+        found: None,
+    };
+    finder.visit_block(block);
+    finder.found
+}
+
+/// Semantic tokens of the document, encoded relative to the previous token
+/// as the protocol requires. The token types are the
+/// [`SemanticKind`]s, the only modifier is `declaration`
+pub fn semantic_tokens(document: &Document) -> Option<Value> {
+    let block = parse_complete(Span::new(&document.text)).ok()?;
+    let mut data = Vec::new();
+    let (mut previous_line, mut previous_start) = (0, 0);
+    for token in highlight::semantic_tokens(&block) {
+        let (line, start) = document.lsp_position(token.span.location_offset());
+        if line != previous_line {
+            previous_start = 0;
+        }
+        data.extend_from_slice(&[
+            line - previous_line,
+            start - previous_start,
+            token.span.fragment().encode_utf16().count() as u32,
+            token.kind.index() as u32,
+            token.declaration as u32,
+        ]);
+        previous_line = line;
+        previous_start = start;
+    }
+    Some(json!({ "data": data }))
+}
+
+/// Token types and modifiers of [`semantic_tokens`]
+pub fn semantic_tokens_legend() -> Value {
+    let types: Vec<&str> = SemanticKind::ALL.iter().map(SemanticKind::name).collect();
+    json!({"tokenTypes": types, "tokenModifiers": ["declaration"]})
+}
+
+/// Foldable ranges of all constructs which are closed by `end`
+pub fn folding_ranges(document: &Document) -> Vec<Value> {
+    let mut ranges: Vec<(u32, u32)> = end_blocks(&document.text)
+        .values()
+        .map(|&(start, end)| {
+            let (start_line, _) = document.lsp_position(start);
+            let (end_line, _) = document.lsp_position(end);
+            (start_line, end_line)
+        })
+        // The line of the `end` stays visible
+        .filter(|&(start_line, end_line)| end_line > start_line + 1)
+        .map(|(start_line, end_line)| (start_line, end_line - 1))
+        .collect();
+    ranges.sort_unstable();
+    ranges.dedup();
+
+    ranges
+        .into_iter()
+        .map(|(start_line, end_line)| json!({"startLine": start_line, "endLine": end_line}))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn document(text: &str) -> Document {
+        Document::new(String::from(text))
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let source = "fun f(a)\n    if a do\n        return 1\n    end\n    let x = if a then 1 else 2\n    return x\nend\nfun g() end";
+        assert_eq!(
+            folding_ranges(&document(source)),
+            vec![
+                json!({"startLine": 0, "endLine": 5}),
+                json!({"startLine": 1, "endLine": 2}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let source = "fun f(a)\n    return a.b\nend\nprint(f(1))";
+        assert_eq!(
+            semantic_tokens(&document(source)).unwrap()["data"],
+            json!([
+                0, 4, 1, 2, 1, // f
+                0, 2, 1, 4, 1, // a
+                1, 11, 1, 4, 0, // a
+                0, 2, 1, 6, 0, // b
+                2, 0, 5, 2, 0, // print
+                0, 6, 1, 2, 0, // f
+            ])
+        );
+        assert_eq!(semantic_tokens(&document("let = 1")), None);
+        assert_eq!(
+            semantic_tokens_legend()["tokenTypes"][SemanticKind::Parameter.index()],
+            "parameter"
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let source = "class A\n    fun m(self, x)\n    end\nend\nfun f()\n    fun inner() end\nend";
+        let symbols = document_symbols(&document(source));
+        let names: Vec<(&str, u64, usize)> = symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol["name"].as_str().unwrap(),
+                    symbol["kind"].as_u64().unwrap(),
+                    symbol["children"].as_array().unwrap().len(),
+                )
+            })
+            .collect();
+        assert_eq!(names, vec![("A", 5, 1), ("f", 12, 1)]);
+
+        let method = &symbols[0]["children"][0];
+        assert_eq!(method["detail"], "(self, x)");
+        assert_eq!(method["range"]["start"], json!({"line": 1, "character": 4}));
+        assert_eq!(method["range"]["end"], json!({"line": 2, "character": 7}));
+    }
+
+    #[test]
+    fn test_hover() {
+        let source = "fun add(a, b)\n    return a + b\nend\nprint(add(1, 2))";
+        let document = document(source);
+
+        let call = source.rfind("add").unwrap();
+        let contents = &hover(&document, call).unwrap()["contents"]["value"];
+        assert_eq!(contents, "```liva\nfun add(a, b)\n```");
+
+        let parameter = source.find("a +").unwrap();
+        let contents = &hover(&document, parameter).unwrap()["contents"]["value"];
+        assert_eq!(contents, "(parameter) a");
+
+        assert_eq!(hover(&document, source.find("return").unwrap()), None);
+    }
+
+    #[test]
+    fn test_definition() {
+        let source = "let x = 1\nfun f()\n    return x\nend\nimport missing.module\nprint(module)";
+        let document = document(source);
+        let search_paths = SearchPaths::default();
+
+        let (uri, range) =
+            definition(&document, source.find("x\nend").unwrap(), &search_paths).unwrap();
+        assert_eq!(uri, None);
+        assert_eq!(range["start"], json!({"line": 0, "character": 4}));
+
+        // Modules which can not be found fall back to the import statement
+        let (uri, range) =
+            definition(&document, source.rfind("module").unwrap(), &search_paths).unwrap();
+        assert_eq!(uri, None);
+        assert_eq!(range["start"], json!({"line": 4, "character": 15}));
+    }
+}
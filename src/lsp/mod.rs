@@ -0,0 +1,322 @@
+//! A language server for liva, which communicates over stdio
+//!
+//! The server keeps the text of every open document and analyses it again
+//! for every request. Supported are
+//!
+//! * diagnostics for syntax errors, name errors and lint findings, which are
+//!   published whenever a document is opened or changed
+//! * document symbols from the [`outline`](crate::outline) of the document
+//! * hover with the parameter list of functions
+//! * go to definition for local names and imported modules
+//! * folding ranges for all blocks closed by `end`
+//! * semantic tokens for the names of the document, see
+//!   [`highlight`](crate::highlight)
+//!
+//! Imports are looked up in the workspace root, the standard library and the
+//! site packages can be set with the initialization options `stdlib` and
+//! `sitePackages`.
+//!
+//! ```no_run
+//! use std::io;
+//!
+//! let code = liva_parser::lsp::run(io::stdin().lock(), io::stdout().lock()).unwrap();
+//! std::process::exit(code);
+//! ```
+
+mod document;
+mod features;
+pub mod transport;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::resolver::SearchPaths;
+use document::Document;
+use transport::{read_message, write_message};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+type RequestError = (i64, String);
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    search_paths: SearchPaths,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    /// Handles a single message and returns the messages to send back
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // Responses to requests of the server are not expected
+            None => return vec![],
+        };
+        let params = &message["params"];
+
+        match message.get("id") {
+            Some(id) => {
+                let response = match self.request(method, params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, message)) => error_response(id, code, &message),
+                };
+                vec![response]
+            }
+            None => self.notification(method, params),
+        }
+    }
+
+    /// The exit code, once the client has sent `exit`
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, RequestError> {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, String::from("server is shut down")));
+        }
+
+        match method {
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/documentSymbol" => {
+                let document = self.document(params)?;
+                Ok(json!(features::document_symbols(document)))
+            }
+            "textDocument/hover" => {
+                let (document, offset) = self.document_position(params)?;
+                Ok(features::hover(document, offset).unwrap_or(Value::Null))
+            }
+            "textDocument/definition" => {
+                let (document, offset) = self.document_position(params)?;
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let location = features::definition(document, offset, &self.search_paths);
+                Ok(match location {
+                    Some((target, range)) => json!({
+                        "uri": target.as_deref().unwrap_or(uri),
+                        "range": range,
+                    }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/semanticTokens/full" => {
+                let document = self.document(params)?;
+                Ok(features::semantic_tokens(document).unwrap_or(Value::Null))
+            }
+            "textDocument/foldingRange" => {
+                let document = self.document(params)?;
+                Ok(json!(features::folding_ranges(document)))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.open(uri, text)
+            }
+            // Only full updates are requested, so the last change has the whole text
+            "textDocument/didChange" => match params["contentChanges"].as_array() {
+                Some(changes) => match changes.last().and_then(|change| change["text"].as_str()) {
+                    Some(text) => self.open(uri, text),
+                    None => vec![],
+                },
+                None => vec![],
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, vec![])]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let options = &params["initializationOptions"];
+        let project = params["rootUri"]
+            .as_str()
+            .and_then(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        self.search_paths = SearchPaths {
+            project: project.unwrap_or_default(),
+            stdlib: options["stdlib"].as_str().map(PathBuf::from),
+            site_packages: options["sitePackages"].as_str().map(PathBuf::from),
+        };
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "documentSymbolProvider": true,
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "foldingRangeProvider": true,
+                "semanticTokensProvider": {
+                    "legend": features::semantic_tokens_legend(),
+                    "full": true,
+                },
+            },
+            "serverInfo": {"name": "liva-lsp", "version": env!("CARGO_PKG_VERSION")},
+        })
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        let document = Document::new(String::from(text));
+        let diagnostics = features::diagnostics(&document);
+        self.documents.insert(String::from(uri), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn document(&self, params: &Value) -> Result<&Document, RequestError> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| (INVALID_PARAMS, String::from("missing text document")))?;
+        self.documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document `{}` is not open", uri)))
+    }
+
+    fn document_position(&self, params: &Value) -> Result<(&Document, usize), RequestError> {
+        let document = self.document(params)?;
+        let position = &params["position"];
+        match (position["line"].as_u64(), position["character"].as_u64()) {
+            (Some(line), Some(character)) => {
+                Ok((document, document.offset(line as u32, character as u32)))
+            }
+            _ => Err((INVALID_PARAMS, String::from("missing position"))),
+        }
+    }
+}
+
+/// Runs the server until the client sends `exit` and returns the exit code
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::default();
+
+    while let Some(body) = read_message(&mut input)? {
+        let responses = match serde_json::from_str::<Value>(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(&Value::Null, PARSE_ERROR, &err.to_string())],
+        };
+        for response in responses.iter() {
+            write_message(&mut output, response)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+
+    // The client went away without asking the server to exit
+    Ok(1)
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+/// Path of a `file://` URI with percent encoded characters
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = match (byte, tail) {
+            (b'%', [high, low, ..]) => std::str::from_utf8(&[*high, *low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_uris() {
+        assert_eq!(
+            uri_to_path("file:///home/a%20b/%C3%BC.lv"),
+            Some(PathBuf::from("/home/a b/ü.lv"))
+        );
+        assert_eq!(uri_to_path("untitled:1"), None);
+        assert_eq!(
+            path_to_uri(Path::new("/does not/exist.lv")),
+            "file:///does%20not/exist.lv"
+        );
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut server = Server::default();
+        let response = &server
+            .handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}))[0];
+        assert_eq!(response["result"]["capabilities"]["hoverProvider"], true);
+
+        let response = &server.handle(&json!({"jsonrpc": "2.0", "id": 2, "method": "unknown"}))[0];
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        server.handle(&json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}));
+        let response = &server.handle(&json!({"jsonrpc": "2.0", "id": 4, "method": "shutdown"}))[0];
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        assert_eq!(server.exit_code(), None);
+        server.handle(&json!({"jsonrpc": "2.0", "method": "exit"}));
+        assert_eq!(server.exit_code(), Some(0));
+    }
+}
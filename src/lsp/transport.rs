@@ -0,0 +1,88 @@
+//! Base protocol of the language server protocol
+//!
+//! Every message is a JSON-RPC object preceded by a `Content-Length` header
+//! and an empty line.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Largest accepted message body, the buffer for the body is allocated
+/// before it is read
+pub const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Reads the body of the next message, `None` if the input is closed
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid_data("unexpected end of headers")),
+            };
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim().parse::<usize>();
+                length = Some(value.map_err(|_| invalid_data("invalid Content-Length"))?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid_data("missing Content-Length"))?;
+    if length > MAX_CONTENT_LENGTH {
+        return Err(invalid_data("Content-Length is too large"));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| invalid_data("message is not valid UTF-8"))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &serde_json::json!({"id": 1})).unwrap();
+        write_message(&mut buffer, &serde_json::json!({"id": "ü"})).unwrap();
+
+        let mut input = &buffer[..];
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), r#"{"id":1}"#);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), r#"{"id":"ü"}"#);
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let mut input = "Content-Type: json\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+
+        let mut input = "Content-Length: 10\r\n".as_bytes();
+        assert!(read_message(&mut input).is_err());
+
+        let header = format!("Content-Length: {}\r\n\r\n", usize::MAX);
+        let err = read_message(&mut header.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,444 @@
+//! Constant folding and simplification of the AST
+//!
+//! The pass rewrites a parsed [`Block`] without changing its behaviour:
+//!
+//! * Operators on literals are evaluated, `2 * 3 + 1` becomes `7`
+//! * `if` statements with a constant condition are replaced by the taken branch
+//! * `while` loops with a falsy constant condition are removed
+//! * Statements after an unconditional `return` or `raise` are removed
+//!
+//! Operators are evaluated with the same semantics as in the
+//! [`interp`](crate::interp) module. Expressions, which would fail at
+//! runtime like `1 / 0`, are kept as they are, so the error still occurs.
+//! A folded literal keeps the span of the expression it replaces, so
+//! diagnostics keep pointing to the original source.
+//!
+//! ```
+//! use liva_parser::{optimize::optimize, parse_source, Span};
+//!
+//! let (_, block) = parse_source(Span::new("let x = 2 * 3 + 1\nwhile False do\nend\n")).unwrap();
+//! let (_, expected) = parse_source(Span::new("let x = 7\n")).unwrap();
+//! assert_eq!(optimize(block), expected);
+//! ```
+
+use std::convert::TryFrom;
+
+use crate::interp::{binary, literal_value, unary, value::Value};
+use crate::parser::{
+    expression::{
+        call::Call,
+        conditional::{Conditional, NilCoalesce},
+        ExprOrVarname, ExprSuffix, Expression, PrefixExpr, Slice,
+    },
+    literals::{Collection, Literal, Token},
+    position::{Spanned, TokenSpan},
+    statement::{
+        declaration::{
+            if_statement::If, match_statement::MatchArm, try_statement::Catch,
+            while_statement::While,
+        },
+        Block, RaiseStmt, ReturnStmt, Statement,
+    },
+    tokens::Operator,
+};
+
+/// Simplifies all statements and expressions of a block
+pub fn optimize(block: Block) -> Block {
+    let mut statements = vec![];
+    let mut terminated = false;
+
+    for stmt in block.statements {
+        for stmt in statement(stmt) {
+            let exits = matches!(stmt, Statement::Return(_) | Statement::Raise(_));
+            statements.push(stmt);
+            if exits {
+                terminated = true;
+                break;
+            }
+        }
+        if terminated {
+            break;
+        }
+    }
+
+    if !terminated {
+        statements.extend(block.return_stmt.map(return_values).map(Statement::Return));
+    }
+    // A return spliced in from an inlined branch ends the block
+    Block::from_statements(statements)
+}
+
+/// Folds all constant sub-expressions of an expression
+pub fn fold_expression(expr: Expression) -> Expression {
+    let span = expr.span();
+
+    match expr {
+        Expression::Literal(_) => expr,
+        Expression::Call(call) => Expression::Call(fold_call(call)),
+        Expression::BinaryOp(mut op) => {
+            op.left = fold_expression(op.left);
+            op.right = fold_expression(op.right);
+
+            let left = constant(&op.left);
+            match (&op.op, left) {
+                (Operator::And, Some(left)) if !left.is_truthy() => op.left,
+                (Operator::Or, Some(left)) if left.is_truthy() => op.left,
+                (Operator::And, Some(_)) | (Operator::Or, Some(_)) => op.right,
+                (_, Some(left)) => {
+                    let folded = constant(&op.right)
+                        .and_then(|right| binary(&op.op, left, right).ok())
+                        .and_then(|value| to_literal(value, span?));
+                    match folded {
+                        Some(literal) => Expression::Literal(literal),
+                        None => Expression::BinaryOp(op),
+                    }
+                }
+                _ => Expression::BinaryOp(op),
+            }
+        }
+        Expression::UnaryOp(mut op) => {
+            op.operand = fold_expression(op.operand);
+
+            let folded = constant(&op.operand)
+                .and_then(|operand| unary(&op.op, operand).ok())
+                .and_then(|value| to_literal(value, span?));
+            match folded {
+                Some(literal) => Expression::Literal(literal),
+                None => Expression::UnaryOp(op),
+            }
+        }
+        Expression::PrefixExpr(prefix_expr) => {
+            let prefix_expr = fold_prefix_expr(*prefix_expr);
+            match prefix_expr.prefix {
+                // Parentheses around a constant are not needed anymore
+                ExprOrVarname::Exp(Expression::Literal(literal))
+                    if prefix_expr.suffix_chain.is_empty() =>
+                {
+                    Expression::Literal(literal)
+                }
+                _ => Expression::PrefixExpr(Box::new(prefix_expr)),
+            }
+        }
+        Expression::Collection(Collection::Array(values)) => Expression::Collection(
+            Collection::Array(values.into_iter().map(fold_expression).collect()),
+        ),
+        Expression::Collection(Collection::Map(values)) => Expression::Collection(Collection::Map(
+            values
+                .into_iter()
+                .map(|(key, value)| (key, fold_expression(value)))
+                .collect(),
+        )),
+        Expression::Conditional(conditional) => {
+            let Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } = *conditional;
+            let cond = fold_expression(cond);
+
+            match constant(&cond) {
+                Some(cond) if cond.is_truthy() => fold_expression(then_expr),
+                Some(_) => fold_expression(else_expr),
+                None => Expression::Conditional(Box::new(Conditional {
+                    cond,
+                    then_expr: fold_expression(then_expr),
+                    else_expr: fold_expression(else_expr),
+                })),
+            }
+        }
+        Expression::NilCoalesce(coalesce) => {
+            let NilCoalesce { left, right } = *coalesce;
+            let left = fold_expression(left);
+
+            match constant(&left) {
+                Some(Value::Nil) => fold_expression(right),
+                Some(_) => left,
+                None => Expression::NilCoalesce(Box::new(NilCoalesce {
+                    left,
+                    right: fold_expression(right),
+                })),
+            }
+        }
+    }
+}
+
+/// Returns the value of a literal expression
+fn constant(expr: &Expression) -> Option<Value<'static>> {
+    match expr {
+        Expression::Literal(literal) => Some(literal_value(literal)),
+        _ => None,
+    }
+}
+
+/// Only values, which can be written as a literal, are folded
+fn to_literal<'a>(value: Value, span: TokenSpan<'a>) -> Option<Literal<'a>> {
+    Some(match value {
+        Value::Nil => Literal::Nil(span),
+        Value::Bool(value) => Literal::Boolean(Token { value, pos: span }),
+        Value::Int(value) => Literal::Int(Token {
+            value: i32::try_from(value).ok()?,
+            pos: span,
+        }),
+        Value::Float(value) => Literal::Float(Token { value, pos: span }),
+        Value::Str(value) => Literal::Str(Token {
+            value: value.to_string(),
+            pos: span,
+        }),
+        _ => return None,
+    })
+}
+
+fn fold_call(call: Call) -> Call {
+    Call {
+        callee: call.callee,
+        args: call.args.into_iter().map(fold_expression).collect(),
+    }
+}
+
+fn fold_prefix_expr(prefix_expr: PrefixExpr) -> PrefixExpr {
+    let prefix = match prefix_expr.prefix {
+        ExprOrVarname::Exp(expr) => ExprOrVarname::Exp(fold_expression(expr)),
+        prefix => prefix,
+    };
+    let suffix_chain = prefix_expr
+        .suffix_chain
+        .into_iter()
+        .map(|suffix| match suffix {
+            ExprSuffix::TableIdx(expr) => ExprSuffix::TableIdx(fold_expression(expr)),
+            ExprSuffix::SafeTableIdx(expr) => ExprSuffix::SafeTableIdx(fold_expression(expr)),
+            ExprSuffix::TableSlice(slice) => ExprSuffix::TableSlice(Slice {
+                start: slice.start.map(fold_expression),
+                end: slice.end.map(fold_expression),
+            }),
+            ExprSuffix::FuncCall(call) => ExprSuffix::FuncCall(fold_call(call)),
+            ExprSuffix::SafeFuncCall(call) => ExprSuffix::SafeFuncCall(fold_call(call)),
+            suffix => suffix,
+        })
+        .collect();
+
+    PrefixExpr {
+        prefix,
+        suffix_chain,
+    }
+}
+
+fn return_values(return_stmt: ReturnStmt) -> ReturnStmt {
+    ReturnStmt {
+        values: return_stmt
+            .values
+            .into_iter()
+            .map(fold_expression)
+            .collect(),
+    }
+}
+
+/// Simplifies a statement, which can be replaced by any number of statements
+fn statement(stmt: Statement) -> Vec<Statement> {
+    let stmt = match stmt {
+        Statement::Assignment(mut assignment) => {
+            assignment.variable = fold_prefix_expr(assignment.variable);
+            assignment.expression = fold_expression(assignment.expression);
+            Statement::Assignment(assignment)
+        }
+        Statement::LAssignment(mut assignment) => {
+            assignment.expression = fold_expression(assignment.expression);
+            Statement::LAssignment(assignment)
+        }
+        Statement::FuncCall(prefix_expr) => Statement::FuncCall(fold_prefix_expr(prefix_expr)),
+        Statement::While(while_stmt) => {
+            let cond = fold_expression(while_stmt.cond);
+            if matches!(constant(&cond), Some(value) if !value.is_truthy()) {
+                return vec![];
+            }
+            Statement::While(While {
+                cond,
+                block: optimize(while_stmt.block),
+            })
+        }
+        Statement::For(mut for_stmt) => {
+            for_stmt.iterator = fold_expression(for_stmt.iterator);
+            for_stmt.block = optimize(for_stmt.block);
+            Statement::For(for_stmt)
+        }
+        Statement::If(if_stmt) => return if_statement(if_stmt),
+        Statement::Match(mut match_stmt) => {
+            match_stmt.expr = fold_expression(match_stmt.expr);
+            match_stmt.arms = match_stmt
+                .arms
+                .into_iter()
+                .map(|arm| MatchArm {
+                    pattern: arm.pattern,
+                    guard: arm.guard.map(fold_expression),
+                    block: optimize(arm.block),
+                })
+                .collect();
+            Statement::Match(match_stmt)
+        }
+        Statement::Try(mut try_stmt) => {
+            try_stmt.block = optimize(try_stmt.block);
+            try_stmt.catch = try_stmt.catch.map(|catch| Catch {
+                binding: catch.binding,
+                block: optimize(catch.block),
+            });
+            try_stmt.finally = try_stmt.finally.map(optimize);
+            Statement::Try(try_stmt)
+        }
+        Statement::Fun(mut function) => {
+            function.block = optimize(function.block);
+            Statement::Fun(function)
+        }
+        Statement::Class(mut class) => {
+            for method in class.methods.iter_mut() {
+                method.block = optimize(std::mem::replace(
+                    &mut method.block,
+                    Block {
+                        statements: vec![],
+                        return_stmt: None,
+                    },
+                ));
+            }
+            Statement::Class(class)
+        }
+        Statement::Return(return_stmt) => Statement::Return(return_values(return_stmt)),
+        Statement::Raise(raise) => Statement::Raise(RaiseStmt {
+            value: fold_expression(raise.value),
+        }),
+        Statement::Import(import) => Statement::Import(import),
+    };
+    vec![stmt]
+}
+
+/// An `if` with a constant condition is replaced by the statements of the taken
+/// branch. If the branch declares names, it is kept as its own block, as the
+/// declarations would leak into the enclosing block otherwise
+fn if_statement(if_stmt: If) -> Vec<Statement> {
+    let cond = fold_expression(if_stmt.cond);
+
+    let taken = match constant(&cond) {
+        Some(value) if value.is_truthy() => Some(if_stmt.stmts),
+        Some(_) => if_stmt.else_statements,
+        None => {
+            return vec![Statement::If(If {
+                cond,
+                stmts: optimize(if_stmt.stmts),
+                else_statements: if_stmt.else_statements.map(optimize),
+            })]
+        }
+    };
+
+    let taken = match taken {
+        Some(taken) => optimize(taken),
+        None => return vec![],
+    };
+
+    let declares = taken.statements.iter().any(|stmt| {
+        matches!(
+            stmt,
+            Statement::LAssignment(_) | Statement::Fun(_) | Statement::Class(_)
+        )
+    });
+    if declares {
+        let stmts = Block {
+            statements: vec![],
+            return_stmt: None,
+        };
+        return vec![match constant(&cond) {
+            Some(value) if value.is_truthy() => Statement::If(If {
+                cond,
+                stmts: taken,
+                else_statements: None,
+            }),
+            _ => Statement::If(If {
+                cond,
+                stmts,
+                else_statements: Some(taken),
+            }),
+        }];
+    }
+
+    let mut statements = taken.statements;
+    statements.extend(taken.return_stmt.map(Statement::Return));
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{
+        expression::binary::UnaryOp, parse_source, statement::declaration::function::Function,
+        tokens::UnOperator, Span,
+    };
+
+    fn parse(source: &str) -> Block<'_> {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(
+            rest.fragment().trim(),
+            "",
+            "Source was not parsed completely"
+        );
+        block
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let block = parse("let a = 2 * 3 + 1\nlet b = \"a\" + \"b\"\nlet c = (1 + 2) * x\nlet d = 7 / 2 < 4\nlet e = Nil ?? 5 - 3\nlet f = if 1 < 2 then x else y");
+        let expected =
+            parse("let a = 7\nlet b = \"ab\"\nlet c = 3 * x\nlet d = True\nlet e = 2\nlet f = x");
+        assert_eq!(optimize(block), expected);
+    }
+
+    #[test]
+    fn test_keep_runtime_errors() {
+        let block = parse("let a = 1 / 0\nlet b = 2147483647 + 1\nlet c = 1 + \"a\"");
+        assert_eq!(optimize(block.clone()), block);
+    }
+
+    #[test]
+    fn test_fold_not() {
+        let expr = Expression::UnaryOp(Box::new(UnaryOp {
+            op: UnOperator::Not,
+            operand: Expression::Literal(Literal::Boolean(Token::new(true, Span::new("true")))),
+        }));
+        assert_eq!(
+            fold_expression(expr),
+            Expression::Literal(Literal::Boolean(Token::new(false, Span::new("false"))))
+        );
+    }
+
+    #[test]
+    fn test_keep_span_of_original_expression() {
+        let block = optimize(parse("let x = 1\nlet y = (2 * 3) + 4"));
+        let span = match &block.statements[1] {
+            Statement::LAssignment(assignment) => assignment.expression.span().unwrap(),
+            stmt => panic!("Expected assignment, found {:?}", stmt),
+        };
+        assert_eq!(span.location_line(), 2);
+        assert_eq!(span.get_utf8_column(), 10);
+    }
+
+    #[test]
+    fn test_eliminate_branches() {
+        let block = parse("if True do\n    print(1)\nelse\n    print(2)\nend\nif 1 > 2 do\n    print(3)\nend\nwhile False do\n    print(4)\nend\nif False do\n    print(5)\nelse\n    let x = 6\nend");
+        let expected = parse("print(1)\nif False do\nelse\n    let x = 6\nend");
+        assert_eq!(optimize(block), expected);
+    }
+
+    #[test]
+    fn test_remove_unreachable_statements() {
+        let block = optimize(parse(
+            "fun f(x)\n    if True do\n        return x\n    end\n    print(x)\n    return 1\nend",
+        ));
+        let function = match &block.statements[0] {
+            Statement::Fun(function) => function,
+            stmt => panic!("Expected function, found {:?}", stmt),
+        };
+        let expected = parse("fun f(x)\n    return x\nend");
+        let expected_block = match &expected.statements[0] {
+            Statement::Fun(Function { block, .. }) => block,
+            stmt => panic!("Expected function, found {:?}", stmt),
+        };
+
+        assert_eq!(&function.block, expected_block);
+    }
+}
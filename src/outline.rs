@@ -0,0 +1,371 @@
+//! Outline of the declarations of a module
+//!
+//! The outline lists the imports, `let` bindings, functions and classes at
+//! the top level of a module. Classes contain their methods and functions
+//! the functions declared in their body. Every item has the span of its
+//! name and the span of the whole declaration, from its first keyword to
+//! its `end`:
+//!
+//! ```rust
+//! use liva_parser::outline::{outline, ItemKind};
+//!
+//! let source = "import math as m\n\npub fun area(r)\n    return m.pi * r * r\nend\n";
+//! let items = outline(source).unwrap();
+//!
+//! assert_eq!(items[0].name(), "math");
+//! assert_eq!(items[1].name(), "area");
+//! assert!(items[1].exported);
+//! assert!(matches!(&items[1].kind, ItemKind::Function { parameters } if parameters.len() == 1));
+//! assert_eq!(items[1].range.location_line(), 3);
+//! assert!(items[1].range.fragment().ends_with("end"));
+//! ```
+//!
+//! Use [`Range::from`](crate::position::Range) to get the line and column
+//! of the spans.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use nom::Slice;
+
+use crate::parser::{
+    expect_end,
+    incremental::IncrementalParse,
+    lexer::{tokenize, Lexeme, TokenKind},
+    literals::Variable,
+    parse_error,
+    position::TokenSpan,
+    statement::{declaration::function::Function, Block, Statement},
+    Span, SyntaxError,
+};
+
+/// A declaration in the outline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item<'a> {
+    pub kind: ItemKind<'a>,
+
+    /// Name of the declaration, the module path of an import
+    pub name: TokenSpan<'a>,
+
+    /// The whole declaration
+    pub range: TokenSpan<'a>,
+
+    /// Declared with `pub` or `export`
+    pub exported: bool,
+
+    /// Documentation of functions, classes and variables
+    pub doc: Option<String>,
+
+    /// Methods of a class and functions declared in a function
+    pub children: Vec<Item<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemKind<'a> {
+    Import {
+        external: bool,
+        alias: Option<Variable<'a>>,
+
+        /// Names imported selectively
+        items: Vec<Variable<'a>>,
+    },
+    Variable,
+    Function {
+        parameters: Vec<Variable<'a>>,
+    },
+    Class,
+    Method {
+        parameters: Vec<Variable<'a>>,
+    },
+}
+
+impl<'a> Item<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name.fragment()
+    }
+
+    /// The item followed by all items below it, depth first
+    pub fn walk(&self) -> Vec<&Item<'a>> {
+        let mut items = vec![self];
+        for child in self.children.iter() {
+            items.extend(child.walk());
+        }
+        items
+    }
+}
+
+/// Parses the whole source code and returns the outline of its top level
+pub fn outline(source: &str) -> Result<Vec<Item<'_>>, SyntaxError> {
+    let input = Span::new(source);
+    let parse = IncrementalParse::new(source).map_err(|err| parse_error(input, err))?;
+    expect_end(parse.rest())?;
+
+    let tokens = tokenize(source).map(without_comments).unwrap_or_default();
+    let blocks = end_blocks(source);
+
+    let mut items = Vec::new();
+    for (stmt, range) in parse
+        .block()
+        .statements
+        .iter()
+        .zip(parse.statement_ranges())
+    {
+        let range = TokenSpan::from(input.slice(trim(source, &tokens, range)));
+        match stmt {
+            Statement::Import(import) => {
+                let (first, last) = match (import.path.first(), import.path.last()) {
+                    (Some(first), Some(last)) => (first.pos, last.pos),
+                    _ => continue,
+                };
+                let path = first.location_offset()..last.location_offset() + last.fragment().len();
+                items.push(Item {
+                    kind: ItemKind::Import {
+                        external: import.external,
+                        alias: import.alias,
+                        items: import.items.clone(),
+                    },
+                    name: input.slice(path).into(),
+                    range,
+                    exported: false,
+                    doc: None,
+                    children: vec![],
+                });
+            }
+            Statement::LAssignment(assignment) => items.push(Item {
+                kind: ItemKind::Variable,
+                name: assignment.variable.pos,
+                range,
+                exported: assignment.exported,
+                doc: assignment.doc.clone(),
+                children: vec![],
+            }),
+            Statement::Fun(function) => items.push(Item {
+                range,
+                ..function_item(input, &blocks, function, false)
+            }),
+            Statement::Class(class) => items.push(Item {
+                kind: ItemKind::Class,
+                name: class.name.pos,
+                range,
+                exported: class.exported,
+                doc: class.doc.clone(),
+                children: class
+                    .methods
+                    .iter()
+                    .map(|method| function_item(input, &blocks, method, true))
+                    .collect(),
+            }),
+            _ => {}
+        }
+    }
+    Ok(items)
+}
+
+fn function_item<'a>(
+    input: Span<'a>,
+    blocks: &EndBlocks,
+    function: &Function<'a>,
+    method: bool,
+) -> Item<'a> {
+    let parameters = function.parameters.clone();
+    let name = function.name.pos;
+    let range = match blocks.get(&name.location_offset()) {
+        Some(&(start, end)) => input.slice(start..end).into(),
+        None => name,
+    };
+
+    Item {
+        kind: if method {
+            ItemKind::Method { parameters }
+        } else {
+            ItemKind::Function { parameters }
+        },
+        name,
+        range,
+        exported: function.exported,
+        doc: function.doc.clone(),
+        children: nested_functions(input, blocks, &function.block),
+    }
+}
+
+/// Functions declared directly in the block of a function
+fn nested_functions<'a>(input: Span<'a>, blocks: &EndBlocks, block: &Block<'a>) -> Vec<Item<'a>> {
+    block
+        .statements
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Statement::Fun(function) => Some(function_item(input, blocks, function, false)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn without_comments(tokens: Vec<Lexeme>) -> Vec<Lexeme> {
+    tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect()
+}
+
+/// Shrinks the range of a statement to its tokens, without the white space
+/// and comments around it
+fn trim(source: &str, tokens: &[Lexeme], range: Range<usize>) -> Range<usize> {
+    let first = tokens.partition_point(|token| token.span.location_offset() < range.start);
+    let last = tokens.partition_point(|token| token.span.location_offset() < range.end);
+    if first < last {
+        let end = &tokens[last - 1];
+        return tokens[first].span.location_offset()..end.span.location_offset() + end.text().len();
+    }
+
+    // The source could not be tokenized
+    let text = &source[range.clone()];
+    let start = range.start + text.len() - text.trim_start().len();
+    start..start.max(range.start + text.trim_end().len())
+}
+
+/// Byte offsets of the opening keyword and of the end of the `end` keyword
+/// of every block, by the offset of the token behind the opening keyword
+pub(crate) type EndBlocks = HashMap<usize, (usize, usize)>;
+
+/// Matches the keywords which start a block with their `end` keywords.
+/// Blocks are started by `fun`, `class`, `try` and by `do`, except for the
+/// `do` of a `catch` clause, which is closed by the `end` of its `try`
+pub(crate) fn end_blocks(source: &str) -> EndBlocks {
+    let tokens = match tokenize(source) {
+        Ok(tokens) => without_comments(tokens),
+        Err(_) => return HashMap::new(),
+    };
+    let is_keyword = |idx: usize, keyword: &str| {
+        tokens.get(idx).is_some_and(|token: &Lexeme| {
+            token.kind == TokenKind::Keyword && token.text() == keyword
+        })
+    };
+
+    let mut blocks = HashMap::new();
+    let mut open = Vec::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Keyword {
+            continue;
+        }
+        let catch_clause =
+            idx >= 1 && is_keyword(idx - 1, "catch") || idx >= 2 && is_keyword(idx - 2, "catch");
+        match token.text() {
+            "fun" | "class" | "try" => open.push(idx),
+            "do" if !catch_clause => open.push(idx),
+            "end" => {
+                if let Some(start) = open.pop() {
+                    if let Some(next) = tokens.get(start + 1) {
+                        let end = token.span.location_offset() + token.text().len();
+                        blocks.insert(
+                            next.span.location_offset(),
+                            (tokens[start].span.location_offset(), end),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Name, first line and last line of every item
+    fn lines<'a>(items: &[Item<'a>]) -> Vec<(&'a str, u32, u32)> {
+        items
+            .iter()
+            .flat_map(|item| item.walk())
+            .map(|item| {
+                let range = crate::parser::position::Range::from(item.range);
+                (item.name(), range.start.line, range.end.line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_outline() {
+        let source = "// Shapes
+from lib.geometry import point, line
+external import numbers as num
+
+pub let origin = point(0,
+    0)
+
+class Circle
+    fun new(r)
+        return r
+    end
+
+    fun area(r)
+        fun square(x)
+            return x * x
+        end
+        return 3 * square(r)
+    end
+end
+
+export fun circle(r)
+    return Circle.new(r)
+end
+print(origin)
+";
+        let items = outline(source).unwrap();
+        assert_eq!(
+            lines(&items),
+            vec![
+                ("lib.geometry", 2, 2),
+                ("numbers", 3, 3),
+                ("origin", 5, 6),
+                ("Circle", 8, 19),
+                ("new", 9, 11),
+                ("area", 13, 18),
+                ("square", 14, 16),
+                ("circle", 21, 23),
+            ]
+        );
+
+        assert_eq!(
+            items[0].range.fragment(),
+            &"from lib.geometry import point, line"
+        );
+        match &items[1].kind {
+            ItemKind::Import {
+                external, alias, ..
+            } => {
+                assert!(external);
+                assert_eq!(alias.map(|alias| alias.value), Some("num"));
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+        assert_eq!(
+            items[2].range.fragment(),
+            &"pub let origin = point(0,\n    0)"
+        );
+        assert!(items[2].exported && items[4].exported);
+        assert!(
+            matches!(&items[3].children[1].kind, ItemKind::Method { parameters } if parameters[0].value == "r")
+        );
+        assert!(matches!(
+            items[3].children[1].children[0].kind,
+            ItemKind::Function { .. }
+        ));
+    }
+
+    #[test]
+    fn test_outline_syntax_error() {
+        let err = outline("let a = 1\nfun f(\n").unwrap_err();
+        assert_eq!(err.position.line, 2);
+    }
+
+    #[test]
+    fn test_end_blocks() {
+        let source = "try\n    f()\ncatch e do\n    g()\nend\nwhile x do\nend";
+        let mut blocks: Vec<(usize, usize)> = end_blocks(source).values().copied().collect();
+        blocks.sort_unstable();
+        assert_eq!(blocks, vec![(0, 34), (43, 49)]);
+    }
+}
@@ -1,20 +1,151 @@
 use crate::parser::{
-    tokens::{comment, newline},
+    literals::ws,
+    tokens::{block_comment_end, block_comment_start, comment, newline, shebang},
     Res, Span,
 };
 
-use nom::{bytes::complete::is_not, error::context, sequence::delimited};
+use nom::{
+    branch::alt,
+    bytes::complete::take_while,
+    combinator::{eof, map, recognize},
+    error::{context, ContextError, ErrorKind, ParseError, VerboseError},
+    sequence::{delimited, pair, terminated},
+    Slice,
+};
 
 /// Parses a comment and discards it
-pub(crate) fn parse_comment(input: Span) -> Res<&str> {
-    context("Comment", delimited(comment, is_not("\n"), newline))(input)
-        .map(|(next_input, _)| (next_input, ""))
+///
+/// Comments are line comments `// ...`, block comments `/* ... */`, which can
+/// be nested, and a `#!` shebang line at the start of the source file
+pub(crate) fn parse_comment(input: Span<'_>) -> Res<'_, &str> {
+    context(
+        "Comment",
+        alt((parse_comment_text, parse_block_comment, parse_shebang)),
+    )(input)
+    .map(|(next_input, _)| (next_input, ""))
+}
+
+/// Parses a line comment and returns the text behind the slashes, the line
+/// break may be missing at the end of the input
+fn parse_comment_text(input: Span<'_>) -> Res<'_, Span<'_>> {
+    delimited(comment, rest_of_line, alt((newline, eof)))(input)
+}
+
+/// Parses a block comment including its delimiters, a block comment without
+/// end is an error
+pub(crate) fn parse_block_comment(input: Span<'_>) -> Res<'_, Span<'_>> {
+    let (rest, _) = block_comment_start(input)?;
+    let text = rest.fragment().as_bytes();
+    let mut depth = 1;
+    let mut idx = 0;
+    while depth > 0 {
+        match text.get(idx..idx + 2) {
+            Some(b"/*") => {
+                depth += 1;
+                idx += 2;
+            }
+            Some(b"*/") => {
+                depth -= 1;
+                idx += 2;
+            }
+            Some(_) => idx += 1,
+            None => {
+                let err = VerboseError::from_error_kind(input, ErrorKind::Eof);
+                return Err(nom::Err::Failure(VerboseError::add_context(
+                    input,
+                    "BlockComment",
+                    err,
+                )));
+            }
+        }
+    }
+
+    // The comment ends behind `*/`, which is always at a character boundary
+    let (rest, _) = block_comment_end(rest.slice(idx - 2..))?;
+    Ok((
+        rest,
+        input.slice(..rest.location_offset() - input.location_offset()),
+    ))
+}
+
+/// Parses a `#!` line at the very start of the source file and returns it
+/// without the line break
+pub(crate) fn parse_shebang(input: Span<'_>) -> Res<'_, Span<'_>> {
+    if input.location_offset() != 0 {
+        return Err(nom::Err::Error(VerboseError::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        )));
+    }
+    terminated(recognize(pair(shebang, rest_of_line)), alt((newline, eof)))(input)
+}
+
+fn rest_of_line(input: Span<'_>) -> Res<'_, Span<'_>> {
+    take_while(|c| c != '\n')(input)
+}
+
+/// Parses the white space and the comments in front of a declaration and
+/// returns its documentation
+///
+/// The documentation are the comment lines directly above the declaration,
+/// a blank line or code in front of a comment ends it. Comments starting
+/// with `///` are documentation comments, but plain `//` comments are
+/// accepted as well:
+///
+/// ```code
+/// /// Calculates the fibonacci sequence
+/// /// recursively
+/// fun fib(n)
+/// ```
+pub(crate) fn parse_doc(input: Span<'_>) -> Res<'_, Option<String>> {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut input = input;
+    loop {
+        let (rest, space) = ws(input)?;
+
+        // Comments end with the line break, so any further line break is a
+        // blank line between the comment and the next line
+        if space.fragment().contains('\n') {
+            lines.clear();
+        }
+
+        // Block comments and the shebang line are no documentation
+        match alt((map(parse_comment_text, Some), map(parse_comment, |_| None)))(rest) {
+            Ok((next_input, Some(text))) => {
+                let indentation = &rest.get_line_beginning()[..rest.get_column() - 1];
+                let own_line = indentation.iter().all(|byte| byte.is_ascii_whitespace());
+                if own_line {
+                    lines.push(text.fragment());
+                } else {
+                    lines.clear();
+                }
+                input = next_input;
+            }
+            Ok((next_input, None)) => {
+                lines.clear();
+                input = next_input;
+            }
+            Err(nom::Err::Error(_)) => {
+                let doc = lines
+                    .iter()
+                    .map(|line| {
+                        let line = line.strip_prefix('/').unwrap_or(line);
+                        line.strip_prefix(' ').unwrap_or(line).trim_end()
+                    })
+                    .collect::<Vec<&str>>()
+                    .join("\n");
+                return Ok((rest, if lines.is_empty() { None } else { Some(doc) }));
+            }
+            Err(err) => return Err(err),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use nom::Slice;
 
     #[test]
     fn test_parse_comment() {
@@ -23,4 +154,63 @@ mod tests {
 
         assert_eq!(res, "");
     }
+
+    #[test]
+    fn test_parse_comment_end_of_input() {
+        for string in ["// last line", "//", "//\n"].iter() {
+            let (rest, _) = parse_comment(Span::new(string)).unwrap();
+            assert_eq!(*rest.fragment(), "");
+        }
+    }
+
+    #[test]
+    fn test_parse_block_comment() {
+        let string = "/* outer /* inner */ still * / comment */ code";
+        let (rest, res) = parse_block_comment(Span::new(string)).unwrap();
+        assert_eq!(*res.fragment(), "/* outer /* inner */ still * / comment */");
+        assert_eq!(*rest.fragment(), " code");
+
+        let (rest, _) = parse_comment(Span::new("/*\nä\n*/\n")).unwrap();
+        assert_eq!(*rest.fragment(), "\n");
+
+        let res = parse_comment(Span::new("/* open /* nested */"));
+        assert!(matches!(res, Err(nom::Err::Failure(_))));
+    }
+
+    #[test]
+    fn test_parse_shebang() {
+        let (rest, res) = parse_shebang(Span::new("#!/usr/bin/env liva\nprint(1)")).unwrap();
+        assert_eq!(*res.fragment(), "#!/usr/bin/env liva");
+        assert_eq!(*rest.fragment(), "print(1)");
+
+        // Only the first line of the file may be a shebang
+        let input = Span::new("\n#!/usr/bin/env liva").slice(1..);
+        assert!(parse_comment(input).is_err());
+    }
+
+    #[test]
+    fn test_parse_doc() {
+        let doc = |string| parse_doc(Span::new(string)).unwrap().1;
+
+        assert_eq!(doc("fun f()"), None);
+        assert_eq!(
+            doc("\n/// Adds\n/// two numbers  \nfun add(a, b)"),
+            Some(String::from("Adds\ntwo numbers"))
+        );
+        assert_eq!(
+            doc("// License\n\n    // Calculates\nfun f()"),
+            Some(String::from("Calculates"))
+        );
+        assert_eq!(doc("// Not attached\n\nfun f()"), None);
+        assert_eq!(doc("/// Old\n/* block */\nfun f()"), None);
+        assert_eq!(
+            doc("#!/usr/bin/env liva\n/* License */\n/// Main\nfun main()"),
+            Some(String::from("Main"))
+        );
+
+        // The rest of a line with code is no documentation
+        let input = Span::new("let a = 1 // one\nfun f()").slice(9..);
+        let (rest, res) = parse_doc(input).unwrap();
+        assert_eq!((*rest.fragment(), res), ("fun f()", None));
+    }
 }
@@ -10,6 +10,8 @@ use nom::{
 };
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct BinaryOp<'a> {
     pub left: Expression<'a>,
     pub right: Expression<'a>,
@@ -38,6 +40,8 @@ pub(crate) fn parse_binary(input: Span) -> Res<BinaryOp> {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct UnaryOp<'a> {
     pub op: UnOperator,
     pub operand: Expression<'a>,
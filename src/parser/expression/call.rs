@@ -16,6 +16,8 @@ use crate::parser::{
 };
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Call<'a> {
     pub callee: Option<Variable<'a>>,
     pub args: Vec<Expression<'a>>,
@@ -0,0 +1,168 @@
+//! Expressions which choose between values
+//!
+//! ```code
+//! let sign = if x < 0 then -1 else 1
+//! let name = user.name ?? "anonymous"
+//! ```
+//!
+//! Both bind weaker than any binary operator, `a + b ?? c` is parsed as `(a + b) ?? c`
+//! and a conditional expression extends as far to the right as possible.
+
+use crate::parser::expression::{parse_flatexp, Expression};
+use crate::parser::literals::sp;
+use crate::parser::tokens::{lelse, lif, nil_coalesce, then};
+use crate::parser::{Res, Span};
+
+use nom::{
+    combinator::opt,
+    error::context,
+    sequence::{preceded, tuple},
+};
+
+use super::parse_expression;
+
+/// Represents `if cond then then_expr else else_expr`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Conditional<'a> {
+    pub cond: Expression<'a>,
+    pub then_expr: Expression<'a>,
+    pub else_expr: Expression<'a>,
+}
+
+/// Represents `left ?? right`, which yields `right` if `left` is nil
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct NilCoalesce<'a> {
+    pub left: Expression<'a>,
+    pub right: Expression<'a>,
+}
+
+pub(crate) fn parse_conditional(input: Span) -> Res<Conditional> {
+    context(
+        "Conditional",
+        tuple((
+            preceded(preceded(sp, lif), parse_expression),
+            preceded(preceded(sp, then), parse_expression),
+            preceded(preceded(sp, lelse), parse_expression),
+        )),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            Conditional {
+                cond: res.0,
+                then_expr: res.1,
+                else_expr: res.2,
+            },
+        )
+    })
+}
+
+/// Parses a binary expression, optionally followed by `?? expression`.
+/// Chains are right associative
+pub(crate) fn parse_nil_coalesce(input: Span) -> Res<Expression> {
+    context(
+        "NilCoalesce",
+        tuple((
+            parse_flatexp,
+            opt(preceded(preceded(sp, nil_coalesce), parse_expression)),
+        )),
+    )(input)
+    .map(|(next_input, (left, right))| {
+        let left = Expression::from(left);
+        match right {
+            Some(right) => (
+                next_input,
+                Expression::NilCoalesce(Box::new(NilCoalesce { left, right })),
+            ),
+            None => (next_input, left),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::parser::expression::{binary::BinaryOp, ExprOrVarname, PrefixExpr};
+    use crate::parser::literals::{Literal, Token};
+    use crate::parser::tokens::Operator;
+
+    fn var(name: &'static str) -> Expression<'static> {
+        Expression::PrefixExpr(Box::new(PrefixExpr {
+            prefix: ExprOrVarname::Varname(Token::new(name, Span::new(name))),
+            suffix_chain: vec![],
+        }))
+    }
+
+    fn int(value: i32) -> Expression<'static> {
+        Expression::Literal(Literal::Int(Token::new(value, Span::new(""))))
+    }
+
+    #[test]
+    fn test_conditional() {
+        let string = "if x < 0 then 1 else 2";
+        let (_, res) = parse_expression(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Expression::Conditional(Box::new(Conditional {
+                cond: Expression::BinaryOp(Box::new(BinaryOp {
+                    left: var("x"),
+                    op: Operator::Lt,
+                    right: int(0),
+                })),
+                then_expr: int(1),
+                else_expr: int(2),
+            }))
+        )
+    }
+
+    #[test]
+    fn test_conditional_else_is_greedy() {
+        let string = "if a then b else c + 1";
+        let (_, res) = parse_expression(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Expression::Conditional(Box::new(Conditional {
+                cond: var("a"),
+                then_expr: var("b"),
+                else_expr: Expression::BinaryOp(Box::new(BinaryOp {
+                    left: var("c"),
+                    op: Operator::Add,
+                    right: int(1),
+                })),
+            }))
+        )
+    }
+
+    #[test]
+    fn test_nil_coalesce_precedence() {
+        let string = "a + b ?? c ?? 1";
+        let (_, res) = parse_expression(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Expression::NilCoalesce(Box::new(NilCoalesce {
+                left: Expression::BinaryOp(Box::new(BinaryOp {
+                    left: var("a"),
+                    op: Operator::Add,
+                    right: var("b"),
+                })),
+                right: Expression::NilCoalesce(Box::new(NilCoalesce {
+                    left: var("c"),
+                    right: int(1),
+                })),
+            }))
+        )
+    }
+
+    #[test]
+    fn test_conditional_in_assignment() {
+        let string = "let y = if flag then x ?? 0 else 1";
+        let (rest, _) = crate::parse_statement(Span::new(string)).unwrap();
+        assert_eq!(rest.fragment(), &"");
+    }
+}
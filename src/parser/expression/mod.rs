@@ -2,23 +2,27 @@
 
 pub mod binary;
 pub mod call;
+pub mod conditional;
 
 use crate::literals::{Collection, Variable};
 use crate::parser::{
-    literals::{parse_collection, parse_literal, parse_variable, sp, Literal},
+    literals::{parse_collection, parse_literal, parse_variable, sp, ws, Literal},
+    nested,
     tokens::{
-        dot, left_bracket, left_paren, parse_binary_operator, parse_unary_operator, right_bracket,
-        right_paren, Operator, UnOperator, BINOP_PRECEDENCE, UNOPS,
+        colon, dot, left_bracket, left_paren, parse_binary_operator, parse_unary_operator,
+        right_bracket, right_paren, safe_dot, safe_left_bracket, Operator, UnOperator,
+        BINOP_PRECEDENCE, UNOPS,
     },
     Res, Span,
 };
 
 use crate::parser::expression::binary::{BinaryOp, UnaryOp};
 use call::{args, parse_call, Call};
+use conditional::{parse_conditional, parse_nil_coalesce, Conditional, NilCoalesce};
 
 use nom::{
     branch::alt,
-    combinator::map,
+    combinator::{map, opt},
     error::context,
     multi::many0,
     sequence::{delimited, preceded, tuple},
@@ -26,6 +30,11 @@ use nom::{
 
 // Represent a token or literal in a expression
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum Expression<'a> {
     Literal(Literal<'a>),
     Call(Call<'a>),
@@ -33,6 +42,8 @@ pub enum Expression<'a> {
     UnaryOp(Box<UnaryOp<'a>>),
     PrefixExpr(Box<PrefixExpr<'a>>),
     Collection(Collection<'a>),
+    Conditional(Box<Conditional<'a>>),
+    NilCoalesce(Box<NilCoalesce<'a>>),
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -42,13 +53,20 @@ pub(crate) enum Expression2<'a> {
     PrefixExpr(PrefixExpr<'a>),
 }
 
-fn parse_flatexp(input: Span) -> Res<FlatExpr> {
+pub(crate) fn parse_flatexp(input: Span) -> Res<FlatExpr> {
     context("FlatExpr", tuple((parse_head, parse_bin_op_chain)))(input)
         .map(|(next_input, res)| (next_input, flat_expr_from_components(res.0, res.1)))
 }
 
 pub(crate) fn parse_expression(input: Span) -> Res<Expression> {
-    map(parse_flatexp, Expression::from)(input)
+    nested(input, |input| {
+        alt((
+            map(parse_conditional, |res| {
+                Expression::Conditional(Box::new(res))
+            }),
+            parse_nil_coalesce,
+        ))(input)
+    })
 }
 
 pub(crate) fn parse_expression2(input: Span) -> Res<Expression2> {
@@ -95,14 +113,21 @@ fn parse_head(input: Span) -> Res<ExprHead> {
 fn parse_bin_op_chain(input: Span) -> Res<Vec<(Operator, ExprHead)>> {
     context(
         "OpChain",
+        // Only white space is skipped behind the expression, a comment in
+        // front of the next statement may be its documentation
         preceded(
-            sp,
-            many0(tuple((parse_binary_operator, preceded(sp, parse_head)))),
+            ws,
+            many0(preceded(
+                sp,
+                tuple((parse_binary_operator, preceded(sp, parse_head))),
+            )),
         ),
     )(input)
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct PrefixExpr<'a> {
     pub prefix: ExprOrVarname<'a>,
     pub suffix_chain: Vec<ExprSuffix<'a>>,
@@ -127,13 +152,18 @@ pub(crate) fn prefixexpr(input: Span) -> Res<PrefixExpr> {
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum ExprOrVarname<'a> {
     Exp(Expression<'a>),
     Varname(Variable<'a>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
-struct FlatExpr<'a>(Vec<OpOrExp2<'a>>);
+pub(crate) struct FlatExpr<'a>(Vec<OpOrExp2<'a>>);
 
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) enum OpOrExp2<'a> {
@@ -290,11 +320,11 @@ impl<'a> From<FlatExpr<'a>> for Expression<'a> {
 
         // First pass: find all triplets of the form a $ b where a and b are Exps and $ is a binop
         // of the highest precedence
-        merge_all_binops(&mut explist, &*BINOP_PRECEDENCE[0]);
-        merge_all_unops(&mut explist, &*UNOPS);
+        merge_all_binops(&mut explist, &BINOP_PRECEDENCE[0]);
+        merge_all_unops(&mut explist, &UNOPS);
 
         for binops in BINOP_PRECEDENCE.iter() {
-            merge_all_binops(&mut explist, &*binops);
+            merge_all_binops(&mut explist, binops);
         }
 
         assert_eq!(explist.len(), 1, "Exp tree construction didn't complete");
@@ -329,7 +359,7 @@ fn flat_expr_from_components<'a>(
     let acc = tuple_to_flat_vec(head);
     let res = binop_chain.into_iter().fold(acc, |mut a, head| {
         a.push(OpOrExp2::Op(UnOrBinOp::BinOp(head.0)));
-        a.extend_from_slice(&*tuple_to_flat_vec(head.1));
+        a.extend_from_slice(&tuple_to_flat_vec(head.1));
         a
     });
 
@@ -350,10 +380,41 @@ fn parse_prefix_expr(input: Span) -> Res<ExprOrVarname> {
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum ExprSuffix<'a> {
     TableDot(Variable<'a>),
     TableIdx(Expression<'a>),
+    TableSlice(Slice<'a>),
     FuncCall(Call<'a>),
+
+    /// `?.name`, yields nil if the value is nil
+    SafeTableDot(Variable<'a>),
+
+    /// `?[expression]`, yields nil if the value is nil
+    SafeTableIdx(Expression<'a>),
+
+    /// `?.method_name(...)`, yields nil if the value is nil
+    SafeFuncCall(Call<'a>),
+}
+
+impl<'a> ExprSuffix<'a> {
+    /// Returns true for suffixes, which call a function or method
+    pub fn is_call(&self) -> bool {
+        matches!(self, ExprSuffix::FuncCall(_) | ExprSuffix::SafeFuncCall(_))
+    }
+}
+
+/// Represents `[start:end]`, both bounds are optional
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Slice<'a> {
+    pub start: Option<Expression<'a>>,
+    pub end: Option<Expression<'a>>,
 }
 
 fn parse_suffix(input: Span) -> Res<ExprSuffix> {
@@ -370,14 +431,37 @@ fn parse_suffix(input: Span) -> Res<ExprSuffix> {
                 })
             }),
             map(preceded(dot, parse_variable), ExprSuffix::TableDot),
+            // Slices have to be tried before plain indexing
+            map(parse_slice, ExprSuffix::TableSlice),
             map(
                 delimited(left_bracket, parse_expression, right_bracket),
                 ExprSuffix::TableIdx,
             ),
+            map(preceded(safe_dot, parse_call), ExprSuffix::SafeFuncCall),
+            map(preceded(safe_dot, parse_variable), ExprSuffix::SafeTableDot),
+            map(
+                delimited(safe_left_bracket, parse_expression, right_bracket),
+                ExprSuffix::SafeTableIdx,
+            ),
         )),
     )(input)
 }
 
+fn parse_slice(input: Span) -> Res<Slice> {
+    context(
+        "Slice",
+        delimited(
+            left_bracket,
+            tuple((
+                opt(parse_expression),
+                preceded(preceded(sp, colon), opt(parse_expression)),
+            )),
+            preceded(sp, right_bracket),
+        ),
+    )(input)
+    .map(|(next_input, (start, end))| (next_input, Slice { start, end }))
+}
+
 impl<'a> From<Expression2<'a>> for Expression<'a> {
     fn from(e: Expression2<'a>) -> Expression<'a> {
         match e {
@@ -500,16 +584,16 @@ mod tests {
 
         let right = Expression::BinaryOp(Box::new(BinaryOp {
             op: Operator::EQ,
-            left: Expression::Literal(Literal::Nil(Span::new("Nil"))),
-            right: Expression::Literal(Literal::Nil(Span::new("Nil"))),
+            left: Expression::Literal(Literal::Nil(Span::new("Nil").into())),
+            right: Expression::Literal(Literal::Nil(Span::new("Nil").into())),
         }));
 
         assert_eq!(
             res,
             Expression::BinaryOp(Box::new(BinaryOp {
-                left: left,
+                left,
                 op: Operator::And,
-                right: right
+                right
             }))
         )
     }
@@ -563,6 +647,74 @@ mod tests {
         )
     }
 
+    fn var(name: &'static str) -> Expression<'static> {
+        Expression::PrefixExpr(Box::new(PrefixExpr {
+            prefix: ExprOrVarname::Varname(Token::new(name, Span::new(name))),
+            suffix_chain: vec![],
+        }))
+    }
+
+    #[test]
+    fn test_safe_navigation() {
+        let string = "a?.b?[i]?.m(x)";
+        let (_, res) = prefixexpr(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            PrefixExpr {
+                prefix: ExprOrVarname::Varname(Token::new("a", Span::new("a"))),
+                suffix_chain: vec![
+                    ExprSuffix::SafeTableDot(Token::new("b", Span::new("b"))),
+                    ExprSuffix::SafeTableIdx(var("i")),
+                    ExprSuffix::SafeFuncCall(Call {
+                        callee: Some(Token::new("m", Span::new("m"))),
+                        args: vec![var("x")]
+                    }),
+                ]
+            }
+        );
+        assert!(res.suffix_chain.last().unwrap().is_call());
+    }
+
+    #[test]
+    fn test_slice() {
+        let string = "xs[lo : hi]";
+        let (_, res) = prefixexpr(Span::new(string)).unwrap();
+        assert_eq!(
+            res.suffix_chain,
+            vec![ExprSuffix::TableSlice(Slice {
+                start: Some(var("lo")),
+                end: Some(var("hi"))
+            })]
+        );
+    }
+
+    #[test]
+    fn test_open_slices() {
+        let (_, res) = prefixexpr(Span::new("xs[:2]")).unwrap();
+        assert_eq!(
+            res.suffix_chain,
+            vec![ExprSuffix::TableSlice(Slice {
+                start: None,
+                end: Some(Expression::Literal(Literal::Int(Token::new(
+                    2,
+                    Span::new("2")
+                ))))
+            })]
+        );
+
+        let (_, res) = prefixexpr(Span::new("xs[1:]")).unwrap();
+        assert!(matches!(
+            res.suffix_chain[0],
+            ExprSuffix::TableSlice(Slice {
+                start: Some(_),
+                end: None
+            })
+        ));
+
+        let (_, res) = prefixexpr(Span::new("xs[1]")).unwrap();
+        assert!(matches!(res.suffix_chain[0], ExprSuffix::TableIdx(_)));
+    }
+
     #[test]
     fn test_parse_list_as_expr() {
         let string = "[1, 2, 3, 4]";
@@ -0,0 +1,900 @@
+//! Incremental reparsing for editors
+//!
+//! An [`IncrementalParse`] remembers where every top level statement ends.
+//! After a [`TextEdit`] only the statements touched by the edit are parsed
+//! again, starting with the statement in front of the edit, because a
+//! statement can continue into the text behind it. Parsing stops as soon as
+//! it reaches the start of a statement behind the edit, which was parsed
+//! before. The statements in front of and behind the parsed range are
+//! reused, only their spans are moved into the new source.
+//!
+//! The result is always the same as parsing the new source with
+//! [`parse_source`](crate::parse_source).
+//!
+//! ```rust
+//! use liva_parser::incremental::{IncrementalParse, TextEdit};
+//!
+//! let source = "let a = 1\nlet b = 2\nlet c = 3\n";
+//! let parse = IncrementalParse::new(source).unwrap();
+//!
+//! let edit = TextEdit::new(18..19, "a + 1");
+//! let source = edit.apply(source);
+//! let parse = parse.reparse(&source, &edit).unwrap();
+//!
+//! assert_eq!(parse.block().statements.len(), 3);
+//! assert_eq!(parse.reparsed(), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use nom::{
+    error::{ContextError, ErrorKind, ParseError, VerboseError},
+    Slice,
+};
+
+use crate::parser::{
+    expression::{
+        binary::{BinaryOp, UnaryOp},
+        call::Call,
+        conditional::{Conditional, NilCoalesce},
+        ExprOrVarname, ExprSuffix, Expression, PrefixExpr, Slice as SliceSuffix,
+    },
+    literals::{Collection, Literal, Token, Variable},
+    position::TokenSpan,
+    statement::{
+        declaration::{
+            assignment::{Assignment, LAssignment},
+            class::Class,
+            for_statement::For,
+            function::Function,
+            if_statement::If,
+            match_statement::{Match, MatchArm, Pattern, RangePattern},
+            try_statement::{Catch, Try},
+            while_statement::While,
+        },
+        import::Import,
+        parse_module_statement, Block, RaiseStmt, ReturnStmt, Statement,
+    },
+    Span,
+};
+
+/// Error of a failed parse, the same as the error of [`parse_source`](crate::parse_source)
+pub type IncrementalError<'a> = nom::Err<VerboseError<Span<'a>>>;
+
+/// Replacement of a byte range of the source code
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        TextEdit {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Applies the edit to the source code it refers to
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = String::with_capacity(source.len() + self.text.len());
+        edited.push_str(&source[..self.range.start]);
+        edited.push_str(&self.text);
+        edited.push_str(&source[self.range.end..]);
+        edited
+    }
+}
+
+/// Parsed source code, which can be updated with edits
+#[derive(Debug, Clone)]
+pub struct IncrementalParse<'a> {
+    source: &'a str,
+    block: Block<'a>,
+
+    /// Input behind the parsed block, like the rest of `parse_source`
+    rest: Span<'a>,
+
+    /// Byte offsets behind every top level statement
+    ends: Vec<usize>,
+
+    /// Number of statements parsed by the last update
+    reparsed: usize,
+}
+
+impl<'a> IncrementalParse<'a> {
+    /// Parses the whole source code
+    pub fn new(source: &'a str) -> Result<Self, IncrementalError<'a>> {
+        let input = Span::new(source);
+        let mut statements = Vec::new();
+        let mut ends = Vec::new();
+        let (rest, _) = parse_statements(input, input, &mut statements, &mut ends, |_| None)?;
+        let return_stmt = pop_return(&mut statements, &mut ends);
+
+        Ok(IncrementalParse {
+            source,
+            reparsed: statements.len(),
+            block: Block {
+                statements,
+                return_stmt,
+            },
+            rest,
+            ends,
+        })
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn block(&self) -> &Block<'a> {
+        &self.block
+    }
+
+    pub fn into_block(self) -> Block<'a> {
+        self.block
+    }
+
+    /// The input, which could not be parsed
+    pub fn rest(&self) -> Span<'a> {
+        self.rest
+    }
+
+    /// Byte ranges of the top level statements, each starts with the white
+    /// space and comments in front of the statement
+    pub fn statement_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts.zip(self.ends.iter()).map(|(start, &end)| start..end)
+    }
+
+    /// Number of top level statements, which were parsed by the last update
+    pub fn reparsed(&self) -> usize {
+        self.reparsed
+    }
+
+    /// Parses `source`, which is the previous source with the edit applied
+    pub fn reparse<'b>(
+        &self,
+        source: &'b str,
+        edit: &TextEdit,
+    ) -> Result<IncrementalParse<'b>, IncrementalError<'b>> {
+        let Range { start, end } = edit.range;
+        let delta = edit.text.len() as isize - (end - start) as isize;
+        debug_assert_eq!(source.len() as isize, self.source.len() as isize + delta);
+
+        let relocation = Relocation {
+            source,
+            edit_end: end,
+            delta,
+            lines: newlines(&edit.text) as i64 - newlines(&self.source[start..end]) as i64,
+        };
+
+        // Statements start where the previous one ends, the last entry is the
+        // start of the return statement and the rest
+        let starts: Vec<usize> = std::iter::once(0)
+            .chain(self.ends.iter().copied())
+            .collect();
+
+        let mut first = self
+            .ends
+            .iter()
+            .position(|&statement_end| statement_end >= start)
+            .unwrap_or(self.ends.len())
+            .saturating_sub(1);
+
+        // An early return only ends the block, if a statement follows it
+        while first > 0 && matches!(self.block.statements[first - 1], Statement::Return(_)) {
+            first -= 1;
+        }
+
+        let mut statements: Vec<Statement<'b>> = self.block.statements[..first]
+            .iter()
+            .map(|stmt| stmt.relocate(&relocation))
+            .collect();
+        let mut ends = self.ends[..first].to_vec();
+
+        // A statement behind the edit is found again, if the new input
+        // starts at its previous start. Every statement starts with skipping
+        // white space, which may have been consumed by the statement before
+        let statement_starts: Vec<usize> = starts[..self.ends.len()]
+            .iter()
+            .map(|&offset| skip_white_space(self.source, offset))
+            .collect();
+        let reusable = |offset: usize| {
+            let previous = offset as isize - delta;
+            if previous >= end as isize && previous as usize == starts[self.ends.len()] {
+                return Some(self.ends.len());
+            }
+
+            let previous = skip_white_space(source, offset) as isize - delta;
+            if previous < end as isize {
+                return None;
+            }
+            statement_starts.binary_search(&(previous as usize)).ok()
+        };
+
+        let full = Span::new(source);
+        let input = full.slice(starts[first]..);
+        let (input, reused) = parse_statements(full, input, &mut statements, &mut ends, reusable)?;
+        let parsed = statements.len();
+
+        let (rest, mut return_stmt) = match reused {
+            Some(reused) => {
+                statements.extend(
+                    self.block.statements[reused..]
+                        .iter()
+                        .map(|stmt| stmt.relocate(&relocation)),
+                );
+                ends.extend(
+                    self.ends[reused..]
+                        .iter()
+                        .map(|&statement_end| (statement_end as isize + delta) as usize),
+                );
+                (
+                    self.rest.relocate(&relocation),
+                    self.block.return_stmt.relocate(&relocation),
+                )
+            }
+            None => (input, None),
+        };
+        if return_stmt.is_none() {
+            return_stmt = pop_return(&mut statements, &mut ends);
+        }
+        // A return at the end is no statement, though it was parsed
+        let reparsed = parsed.min(statements.len()) - first;
+
+        Ok(IncrementalParse {
+            source,
+            block: Block {
+                statements,
+                return_stmt,
+            },
+            rest,
+            ends,
+            reparsed,
+        })
+    }
+}
+
+/// Offset behind the white space at `offset`, like the start of `sp`
+fn skip_white_space(source: &str, offset: usize) -> usize {
+    offset
+        + source[offset..]
+            .bytes()
+            .take_while(|byte| b" \t\r\n".contains(byte))
+            .count()
+}
+
+fn newlines(text: &str) -> usize {
+    text.bytes().filter(|&byte| byte == b'\n').count()
+}
+
+/// Parses top level statements like `parse_module_block` until no further
+/// statement can be parsed or `reusable` returns the index of a previously
+/// parsed statement for the current offset
+fn parse_statements<'b>(
+    full: Span<'b>,
+    mut input: Span<'b>,
+    statements: &mut Vec<Statement<'b>>,
+    ends: &mut Vec<usize>,
+    reusable: impl Fn(usize) -> Option<usize>,
+) -> Result<(Span<'b>, Option<usize>), IncrementalError<'b>> {
+    loop {
+        if let Some(reused) = reusable(input.location_offset()) {
+            return Ok((input, Some(reused)));
+        }
+
+        match parse_module_statement(input) {
+            // Like `many0`, a statement has to consume input
+            Ok((rest, _)) if rest.location_offset() == input.location_offset() => {
+                let err = VerboseError::from_error_kind(input, ErrorKind::Many0);
+                return Err(nom::Err::Error(module_context(full, err)));
+            }
+            Ok((rest, statement)) => {
+                statements.push(statement);
+                ends.push(rest.location_offset());
+                input = rest;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, None)),
+            Err(err) => return Err(err.map(|err| module_context(full, err))),
+        }
+    }
+}
+
+/// Moves a return at the end of the statements to the return statement of the
+/// block, like `Block::from_statements` does
+fn pop_return<'b>(
+    statements: &mut Vec<Statement<'b>>,
+    ends: &mut Vec<usize>,
+) -> Option<ReturnStmt<'b>> {
+    if !matches!(statements.last(), Some(Statement::Return(_))) {
+        return None;
+    }
+    ends.pop();
+    match statements.pop() {
+        Some(Statement::Return(return_stmt)) => Some(return_stmt),
+        _ => None,
+    }
+}
+
+/// Adds the context of `parse_module_block` to an error
+fn module_context<'b>(full: Span<'b>, err: VerboseError<Span<'b>>) -> VerboseError<Span<'b>> {
+    VerboseError::add_context(full, "ModuleBlock", err)
+}
+
+/// Moves spans of the previous source into the new source
+struct Relocation<'b> {
+    source: &'b str,
+
+    /// Offset behind the replaced text in the previous source
+    edit_end: usize,
+
+    /// Change of the length and of the line count by the edit
+    delta: isize,
+    lines: i64,
+}
+
+impl<'b> Relocation<'b> {
+    fn span(&self, span: Span) -> Span<'b> {
+        let (offset, line) = if span.location_offset() >= self.edit_end {
+            (
+                (span.location_offset() as isize + self.delta) as usize,
+                (span.location_line() as i64 + self.lines) as u32,
+            )
+        } else {
+            (span.location_offset(), span.location_line())
+        };
+        let fragment = &self.source[offset..offset + span.fragment().len()];
+
+        // SAFETY: `fragment` starts `offset` bytes behind the start of the
+        // source, so it has the same layout as the spans created by parsing
+        // the new source
+        unsafe { Span::new_from_raw_offset(offset, line, fragment, ()) }
+    }
+}
+
+/// Copy of a node with its spans moved into the new source
+trait Relocate<'b> {
+    type Output;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output;
+}
+
+impl<'a, 'b> Relocate<'b> for Span<'a> {
+    type Output = Span<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Span<'b> {
+        relocation.span(*self)
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for TokenSpan<'a> {
+    type Output = TokenSpan<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> TokenSpan<'b> {
+        match self {
+            TokenSpan::Source(span) => TokenSpan::Source(span.relocate(relocation)),
+            TokenSpan::Location(location) => TokenSpan::Location(*location),
+        }
+    }
+}
+
+impl<'b, T: Relocate<'b>> Relocate<'b> for Box<T> {
+    type Output = Box<T::Output>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output {
+        Box::new((**self).relocate(relocation))
+    }
+}
+
+impl<'b, T: Relocate<'b>> Relocate<'b> for Option<T> {
+    type Output = Option<T::Output>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output {
+        self.as_ref().map(|node| node.relocate(relocation))
+    }
+}
+
+impl<'b, T: Relocate<'b>> Relocate<'b> for Vec<T> {
+    type Output = Vec<T::Output>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output {
+        self.iter().map(|node| node.relocate(relocation)).collect()
+    }
+}
+
+impl<'b, T: Relocate<'b>> Relocate<'b> for HashMap<String, T> {
+    type Output = HashMap<String, T::Output>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output {
+        self.iter()
+            .map(|(key, node)| (key.clone(), node.relocate(relocation)))
+            .collect()
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Variable<'a> {
+    type Output = Variable<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Variable<'b> {
+        // Identifiers are the fragment of their span
+        let pos = self.pos.relocate(relocation);
+        Token {
+            value: *pos.fragment(),
+            pos,
+        }
+    }
+}
+
+macro_rules! relocate_tokens {
+    ($($value:ty),*) => {
+        $(
+            impl<'a, 'b> Relocate<'b> for Token<'a, $value> {
+                type Output = Token<'b, $value>;
+
+                fn relocate(&self, relocation: &Relocation<'b>) -> Self::Output {
+                    Token {
+                        value: self.value.clone(),
+                        pos: self.pos.relocate(relocation),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+relocate_tokens!(String, bool, f64, i32);
+
+impl<'a, 'b> Relocate<'b> for Literal<'a> {
+    type Output = Literal<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Literal<'b> {
+        match self {
+            Literal::Str(token) => Literal::Str(token.relocate(relocation)),
+            Literal::Boolean(token) => Literal::Boolean(token.relocate(relocation)),
+            Literal::Nil(span) => Literal::Nil(span.relocate(relocation)),
+            Literal::Float(token) => Literal::Float(token.relocate(relocation)),
+            Literal::Int(token) => Literal::Int(token.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Collection<'a> {
+    type Output = Collection<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Collection<'b> {
+        match self {
+            Collection::Array(values) => Collection::Array(values.relocate(relocation)),
+            Collection::Map(values) => Collection::Map(values.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Expression<'a> {
+    type Output = Expression<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Expression<'b> {
+        match self {
+            Expression::Literal(literal) => Expression::Literal(literal.relocate(relocation)),
+            Expression::Call(call) => Expression::Call(call.relocate(relocation)),
+            Expression::BinaryOp(op) => Expression::BinaryOp(op.relocate(relocation)),
+            Expression::UnaryOp(op) => Expression::UnaryOp(op.relocate(relocation)),
+            Expression::PrefixExpr(prefix_expr) => {
+                Expression::PrefixExpr(prefix_expr.relocate(relocation))
+            }
+            Expression::Collection(collection) => {
+                Expression::Collection(collection.relocate(relocation))
+            }
+            Expression::Conditional(conditional) => {
+                Expression::Conditional(conditional.relocate(relocation))
+            }
+            Expression::NilCoalesce(coalesce) => {
+                Expression::NilCoalesce(coalesce.relocate(relocation))
+            }
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for BinaryOp<'a> {
+    type Output = BinaryOp<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> BinaryOp<'b> {
+        BinaryOp {
+            left: self.left.relocate(relocation),
+            right: self.right.relocate(relocation),
+            op: self.op.clone(),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for UnaryOp<'a> {
+    type Output = UnaryOp<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> UnaryOp<'b> {
+        UnaryOp {
+            op: self.op.clone(),
+            operand: self.operand.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Call<'a> {
+    type Output = Call<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Call<'b> {
+        Call {
+            callee: self.callee.relocate(relocation),
+            args: self.args.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Conditional<'a> {
+    type Output = Conditional<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Conditional<'b> {
+        Conditional {
+            cond: self.cond.relocate(relocation),
+            then_expr: self.then_expr.relocate(relocation),
+            else_expr: self.else_expr.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for NilCoalesce<'a> {
+    type Output = NilCoalesce<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> NilCoalesce<'b> {
+        NilCoalesce {
+            left: self.left.relocate(relocation),
+            right: self.right.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for PrefixExpr<'a> {
+    type Output = PrefixExpr<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> PrefixExpr<'b> {
+        PrefixExpr {
+            prefix: self.prefix.relocate(relocation),
+            suffix_chain: self.suffix_chain.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for ExprOrVarname<'a> {
+    type Output = ExprOrVarname<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> ExprOrVarname<'b> {
+        match self {
+            ExprOrVarname::Exp(expr) => ExprOrVarname::Exp(expr.relocate(relocation)),
+            ExprOrVarname::Varname(name) => ExprOrVarname::Varname(name.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for ExprSuffix<'a> {
+    type Output = ExprSuffix<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> ExprSuffix<'b> {
+        match self {
+            ExprSuffix::TableDot(name) => ExprSuffix::TableDot(name.relocate(relocation)),
+            ExprSuffix::TableIdx(expr) => ExprSuffix::TableIdx(expr.relocate(relocation)),
+            ExprSuffix::TableSlice(slice) => ExprSuffix::TableSlice(slice.relocate(relocation)),
+            ExprSuffix::FuncCall(call) => ExprSuffix::FuncCall(call.relocate(relocation)),
+            ExprSuffix::SafeTableDot(name) => ExprSuffix::SafeTableDot(name.relocate(relocation)),
+            ExprSuffix::SafeTableIdx(expr) => ExprSuffix::SafeTableIdx(expr.relocate(relocation)),
+            ExprSuffix::SafeFuncCall(call) => ExprSuffix::SafeFuncCall(call.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for SliceSuffix<'a> {
+    type Output = SliceSuffix<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> SliceSuffix<'b> {
+        SliceSuffix {
+            start: self.start.relocate(relocation),
+            end: self.end.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Block<'a> {
+    type Output = Block<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Block<'b> {
+        Block {
+            statements: self.statements.relocate(relocation),
+            return_stmt: self.return_stmt.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Statement<'a> {
+    type Output = Statement<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Statement<'b> {
+        match self {
+            Statement::Assignment(assignment) => {
+                Statement::Assignment(assignment.relocate(relocation))
+            }
+            Statement::LAssignment(assignment) => {
+                Statement::LAssignment(assignment.relocate(relocation))
+            }
+            Statement::FuncCall(call) => Statement::FuncCall(call.relocate(relocation)),
+            Statement::While(while_stmt) => Statement::While(while_stmt.relocate(relocation)),
+            Statement::For(for_stmt) => Statement::For(for_stmt.relocate(relocation)),
+            Statement::If(if_stmt) => Statement::If(if_stmt.relocate(relocation)),
+            Statement::Match(match_stmt) => Statement::Match(match_stmt.relocate(relocation)),
+            Statement::Try(try_stmt) => Statement::Try(try_stmt.relocate(relocation)),
+            Statement::Fun(function) => Statement::Fun(function.relocate(relocation)),
+            Statement::Class(class) => Statement::Class(class.relocate(relocation)),
+            Statement::Return(return_stmt) => Statement::Return(return_stmt.relocate(relocation)),
+            Statement::Import(import) => Statement::Import(import.relocate(relocation)),
+            Statement::Raise(raise) => Statement::Raise(raise.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for ReturnStmt<'a> {
+    type Output = ReturnStmt<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> ReturnStmt<'b> {
+        ReturnStmt {
+            values: self.values.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for RaiseStmt<'a> {
+    type Output = RaiseStmt<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> RaiseStmt<'b> {
+        RaiseStmt {
+            value: self.value.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Assignment<'a> {
+    type Output = Assignment<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Assignment<'b> {
+        Assignment {
+            variable: self.variable.relocate(relocation),
+            expression: self.expression.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for LAssignment<'a> {
+    type Output = LAssignment<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> LAssignment<'b> {
+        LAssignment {
+            exported: self.exported,
+            doc: self.doc.clone(),
+            variable: self.variable.relocate(relocation),
+            expression: self.expression.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Class<'a> {
+    type Output = Class<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Class<'b> {
+        Class {
+            exported: self.exported,
+            doc: self.doc.clone(),
+            name: self.name.relocate(relocation),
+            methods: self.methods.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for For<'a> {
+    type Output = For<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> For<'b> {
+        For {
+            iter_item: self.iter_item.relocate(relocation),
+            iterator: self.iterator.relocate(relocation),
+            block: self.block.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Function<'a> {
+    type Output = Function<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Function<'b> {
+        Function {
+            exported: self.exported,
+            doc: self.doc.clone(),
+            name: self.name.relocate(relocation),
+            parameters: self.parameters.relocate(relocation),
+            block: self.block.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for If<'a> {
+    type Output = If<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> If<'b> {
+        If {
+            cond: self.cond.relocate(relocation),
+            stmts: self.stmts.relocate(relocation),
+            else_statements: self.else_statements.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Match<'a> {
+    type Output = Match<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Match<'b> {
+        Match {
+            expr: self.expr.relocate(relocation),
+            arms: self.arms.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for MatchArm<'a> {
+    type Output = MatchArm<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> MatchArm<'b> {
+        MatchArm {
+            pattern: self.pattern.relocate(relocation),
+            guard: self.guard.relocate(relocation),
+            block: self.block.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Pattern<'a> {
+    type Output = Pattern<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Pattern<'b> {
+        match self {
+            Pattern::Literal(literal) => Pattern::Literal(literal.relocate(relocation)),
+            Pattern::Range(range) => Pattern::Range(range.relocate(relocation)),
+            Pattern::Wildcard(span) => Pattern::Wildcard(span.relocate(relocation)),
+            Pattern::Binding(name) => Pattern::Binding(name.relocate(relocation)),
+            Pattern::Array(patterns) => Pattern::Array(patterns.relocate(relocation)),
+            Pattern::Map(patterns) => Pattern::Map(patterns.relocate(relocation)),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for RangePattern<'a> {
+    type Output = RangePattern<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> RangePattern<'b> {
+        RangePattern {
+            start: self.start.relocate(relocation),
+            end: self.end.relocate(relocation),
+            inclusive: self.inclusive,
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Try<'a> {
+    type Output = Try<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Try<'b> {
+        Try {
+            block: self.block.relocate(relocation),
+            catch: self.catch.relocate(relocation),
+            finally: self.finally.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Catch<'a> {
+    type Output = Catch<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Catch<'b> {
+        Catch {
+            binding: self.binding.relocate(relocation),
+            block: self.block.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for While<'a> {
+    type Output = While<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> While<'b> {
+        While {
+            cond: self.cond.relocate(relocation),
+            block: self.block.relocate(relocation),
+        }
+    }
+}
+
+impl<'a, 'b> Relocate<'b> for Import<'a> {
+    type Output = Import<'b>;
+
+    fn relocate(&self, relocation: &Relocation<'b>) -> Import<'b> {
+        Import {
+            external: self.external,
+            path: self.path.relocate(relocation),
+            alias: self.alias.relocate(relocation),
+            items: self.items.relocate(relocation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, position::Spanned};
+
+    /// Applies the edit and checks the result against a full parse
+    fn check_edit<'b>(
+        parse: &IncrementalParse,
+        source: &'b str,
+        edit: &TextEdit,
+    ) -> IncrementalParse<'b> {
+        let incremental = parse.reparse(source, edit).unwrap();
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(format!("{:?}", incremental.block()), format!("{:?}", block));
+        assert_eq!(incremental.rest(), rest);
+        incremental
+    }
+
+    #[test]
+    fn test_reparse_inside_block() {
+        let source = "let a = 1\nfun f(x)\n    return x\nend\nprint(f(a))\n";
+        let parse = IncrementalParse::new(source).unwrap();
+        assert_eq!(parse.reparsed(), 3);
+
+        // `a` is reused behind a new line
+        let body = source.find("\n    return").unwrap();
+        let edit = TextEdit::new(body..body, "\n    let y = x");
+        let edited = edit.apply(source);
+        let parse = check_edit(&parse, &edited, &edit);
+        assert_eq!(parse.reparsed(), 2);
+
+        let print = &parse.block().statements[2];
+        assert_eq!(print.span().unwrap().location_line(), 6);
+    }
+
+    #[test]
+    fn test_reparse_removed_end() {
+        let source = "if a do\n    b = 1\nend\nc = 2\nd = 3\n";
+        let parse = IncrementalParse::new(source).unwrap();
+
+        // Without the `end` the following statements belong to the if statement,
+        // until the parse fails
+        let end = source.find("end").unwrap();
+        let edit = TextEdit::new(end..end + 3, "");
+        let edited = edit.apply(source);
+        let parse = check_edit(&parse, &edited, &edit);
+        assert!(parse.block().statements.is_empty());
+
+        let edit = TextEdit::new(end..end, "end");
+        let restored = edit.apply(&edited);
+        let parse = check_edit(&parse, &restored, &edit);
+        assert_eq!(parse.block().statements.len(), 3);
+    }
+
+    #[test]
+    fn test_reparse_return() {
+        let source = "let a = 1\nreturn a\n";
+        let parse = IncrementalParse::new(source).unwrap();
+
+        let edit = TextEdit::new(source.len()..source.len(), "print(a)\n");
+        let edited = edit.apply(source);
+        let parse = check_edit(&parse, &edited, &edit);
+        assert!(matches!(parse.block().statements[1], Statement::Return(_)));
+        assert_eq!(parse.block().return_stmt, None);
+    }
+}
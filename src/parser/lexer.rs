@@ -0,0 +1,281 @@
+//! Splits the source code into a flat list of tokens
+//!
+//! The parser works directly on the source code, so the tokens are not used
+//! for parsing. They are meant for tools like syntax highlighters, which are
+//! interested in every part of the source code including comments.
+
+use std::error::Error;
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag},
+    character::complete::{alpha1, alphanumeric1, char, digit1, multispace1},
+    combinator::{map, opt, recognize},
+    error::ErrorKind,
+    error_position,
+    multi::many0,
+    sequence::{pair, tuple},
+};
+
+use crate::parser::{
+    comment::{parse_block_comment, parse_shebang},
+    literals::parse_str_raw,
+    position::Position,
+    tokens::{KEYWORDS, WORD_OPERATORS},
+    Res, Span,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    Boolean,
+    Nil,
+    Int,
+    Float,
+    Str,
+    Operator,
+    Punctuation,
+    Comment,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A single token, strings include their quotes and comments their slashes
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Lexeme<'a> {
+    pub kind: TokenKind,
+    pub span: Span<'a>,
+}
+
+impl<'a> Lexeme<'a> {
+    pub fn text(&self) -> &'a str {
+        self.span.fragment()
+    }
+}
+
+/// Input which does not start any token
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub position: Position,
+    pub found: char,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: unexpected character {:?}",
+            self.position, self.found
+        )
+    }
+}
+
+impl Error for LexError {}
+
+/// Operators and punctuation, longer ones have to come first
+pub(crate) const SYMBOLS: [(&str, TokenKind); 27] = [
+    ("..=", TokenKind::Operator),
+    ("..", TokenKind::Operator),
+    ("??", TokenKind::Operator),
+    ("?.", TokenKind::Operator),
+    ("?[", TokenKind::Punctuation),
+    ("==", TokenKind::Operator),
+    ("!=", TokenKind::Operator),
+    ("<=", TokenKind::Operator),
+    (">=", TokenKind::Operator),
+    ("+", TokenKind::Operator),
+    ("-", TokenKind::Operator),
+    ("*", TokenKind::Operator),
+    ("/", TokenKind::Operator),
+    ("%", TokenKind::Operator),
+    ("^", TokenKind::Operator),
+    ("<", TokenKind::Operator),
+    (">", TokenKind::Operator),
+    ("=", TokenKind::Operator),
+    (".", TokenKind::Operator),
+    ("(", TokenKind::Punctuation),
+    (")", TokenKind::Punctuation),
+    ("[", TokenKind::Punctuation),
+    ("]", TokenKind::Punctuation),
+    ("{", TokenKind::Punctuation),
+    ("}", TokenKind::Punctuation),
+    (",", TokenKind::Punctuation),
+    (":", TokenKind::Punctuation),
+];
+
+/// Splits the whole source code into tokens, whitespace is skipped
+pub fn tokenize(source: &str) -> Result<Vec<Lexeme<'_>>, LexError> {
+    let mut input = Span::new(source);
+    let mut lexemes = Vec::new();
+
+    loop {
+        if let Ok((rest, _)) = multispace1::<_, ()>(input) {
+            input = rest;
+        }
+        if input.fragment().is_empty() {
+            return Ok(lexemes);
+        }
+
+        match lexeme(input) {
+            Ok((rest, lexeme)) => {
+                lexemes.push(lexeme);
+                input = rest;
+            }
+            Err(_) => {
+                return Err(LexError {
+                    position: Position::from(input),
+                    found: input.fragment().chars().next().unwrap_or_default(),
+                })
+            }
+        }
+    }
+}
+
+fn lexeme(input: Span) -> Res<Lexeme> {
+    alt((
+        map(recognize(pair(tag("//"), opt(is_not("\n")))), |span| {
+            (TokenKind::Comment, span)
+        }),
+        map(parse_block_comment, |span| (TokenKind::Comment, span)),
+        map(parse_shebang, |span| (TokenKind::Comment, span)),
+        map(recognize(parse_str_raw), |span| (TokenKind::Str, span)),
+        map(recognize(tuple((digit1, char('.'), digit1))), |span| {
+            (TokenKind::Float, span)
+        }),
+        map(digit1, |span| (TokenKind::Int, span)),
+        map(word, |span: Span| (word_kind(span.fragment()), span)),
+        symbol,
+    ))(input)
+    .map(|(rest, (kind, span))| (rest, Lexeme { kind, span }))
+}
+
+fn word(input: Span) -> Res<Span> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0(alt((alphanumeric1, tag("_")))),
+    ))(input)
+}
+
+fn word_kind(word: &str) -> TokenKind {
+    match word.to_lowercase().as_str() {
+        "true" | "false" => TokenKind::Boolean,
+        "nil" => TokenKind::Nil,
+        _ if WORD_OPERATORS.contains(&word) => TokenKind::Operator,
+        _ if KEYWORDS.contains(&word) => TokenKind::Keyword,
+        _ => TokenKind::Identifier,
+    }
+}
+
+fn symbol(input: Span) -> Res<(TokenKind, Span)> {
+    for (symbol, kind) in SYMBOLS.iter() {
+        if let Ok((rest, span)) = tag::<_, _, ()>(*symbol)(input) {
+            return Ok((rest, (*kind, span)));
+        }
+    }
+    Err(nom::Err::Error(error_position!(input, ErrorKind::Tag)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(source)
+            .unwrap()
+            .iter()
+            .map(|lexeme| (lexeme.kind, lexeme.text()))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("let x = a?.b ?? 1.5 // done\nfor"),
+            vec![
+                (Keyword, "let"),
+                (Identifier, "x"),
+                (Operator, "="),
+                (Identifier, "a"),
+                (Operator, "?."),
+                (Identifier, "b"),
+                (Operator, "??"),
+                (Float, "1.5"),
+                (Comment, "// done"),
+                (Keyword, "for"),
+            ]
+        );
+        assert_eq!(
+            kinds("print(\"a b\", True, Nil, x and not y)"),
+            vec![
+                (Identifier, "print"),
+                (Punctuation, "("),
+                (Str, "\"a b\""),
+                (Punctuation, ","),
+                (Boolean, "True"),
+                (Punctuation, ","),
+                (Nil, "Nil"),
+                (Punctuation, ","),
+                (Identifier, "x"),
+                (Operator, "and"),
+                (Operator, "not"),
+                (Identifier, "y"),
+                (Punctuation, ")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        use TokenKind::*;
+
+        assert_eq!(
+            kinds("#!/usr/bin/env liva\n/* a /* b */\n*/ x //"),
+            vec![
+                (Comment, "#!/usr/bin/env liva"),
+                (Comment, "/* a /* b */\n*/"),
+                (Identifier, "x"),
+                (Comment, "//"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tables_agree() {
+        use crate::parser::tokens::{SYMBOL_TOKENS, WORD_TOKENS};
+
+        // Comment delimiters and line breaks are no symbols of the lexer
+        let symbols = SYMBOL_TOKENS
+            .iter()
+            .filter(|token| !["//", "/*", "*/", "#!", "\n"].contains(token));
+        for token in symbols {
+            assert!(
+                SYMBOLS.iter().any(|(symbol, _)| symbol == token),
+                "`{}` is missing in SYMBOLS",
+                token
+            );
+        }
+        for token in WORD_TOKENS.iter() {
+            assert!(
+                KEYWORDS.contains(token) || WORD_OPERATORS.contains(token),
+                "`{}` is neither a keyword nor an operator",
+                token
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let err = tokenize("let x = 1\nx = $").unwrap_err();
+        assert_eq!(err.position, Position::new(2, 5));
+        assert_eq!(err.found, '$');
+    }
+}
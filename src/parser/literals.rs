@@ -2,13 +2,13 @@
 
 use nom::sequence::delimited;
 use crate::Span;
-use std::borrow::Borrow;
 use std::collections::HashMap;
 
 use crate::parser::{
     comment::parse_comment,
     expression::{parse_expression, Expression},
-    tokens::KEYWORDS,
+    position::TokenSpan,
+    tokens::{is_reserved, word_end},
     Res,
 };
 use nom::number::complete::recognize_float;
@@ -19,7 +19,7 @@ use nom::{
     character::complete::char,
     character::complete::{alpha1, alphanumeric1 as alphanumeric, digit1, one_of, space1},
     combinator::{cut, map, opt, recognize},
-    error::context,
+    error::{context, ContextError, ErrorKind, ParseError, VerboseError},
     error_position,
     multi::{many0, separated_list0},
     sequence::{pair, preceded, separated_pair, terminated, tuple},
@@ -27,14 +27,19 @@ use nom::{
 };
 
 #[derive(Clone, Debug, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token<'a, T> {
     pub value: T,
-    pub pos: Span<'a>,
+    #[cfg_attr(feature = "serde", serde(rename = "range"))]
+    pub pos: TokenSpan<'a>,
 }
 
 impl<'a, T> Token<'a, T> {
     pub fn new(value: T, pos: Span) -> Token<T> {
-        Token { value, pos }
+        Token {
+            value,
+            pos: TokenSpan::Source(pos),
+        }
     }
 }
 
@@ -42,10 +47,15 @@ pub type Variable<'a> = Token<'a, Identifier<'a>>;
 
 // Collection of all possible literals
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum Literal<'a> {
     Str(Token<'a, String>),
     Boolean(Token<'a, bool>),
-    Nil(Span<'a>),
+    Nil(TokenSpan<'a>),
     Float(Token<'a, f64>),
     Int(Token<'a, i32>), // Array(Vec<Literal<'a>>),
                          // Map(HashMap<String, Literal<'a>>),
@@ -55,6 +65,11 @@ pub enum Literal<'a> {
 /// Collections are seperated from literals, as they allow
 /// for expressions
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum Collection<'a> {
     Array(Vec<Expression<'a>>),
     Map(HashMap<String, Expression<'a>>),
@@ -67,10 +82,6 @@ where
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
     }
-
-    fn ne(&self, other: &Self) -> bool {
-        self.value != other.value
-    }
 }
 
 /// Contains the name of a identifier, which has to start with letter, but can contain
@@ -80,7 +91,7 @@ pub type Identifier<'a> = &'a str;
 pub(crate) fn parse_variable_raw(input: Span) -> Res<Span> {
     let res = context(
         "Variable",
-        // Keywords should not be parsed
+        // Reserved words should not be parsed
         recognize(pair(
             alt((alpha1, tag("_"))),
             many0(alt((alphanumeric, tag("_")))),
@@ -88,7 +99,7 @@ pub(crate) fn parse_variable_raw(input: Span) -> Res<Span> {
     )(input);
 
     if let IResult::Ok((_, name)) = res {
-        if KEYWORDS.contains(name.borrow()) {
+        if is_reserved(name.fragment()) {
             return Err(nom::Err::Error(error_position!(
                 input,
                 nom::error::ErrorKind::Tag
@@ -103,10 +114,15 @@ pub(crate) fn parse_variable(input: Span) -> Res<Variable> {
         .map(|(next_input, res)| (next_input, Token::new(*res.fragment(), res)))
 }
 
-pub(crate) fn sp(input: Span) -> Res<&str> {
+/// Skips white space and comments
+pub(crate) fn sp(input: Span<'_>) -> Res<'_, &str> {
+    tuple((ws, many0(tuple((parse_comment, ws)))))(input).map(|(next_input, _)| (next_input, ""))
+}
+
+/// Skips white space, but in contrast to [`sp`] no comment
+pub(crate) fn ws(input: Span<'_>) -> Res<'_, Span<'_>> {
     let chars = " \t\r\n";
-    tuple((take_while(move |c| chars.contains(c)), opt(parse_comment)))(input)
-        .map(|(next_input, _)| (next_input, ""))
+    take_while(move |c| chars.contains(c))(input)
 }
 
 fn alphanumeric_ws(input: Span) -> Res<Span> {
@@ -114,10 +130,11 @@ fn alphanumeric_ws(input: Span) -> Res<Span> {
 }
 
 fn parse_string(input: Span) -> Res<Span> {
-    escaped(alphanumeric_ws, '\\', one_of("\"n\\"))(input)
+    // `escaped` does not accept empty input, but empty strings are fine
+    recognize(opt(escaped(alphanumeric_ws, '\\', one_of("\"n\\"))))(input)
 }
 
-fn parse_str_raw(input: Span) -> Res<Span> {
+pub(crate) fn parse_str_raw(input: Span) -> Res<Span> {
     alt((parse_str_raw_single, parse_str_raw_double))(input)
 }
 fn parse_str_raw_single(input: Span) -> Res<Span> {
@@ -159,18 +176,16 @@ fn parse_float(input: Span) -> Res<Literal> {
 }
 
 fn parse_int(input: Span) -> Res<Literal> {
-    context(
-        "Int",
-        recognize(digit1)
-    )(input).map(|(next_input, res)| {
-        (
-            next_input,
-            Literal::Int(Token::new(
-                res.parse::<i32>().expect("Could not parse int"),
-                res,
-            )),
-        )
-    })
+    // Literals which do not fit into an int are an error and not a panic
+    let (next_input, res) = context("Int", recognize(digit1))(input)?;
+    match res.parse::<i32>() {
+        Ok(value) => Ok((next_input, Literal::Int(Token::new(value, res)))),
+        Err(_) => Err(nom::Err::Failure(VerboseError::add_context(
+            input,
+            "Int",
+            VerboseError::from_error_kind(input, ErrorKind::TooLarge),
+        ))),
+    }
 }
 
 fn parse_num(input: Span) -> Res<Literal> {
@@ -183,12 +198,12 @@ fn parse_num(input: Span) -> Res<Literal> {
 }
 
 fn parse_false(input: Span) -> Res<Literal> {
-    context("False", tag_no_case("False"))(input)
+    context("False", terminated(tag_no_case("False"), word_end))(input)
         .map(|(next_input, res)| (next_input, Literal::Boolean(Token::new(false, res))))
 }
 
 fn parse_true(input: Span) -> Res<Literal> {
-    context("True", tag_no_case("True"))(input)
+    context("True", terminated(tag_no_case("True"), word_end))(input)
         .map(|(next_input, res)| (next_input, Literal::Boolean(Token::new(true, res))))
 }
 
@@ -197,8 +212,8 @@ fn parse_boolean(input: Span) -> Res<Literal> {
 }
 
 fn parse_nil(input: Span) -> Res<Literal> {
-    context("Nil", tag_no_case("Nil"))(input)
-        .map(|(next_input, res)| (next_input, Literal::Nil(res)))
+    context("Nil", terminated(tag_no_case("Nil"), word_end))(input)
+        .map(|(next_input, res)| (next_input, Literal::Nil(res.into())))
 }
 
 fn parse_array(input: Span) -> Res<Collection> {
@@ -215,7 +230,7 @@ fn parse_array(input: Span) -> Res<Collection> {
     .map(|(next_input, res)| (next_input, Collection::Array(res)))
 }
 
-pub(crate) fn parse_collection<'a>(input: Span<'a>) -> Res<Collection<'a>> {
+pub(crate) fn parse_collection(input: Span) -> Res<Collection> {
     context("Collection", preceded(sp, alt((parse_array, parse_map))))(input)
 }
 
@@ -250,7 +265,7 @@ fn parse_map(input: Span) -> Res<Collection> {
     .map(|(next_input, res)| (next_input, Collection::Map(res)))
 }
 
-pub(crate) fn parse_literal<'a>(input: Span<'a>) -> Res<Literal<'a>> {
+pub(crate) fn parse_literal(input: Span) -> Res<Literal> {
     context(
         "Literal",
         preceded(
@@ -301,7 +316,7 @@ mod tests {
     fn parse_nil_test() {
         let string = "Nil";
         let (_, res) = parse_nil(Span::new(string)).unwrap();
-        assert_eq!(res, Literal::Nil(Span::new("Nil")));
+        assert_eq!(res, Literal::Nil(Span::new("Nil").into()));
     }
 
     // #[ignore]
@@ -370,6 +385,9 @@ mod tests {
                 Span::new("Hello    World")
             ))
         );
+
+        let (_, res) = parse_str(Span::new("\"\"")).unwrap();
+        assert_eq!(res, Literal::Str(Token::new(String::new(), Span::new(""))));
     }
 
     #[test]
@@ -385,6 +403,12 @@ mod tests {
         let string = "1";
         let (_, res) = parse_literal(Span::new(string)).unwrap();
         assert_eq!(res, Literal::Int(Token::new(1, Span::new("1"))));
+
+        let string = "99999999999999999999";
+        assert!(matches!(
+            parse_literal(Span::new(string)),
+            Err(nom::Err::Failure(_))
+        ));
     }
 
     #[test]
@@ -462,7 +486,7 @@ mod tests {
     fn parse_literal_test() {
         let string = "Nil";
         let (_, res) = parse_literal(Span::new(string)).unwrap();
-        assert_eq!(res, Literal::Nil(Span::new("Nil")))
+        assert_eq!(res, Literal::Nil(Span::new("Nil").into()))
     }
 
     #[test]
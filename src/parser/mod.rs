@@ -2,21 +2,191 @@
 
 mod comment;
 pub mod expression;
+pub mod incremental;
+pub mod lexer;
 pub mod literals;
+pub mod module;
+pub mod position;
+#[cfg(feature = "serde")]
+pub mod schema;
 pub mod statement;
 pub mod tokens;
 
-use nom::{error::VerboseError, IResult};
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+
+use nom::{
+    error::{ContextError, ErrorKind, ParseError, VerboseError, VerboseErrorKind},
+    IResult,
+};
 
 use nom_locate::LocatedSpan;
 
-use crate::parser::statement::{parse_block, Block};
+use crate::parser::{
+    literals::sp,
+    position::Position,
+    statement::{parse_module_block, Block},
+};
 
 pub type Span<'a> = LocatedSpan<&'a str>;
 
 pub type Res<'a, O> = IResult<Span<'a>, O, VerboseError<Span<'a>>>;
 
+/// Maximum nesting of expressions and blocks, deeper nesting fails to parse
+/// with "nesting too deep"
+pub const MAX_NESTING: usize = 256;
+
+/// Context of the error of too deep nesting
+const NESTING_TOO_DEEP: &str = "nesting too deep";
+
+/// The parser recurses natively for every nested expression and block. The
+/// stack is grown by [`STACK_GROWTH`] bytes, if less than [`STACK_RED_ZONE`]
+/// bytes are left, so parsing up to [`MAX_NESTING`] does not depend on the
+/// size of the thread stack
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_GROWTH: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    /// Nesting of the parsers running on this thread
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Leaves a nesting level, even if the parser panics
+struct NestingGuard;
+
+impl Drop for NestingGuard {
+    fn drop(&mut self) {
+        NESTING.with(|nesting| nesting.set(nesting.get() - 1));
+    }
+}
+
+/// Runs the parser one nesting level deeper and fails above [`MAX_NESTING`]
+pub(crate) fn nested<'a, O>(
+    input: Span<'a>,
+    parser: impl FnOnce(Span<'a>) -> Res<'a, O>,
+) -> Res<'a, O> {
+    let depth = NESTING.with(|nesting| {
+        nesting.set(nesting.get() + 1);
+        nesting.get()
+    });
+    let _guard = NestingGuard;
+    if depth > MAX_NESTING {
+        let err = VerboseError::from_error_kind(input, ErrorKind::TooLarge);
+        return Err(nom::Err::Failure(VerboseError::add_context(
+            input,
+            NESTING_TOO_DEEP,
+            err,
+        )));
+    }
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || parser(input))
+}
+
 /// Entry point to the parser, which parse the liva language
 pub fn parse_source(input: Span) -> Res<Block> {
-    parse_block(input)
+    parse_module_block(input)
+}
+
+/// The source code could not be parsed completely
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// Position of the first input which could not be parsed
+    pub position: Position,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+impl Error for SyntaxError {}
+
+/// Parses the whole source code, in contrast to [`parse_source`] remaining
+/// input is an error
+pub fn parse_complete(input: Span) -> Result<Block, SyntaxError> {
+    let (rest, block) = parse_source(input).map_err(|err| parse_error(input, err))?;
+    expect_end(rest)?;
+    Ok(block)
+}
+
+/// Converts the error of a parse of the input
+pub(crate) fn parse_error(input: Span, err: nom::Err<VerboseError<Span>>) -> SyntaxError {
+    match err {
+        nom::Err::Error(err) | nom::Err::Failure(err) => syntax_error(err),
+        nom::Err::Incomplete(_) => SyntaxError {
+            position: Position::from(input),
+            message: String::from("unexpected end of input"),
+        },
+    }
+}
+
+/// Fails unless the rest of a parse is only white space and comments
+pub(crate) fn expect_end(rest: Span) -> Result<(), SyntaxError> {
+    // Only the white space skipped by the parser counts, like the lexer
+    // does not accept other white space either
+    let rest = sp(rest).map(|(rest, _)| rest).unwrap_or(rest);
+    if !rest.fragment().is_empty() {
+        let line = rest.fragment().lines().next().unwrap_or_default();
+        let message = match line.trim() {
+            "" => format!(
+                "unexpected character {:?}",
+                line.chars().next().unwrap_or_default()
+            ),
+            line => format!("unexpected `{}`", line),
+        };
+        return Err(SyntaxError {
+            position: Position::from(rest),
+            message,
+        });
+    }
+    Ok(())
+}
+
+/// Reports the innermost error together with the innermost context around it
+fn syntax_error(err: VerboseError<Span>) -> SyntaxError {
+    let position = err
+        .errors
+        .first()
+        .map_or(Position::new(1, 1), |(span, _)| Position::from(*span));
+    let message = match err.errors.iter().find_map(|(_, kind)| match kind {
+        VerboseErrorKind::Context(context) => Some(*context),
+        _ => None,
+    }) {
+        Some(NESTING_TOO_DEEP) => String::from(NESTING_TOO_DEEP),
+        Some(context) => format!("invalid {}", context),
+        None => String::from("invalid syntax"),
+    };
+    SyntaxError { position, message }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_complete() {
+        assert!(parse_complete(Span::new("let x = 1\n// done\n")).is_ok());
+        assert!(parse_complete(Span::new("let x = 1\n// done")).is_ok());
+        assert!(parse_complete(Span::new("#!/usr/bin/env liva\nprint(1)\n")).is_ok());
+        assert!(parse_complete(Span::new(
+            "/* header\n   /* nested */ */\nlet x = /* one */ 1 /* two */ + 2\nprint(x) // end\n//"
+        ))
+        .is_ok());
+
+        let err = parse_complete(Span::new("let x = 1\n/* open\n")).unwrap_err();
+        assert_eq!(err.position, Position::new(2, 1));
+        assert_eq!(err.message, "invalid BlockComment");
+
+        let err = parse_complete(Span::new("let x = 1\nlet = 2\n")).unwrap_err();
+        assert_eq!(err.position, Position::new(2, 1));
+        assert_eq!(err.message, "unexpected `let = 2`");
+        assert_eq!(err.to_string(), "2:1: unexpected `let = 2`");
+
+        let err = parse_complete(Span::new("let x = 1\n\u{2000}\n")).unwrap_err();
+        assert_eq!(err.position, Position::new(2, 1));
+        assert_eq!(err.message, "unexpected character '\\u{2000}'");
+    }
 }
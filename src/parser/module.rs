@@ -0,0 +1,118 @@
+//! A module represents the content of a single liva source file
+//!
+//! Top level functions, classes and variables can be exported with
+//! `pub` or `export`, all other names are private to the module.
+//!
+//! ```code
+//! pub fun area(r)
+//!     return PI * r * r
+//! end
+//!
+//! export let PI = 3.14
+//! ```
+
+use crate::parser::{
+    literals::Variable,
+    parse_source,
+    statement::{import::Import, Block, Statement},
+    Res, Span,
+};
+
+/// The parsed content of a source file
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Module<'a> {
+    pub block: Block<'a>,
+}
+
+/// The kind of declaration an exported name refers to
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExportKind {
+    Function,
+    Class,
+    Variable,
+}
+
+/// A name which is visible to other modules
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Export<'a> {
+    /// Name of the declaration, the token holds the span of the name
+    pub name: Variable<'a>,
+    pub kind: ExportKind,
+}
+
+impl<'a> Module<'a> {
+    /// Lists all exported names in the order they are declared
+    pub fn exports(&self) -> Vec<Export<'a>> {
+        self.block
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Fun(function) if function.exported => Some(Export {
+                    name: function.name,
+                    kind: ExportKind::Function,
+                }),
+                Statement::Class(class) if class.exported => Some(Export {
+                    name: class.name,
+                    kind: ExportKind::Class,
+                }),
+                Statement::LAssignment(assignment) if assignment.exported => Some(Export {
+                    name: assignment.variable,
+                    kind: ExportKind::Variable,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lists all top level imports of the module
+    pub fn imports(&self) -> Vec<&Import<'a>> {
+        self.block
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Import(import) => Some(import),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Parses a complete source file into a [`Module`]
+pub fn parse_module(input: Span) -> Res<Module> {
+    parse_source(input).map(|(next_input, block)| (next_input, Module { block }))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_exports() {
+        let string = "import math\n\npub fun area(r)\n    return r * r\nend\n\nfun helper() end\n\nexport class Circle end\n\npub let PI = 3.14\nlet e = 2.71\n";
+        let (_, module) = parse_module(Span::new(string)).unwrap();
+        let exports = module.exports();
+
+        let names: Vec<(&str, ExportKind)> =
+            exports.iter().map(|e| (e.name.value, e.kind)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("area", ExportKind::Function),
+                ("Circle", ExportKind::Class),
+                ("PI", ExportKind::Variable)
+            ]
+        );
+
+        let area = exports[0].name.pos;
+        assert_eq!(area.location_line(), 3);
+        assert_eq!(area.get_utf8_column(), 9);
+
+        assert_eq!(module.imports().len(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+//! Positions of nodes in the source code
+
+use crate::parser::{
+    expression::{ExprOrVarname, Expression, PrefixExpr},
+    literals::{Collection, Literal},
+    statement::{declaration::match_statement::Pattern, Statement},
+    Span,
+};
+
+/// Line and column of a location in the source code, both start at 1.
+/// In contrast to a [`Span`] it outlives the parsed source code
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub line: u32,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: u32, column: usize) -> Self {
+        Position { line, column }
+    }
+}
+
+impl<'a> From<Span<'a>> for Position {
+    fn from(span: Span<'a>) -> Self {
+        Position {
+            line: span.location_line(),
+            column: span.get_utf8_column(),
+        }
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Start and exclusive end of a span in the source code
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl<'a> From<Span<'a>> for Range {
+    fn from(span: Span<'a>) -> Self {
+        let start = Position::from(span);
+        let fragment = *span.fragment();
+        let end = match fragment.rfind('\n') {
+            Some(idx) => Position::new(
+                start.line + fragment.matches('\n').count() as u32,
+                fragment[idx + 1..].chars().count() + 1,
+            ),
+            None => Position::new(start.line, start.column + fragment.chars().count()),
+        };
+        Range { start, end }
+    }
+}
+
+impl<'a> From<TokenSpan<'a>> for Position {
+    fn from(span: TokenSpan<'a>) -> Self {
+        match span {
+            TokenSpan::Source(span) => Position::from(span),
+            TokenSpan::Location(location) => location.start,
+        }
+    }
+}
+
+impl<'a> From<TokenSpan<'a>> for Range {
+    fn from(span: TokenSpan<'a>) -> Self {
+        match span {
+            TokenSpan::Source(span) => Range::from(span),
+            TokenSpan::Location(location) => Range {
+                start: location.start,
+                end: location.end,
+            },
+        }
+    }
+}
+
+/// Owned location of a token, which is kept when an AST is deserialized
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    pub start: Position,
+    pub end: Position,
+    /// Byte offset of the start in the source code
+    pub offset: usize,
+}
+
+impl<'a> From<Span<'a>> for Location {
+    fn from(span: Span<'a>) -> Self {
+        let Range { start, end } = Range::from(span);
+        Location {
+            start,
+            end,
+            offset: span.location_offset(),
+        }
+    }
+}
+
+/// Location of a token. Parsed tokens point into the source code, while
+/// deserialized tokens only know their [`Location`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenSpan<'a> {
+    Source(Span<'a>),
+    Location(Location),
+}
+
+impl<'a> TokenSpan<'a> {
+    /// Byte offset of the token in the source code
+    pub fn location_offset(&self) -> usize {
+        match self {
+            TokenSpan::Source(span) => span.location_offset(),
+            TokenSpan::Location(location) => location.offset,
+        }
+    }
+
+    pub fn location_line(&self) -> u32 {
+        Position::from(*self).line
+    }
+
+    pub fn get_utf8_column(&self) -> usize {
+        Position::from(*self).column
+    }
+
+    /// Text of the token, which is empty for deserialized tokens
+    pub fn fragment(&self) -> &&'a str {
+        match self {
+            TokenSpan::Source(span) => span.fragment(),
+            TokenSpan::Location(_) => &"",
+        }
+    }
+}
+
+impl<'a> From<Span<'a>> for TokenSpan<'a> {
+    fn from(span: Span<'a>) -> Self {
+        TokenSpan::Source(span)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for TokenSpan<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TokenSpan::Source(span) => Location::from(*span).serialize(serializer),
+            TokenSpan::Location(location) => location.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, 'a> serde::Deserialize<'de> for TokenSpan<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Location::deserialize(deserializer).map(TokenSpan::Location)
+    }
+}
+
+/// Nodes of the AST, which can point to a location in the source code
+///
+/// Only names and literals keep their location while parsing, so the span
+/// of a node is the span of its first name or literal.
+pub trait Spanned<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>>;
+}
+
+impl<'a> Spanned<'a> for Literal<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>> {
+        Some(match self {
+            Literal::Str(token) => token.pos,
+            Literal::Boolean(token) => token.pos,
+            Literal::Nil(span) => *span,
+            Literal::Float(token) => token.pos,
+            Literal::Int(token) => token.pos,
+        })
+    }
+}
+
+impl<'a> Spanned<'a> for Expression<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>> {
+        match self {
+            Expression::Literal(literal) => literal.span(),
+            Expression::Call(call) => call
+                .callee
+                .map(|callee| callee.pos)
+                .or_else(|| call.args.iter().find_map(Spanned::span)),
+            Expression::BinaryOp(op) => op.left.span().or_else(|| op.right.span()),
+            Expression::UnaryOp(op) => op.operand.span(),
+            Expression::PrefixExpr(prefix_expr) => prefix_expr.span(),
+            Expression::Collection(Collection::Array(values)) => {
+                values.iter().find_map(Spanned::span)
+            }
+            Expression::Collection(Collection::Map(values)) => values
+                .values()
+                .filter_map(Spanned::span)
+                .min_by_key(|span| span.location_offset()),
+            Expression::Conditional(conditional) => conditional.cond.span(),
+            Expression::NilCoalesce(coalesce) => coalesce.left.span(),
+        }
+    }
+}
+
+impl<'a> Spanned<'a> for PrefixExpr<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>> {
+        match &self.prefix {
+            ExprOrVarname::Varname(name) => Some(name.pos),
+            ExprOrVarname::Exp(expr) => expr.span(),
+        }
+    }
+}
+
+impl<'a> Spanned<'a> for Pattern<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>> {
+        match self {
+            Pattern::Literal(literal) => literal.span(),
+            Pattern::Range(range) => range.start.span(),
+            Pattern::Wildcard(span) => Some(*span),
+            Pattern::Binding(name) => Some(name.pos),
+            Pattern::Array(patterns) => patterns.iter().find_map(Spanned::span),
+            Pattern::Map(patterns) => patterns
+                .values()
+                .filter_map(Spanned::span)
+                .min_by_key(|span| span.location_offset()),
+        }
+    }
+}
+
+impl<'a> Spanned<'a> for Statement<'a> {
+    fn span(&self) -> Option<TokenSpan<'a>> {
+        match self {
+            Statement::Assignment(assignment) => assignment.variable.span(),
+            Statement::LAssignment(assignment) => Some(assignment.variable.pos),
+            Statement::FuncCall(call) => call.span(),
+            Statement::While(while_stmt) => while_stmt.cond.span(),
+            Statement::For(for_stmt) => Some(for_stmt.iter_item.pos),
+            Statement::If(if_stmt) => if_stmt.cond.span(),
+            Statement::Match(match_stmt) => match_stmt.expr.span(),
+            Statement::Try(try_stmt) => try_stmt.block.statements.iter().find_map(Spanned::span),
+            Statement::Fun(function) => Some(function.name.pos),
+            Statement::Class(class) => Some(class.name.pos),
+            Statement::Return(return_stmt) => return_stmt.values.iter().find_map(Spanned::span),
+            Statement::Import(import) => import.path.first().map(|name| name.pos),
+            Statement::Raise(raise) => raise.value.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use nom::bytes::complete::take;
+
+    #[test]
+    fn test_position_from_span() {
+        let input = Span::new("let x = 3\nlet y = 4");
+        let (rest, _) = take::<_, _, ()>(14usize)(input).unwrap();
+        assert_eq!(Position::from(rest), Position::new(2, 5));
+    }
+
+    #[test]
+    fn test_range_from_span() {
+        let input = Span::new("let s = \"ä\nb\"");
+        let (rest, _) = take::<_, _, ()>(8usize)(input).unwrap();
+        let (_, string) = take::<_, _, ()>(5usize)(rest).unwrap();
+        assert_eq!(
+            Range::from(string),
+            Range {
+                start: Position::new(1, 9),
+                end: Position::new(2, 3)
+            }
+        );
+
+        let (_, name) = take::<_, _, ()>(3usize)(input).unwrap();
+        assert_eq!(Range::from(name).end, Position::new(1, 4));
+    }
+}
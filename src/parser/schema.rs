@@ -0,0 +1,176 @@
+//! Versioned JSON representation of the AST
+//!
+//! With the `serde` feature enabled all AST nodes implement `Serialize` and
+//! `Deserialize`. The format is described by the JSON schema in
+//! [`JSON_SCHEMA`]:
+//!
+//! * Enums are tagged with the name of the variant in `kind`, their content is
+//!   stored in `node`, e.g. `{"kind": "Int", "node": {...}}`
+//! * Structs are objects with one entry for every field
+//! * Operators are strings like `"Add"`
+//! * Spans are stored as a location `{"start": {"line": 1, "column": 1}, "end": ..., "offset": 0}`,
+//!   lines and columns start at 1, the end is exclusive and the offset is the
+//!   byte offset of the start
+//!
+//! A deserialized AST does not point into the source code anymore, its tokens
+//! only keep their [`Location`](crate::parser::position::Location). Names
+//! still borrow from the JSON text, so an AST can only be deserialized from a
+//! string which outlives it, like with `serde_json::from_str`. Functions for
+//! owned data like `serde_json::from_reader` do not accept a [`Document`], and
+//! deserializing one from a reader or a `serde_json::Value` fails with
+//! "expected a borrowed string".
+//!
+//! Tools should exchange a [`Document`], which stores the version of the
+//! schema next to the AST. Every change to the format increments
+//! [`SCHEMA_VERSION`].
+//!
+//! ## Example
+//! ```rust
+//! use liva_parser::schema::Document;
+//! use liva_parser::{parse_source, Span};
+//!
+//! let (_, block) = parse_source(Span::new("let x = 3")).unwrap();
+//! let json = serde_json::to_string(&Document::new(block)).unwrap();
+//! assert!(json.starts_with(r#"{"version":1,"ast":{"statements":[{"kind":"LAssignment""#));
+//! ```
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use crate::parser::statement::Block;
+
+/// Version of the JSON format of the AST
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON schema of a [`Document`]
+pub const JSON_SCHEMA: &str = include_str!("../../schema/ast.schema.json");
+
+/// The AST of a source file together with the version of its format
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'a"))]
+pub struct Document<'a> {
+    #[serde(deserialize_with = "version")]
+    pub version: u32,
+    pub ast: Block<'a>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(ast: Block<'a>) -> Self {
+        Document {
+            version: SCHEMA_VERSION,
+            ast,
+        }
+    }
+}
+
+/// Rejects documents of other versions, as their format may differ
+fn version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version != SCHEMA_VERSION {
+        return Err(D::Error::custom(format!(
+            "unsupported schema version {}, expected {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::position::{Range, Spanned};
+    use crate::parser::statement::Statement;
+    use crate::parser::{parse_source, Span};
+
+    #[test]
+    fn test_roundtrip() {
+        let source = "fun add(a, b)\n    return a + b\nend\nlet xs = [add(1, 2.5), \"s\", True]\n";
+        let (_, block) = parse_source(Span::new(source)).unwrap();
+
+        let json = serde_json::to_string(&Document::new(block.clone())).unwrap();
+        let document: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(document.ast, block);
+
+        // Deserialized spans keep their location
+        let locations = |block: &Block| -> Vec<_> {
+            block
+                .statements
+                .iter()
+                .map(|s| {
+                    s.span()
+                        .map(|span| (span.location_offset(), Range::from(span)))
+                })
+                .collect()
+        };
+        assert_eq!(locations(&block), locations(&document.ast));
+    }
+
+    #[test]
+    fn test_roundtrip_long_line() {
+        let source = format!("let s = \"{}\"\nlet y = s", "a".repeat(2000));
+        let (_, block) = parse_source(Span::new(&source)).unwrap();
+
+        let json = serde_json::to_string(&block).unwrap();
+        let restored: Block = serde_json::from_str(&json).unwrap();
+
+        let span = restored.statements[0].span().unwrap();
+        assert_eq!(
+            Range::from(span),
+            Range::from(block.statements[0].span().unwrap())
+        );
+        assert_eq!(span.get_utf8_column(), 5);
+
+        let string = match &restored.statements[0] {
+            Statement::LAssignment(assignment) => assignment.expression.span().unwrap(),
+            stmt => panic!("expected an assignment, got {:?}", stmt),
+        };
+        assert_eq!(string.location_offset(), 9);
+        assert_eq!(Range::from(string).end.column, 2010);
+    }
+
+    #[test]
+    fn test_node_format() {
+        let (_, block) = parse_source(Span::new("x = -y")).unwrap();
+        let json = serde_json::to_value(&block.statements[0]).unwrap();
+
+        assert_eq!(json["kind"], "Assignment");
+        let expression = &json["node"]["expression"];
+        assert_eq!(expression["kind"], "UnaryOp");
+        assert_eq!(expression["node"]["op"], "Sub");
+
+        let operand = &expression["node"]["operand"]["node"]["prefix"];
+        assert_eq!(operand["kind"], "Varname");
+        assert_eq!(operand["node"]["value"], "y");
+        assert_eq!(
+            operand["node"]["range"],
+            serde_json::json!({
+                "start": {"line": 1, "column": 6},
+                "end": {"line": 1, "column": 7},
+                "offset": 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_names_borrow_from_the_json() {
+        let (_, block) = parse_source(Span::new("let x = y")).unwrap();
+        let json = serde_json::to_string(&Document::new(block)).unwrap();
+
+        assert!(serde_json::from_str::<Document>(&json).is_ok());
+
+        let mut reader = serde_json::Deserializer::from_reader(json.as_bytes());
+        let err = Document::deserialize(&mut reader).unwrap_err();
+        assert!(err.to_string().contains("expected a borrowed string"));
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let err = Document::deserialize(value).unwrap_err();
+        assert!(err.to_string().contains("expected a borrowed string"));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let json = r#"{"version": 0, "ast": {"statements": [], "return_stmt": null}}"#;
+        let err = serde_json::from_str::<Document>(json).unwrap_err();
+        assert!(err.to_string().contains("unsupported schema version 0"));
+    }
+}
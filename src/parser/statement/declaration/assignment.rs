@@ -12,6 +12,8 @@ use nom::{
 };
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Assignment<'a> {
     pub variable: PrefixExpr<'a>,
     pub expression: Expression<'a>,
@@ -20,7 +22,16 @@ pub struct Assignment<'a> {
 /// A assignment is a statement, while a re-assignemnt is an epression
 /// so we only parse a statement here, therefore is the let mandatory
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct LAssignment<'a> {
+    /// If the variable is visible to other modules.
+    /// Only top level variables can be exported
+    pub exported: bool,
+
+    /// Documentation from the comment lines directly above the declaration
+    pub doc: Option<String>,
+
     pub variable: Variable<'a>,
     pub expression: Expression<'a>,
 }
@@ -71,6 +82,8 @@ pub(crate) fn parse_lassignment(input: Span) -> Res<LAssignment> {
         (
             next_input,
             LAssignment {
+                exported: false,
+                doc: None,
                 variable,
                 expression,
             },
@@ -94,6 +107,8 @@ mod tests {
         assert_eq!(
             res,
             LAssignment {
+                exported: false,
+                doc: None,
                 variable: Token::new("x", Span::new("x")),
                 expression: Expression::Literal(Literal::Int(Token::new(3, Span::new("3"))))
             }
@@ -108,6 +123,8 @@ mod tests {
         assert_eq!(
             res,
             LAssignment {
+                exported: false,
+                doc: None,
                 variable: Token::new("x", Span::new("x")),
                 expression: Expression::Collection(Collection::Array(vec![
                     Expression::Literal(Literal::Int(Token::new(1, Span::new("1")))),
@@ -1,8 +1,8 @@
 use super::function::{parse_function, Function};
 
 use crate::parser::{
-    literals::parse_variable_raw,
-    literals::sp,
+    comment::parse_doc,
+    literals::{parse_variable, sp, Variable},
     statement::opt_line_ending,
     tokens::{class, end},
     Res, Span,
@@ -15,8 +15,17 @@ use nom::{
 };
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Class<'a> {
-    pub name: String,
+    /// If the class is visible to other modules.
+    /// Only top level classes can be exported
+    pub exported: bool,
+
+    /// Documentation from the comment lines directly above the declaration
+    pub doc: Option<String>,
+
+    pub name: Variable<'a>,
     pub methods: Vec<Function<'a>>,
 }
 
@@ -35,30 +44,34 @@ pub fn parse_class(input: Span) -> Res<Class> {
         (
             next_input,
             Class {
-                name: String::from(*res.0.fragment()),
+                exported: false,
+                doc: None,
+                name: res.0,
                 methods: res.1,
             },
         )
     })
 }
 
-fn parse_class_name(input: Span) -> Res<Span> {
+fn parse_class_name(input: Span) -> Res<Variable> {
     context(
         "ClassName",
-        preceded(preceded(sp, class), preceded(sp, parse_variable_raw)),
+        preceded(preceded(sp, class), preceded(sp, parse_variable)),
     )(input)
 }
 
 fn parse_methods(input: Span) -> Res<Vec<Function>> {
     context(
         "Methods",
-        terminated(
-            preceded(sp, preceded(sp, many0(parse_function))),
-            opt_line_ending,
-        ),
+        terminated(terminated(many0(parse_method), parse_doc), opt_line_ending),
     )(input)
 }
 
+fn parse_method(input: Span) -> Res<Function> {
+    tuple((parse_doc, parse_function))(input)
+        .map(|(next_input, (doc, method))| (next_input, Function { doc, ..method }))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -77,7 +90,7 @@ mod tests {
     fn test_prase_class_name() {
         let string = " class Hello ";
         let (_, res) = parse_class_name(Span::new(string)).unwrap();
-        assert_eq!(res.fragment(), &"Hello")
+        assert_eq!(res.value, "Hello")
     }
 
     #[test]
@@ -87,13 +100,19 @@ mod tests {
         assert_eq!(
             res,
             Class {
-                name: String::from("Hello"),
+                exported: false,
+                doc: None,
+                name: Token::new("Hello", Span::new("Hello")),
                 methods: vec![Function {
-                    name: "foo",
+                    exported: false,
+                    doc: None,
+                    name: Token::new("foo", Span::new("foo")),
                     parameters: vec![],
                     block: Block {
                         return_stmt: None,
                         statements: vec![Statement::LAssignment(LAssignment {
+                            exported: false,
+                            doc: None,
                             variable: Token::new("some", Span::new("some")),
                             expression: Expression::Literal(Literal::Int(Token::new(
                                 1,
@@ -113,9 +132,13 @@ mod tests {
         assert_eq!(
             res,
             Class {
-                name: String::from("Hello"),
+                exported: false,
+                doc: None,
+                name: Token::new("Hello", Span::new("Hello")),
                 methods: vec![Function {
-                    name: "method1",
+                    exported: false,
+                    doc: None,
+                    name: Token::new("method1", Span::new("method1")),
                     parameters: vec![],
                     block: Block {
                         return_stmt: Some(ReturnStmt {
@@ -139,7 +162,9 @@ mod tests {
         assert_eq!(
             res,
             Class {
-                name: String::from("Test"),
+                exported: false,
+                doc: None,
+                name: Token::new("Test", Span::new("Test")),
                 methods: vec![]
             }
         )
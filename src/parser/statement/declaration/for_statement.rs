@@ -11,6 +11,8 @@ use crate::parser::tokens::{end, ldo, lfor, lin};
 use crate::parser::{Res, Span};
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct For<'a> {
     pub iter_item: Variable<'a>,
     pub iterator: Expression<'a>,
@@ -35,7 +37,7 @@ pub fn parse_for(input: Span) -> Res<For> {
     })
 }
 
-fn parse_iter_item<'a>(input: Span<'a>) -> Res<Variable<'a>> {
+fn parse_iter_item(input: Span) -> Res<Variable> {
     context(
         "ForIterItem",
         preceded(preceded(sp, lfor), preceded(sp, parse_variable)),
@@ -97,6 +99,8 @@ mod tests {
                 })),
                 block: Block {
                     statements: vec![Statement::LAssignment(LAssignment {
+                        exported: false,
+                        doc: None,
                         variable: Token::new("y", Span::new("y")),
                         expression: Expression::Literal(Literal::Int(Token::new(
                             3,
@@ -16,7 +16,7 @@
 //!
 use crate::literals::Variable;
 use crate::parser::{
-    literals::{parse_variable, parse_variable_raw, sp, Identifier},
+    literals::{parse_variable, sp},
     statement::opt_line_ending,
     statement::parse_block,
     statement::Block,
@@ -36,9 +36,18 @@ use nom::{
 // TODO: Function.name does not have to be a literal, but can be str
 /// Represents a function declaration.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Function<'a> {
+    /// If the function is visible to other modules.
+    /// Only top level functions can be exported
+    pub exported: bool,
+
+    /// Documentation from the comment lines directly above the declaration
+    pub doc: Option<String>,
+
     /// Name of the function
-    pub name: Identifier<'a>,
+    pub name: Variable<'a>,
 
     /// Parameter list, of the function
     pub parameters: Vec<Variable<'a>>,
@@ -62,6 +71,8 @@ pub fn parse_function(input: Span) -> Res<Function> {
         (
             next_input,
             Function {
+                exported: false,
+                doc: None,
                 name: res.0,
                 parameters: res.1,
                 block: res.2,
@@ -70,18 +81,17 @@ pub fn parse_function(input: Span) -> Res<Function> {
     })
 }
 
-fn parse_function_name(input: Span) -> Res<&str> {
+fn parse_function_name(input: Span) -> Res<Variable> {
     context(
         "FuncName",
         preceded(
             opt_line_ending,
-            preceded(sp, preceded(fun, preceded(sp, parse_variable_raw))),
+            preceded(sp, preceded(fun, preceded(sp, parse_variable))),
         ),
     )(input)
-    .map(|(next_input, res)| (next_input, *res.fragment()))
 }
 
-fn parse_function_arguments<'a>(input: Span<'a>) -> Res<Vec<Variable>> {
+fn parse_function_arguments(input: Span) -> Res<Vec<Variable>> {
     context(
         "ParameterList",
         preceded(
@@ -110,7 +120,7 @@ mod tests {
     fn test_parse_function_name() {
         let string = "fun hello ()";
         let (_, res) = parse_function_name(Span::new(string)).unwrap();
-        assert_eq!(res, "hello");
+        assert_eq!(res, Token::new("hello", Span::new("hello")));
     }
 
     #[test]
@@ -121,13 +131,17 @@ mod tests {
         assert_eq!(
             res,
             Function {
-                name: "hello",
+                exported: false,
+                doc: None,
+                name: Token::new("hello", Span::new("hello")),
                 parameters: vec![
                     Token::new("x", Span::new("x")),
                     Token::new("y", Span::new("y"))
                 ],
                 block: Block {
                     statements: vec![Statement::LAssignment(LAssignment {
+                        exported: false,
+                        doc: None,
                         variable: Token::new("some", Span::new("some")),
                         expression: Expression::Literal(Literal::Str(Token::new(
                             String::from("1"),
@@ -147,7 +161,9 @@ mod tests {
         assert_eq!(
             res,
             Function {
-                name: "fib",
+                exported: false,
+                doc: None,
+                name: Token::new("fib", Span::new("fib")),
                 parameters: vec![Token::new("n", Span::new("n"))],
                 block: Block {
                     statements: vec![],
@@ -164,7 +180,9 @@ mod tests {
         assert_eq!(
             res,
             Function {
-                name: "fib",
+                exported: false,
+                doc: None,
+                name: Token::new("fib", Span::new("fib")),
                 parameters: vec![Token::new("n", Span::new("n"))],
                 block: Block {
                     statements: vec![],
@@ -179,7 +197,6 @@ mod tests {
         );
     }
 
-    #[test]
     #[test]
     fn test_fun() {
         let string = "fun fib(n)\n    if n == 0  do\n        return 0\n    end\nend";
@@ -188,7 +205,9 @@ mod tests {
         assert_eq!(
             res,
             Function {
-                name: "fib",
+                exported: false,
+                doc: None,
+                name: Token::new("fib", Span::new("fib")),
                 parameters: vec![Token::new("n", Span::new("n"))],
                 block: Block {
                     statements: vec![Statement::If(If {
@@ -24,6 +24,8 @@ use nom::{
 
 /// Struct to represent a If-Block
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct If<'a> {
     /// Expression which will be evaluated. If it yields
     /// a truthy value, the If code block with be executed
@@ -130,6 +132,8 @@ mod tests {
                 stmts: Block {
                     statements: vec![
                         Statement::LAssignment(LAssignment {
+                            exported: false,
+                            doc: None,
                             variable: Token::new("z", Span::new("z")),
                             expression: Expression::BinaryOp(Box::new(BinaryOp {
                                 left: Expression::PrefixExpr(Box::new(PrefixExpr {
@@ -147,6 +151,8 @@ mod tests {
                             })),
                         }),
                         Statement::LAssignment(LAssignment {
+                            exported: false,
+                            doc: None,
                             variable: Token::new("y", Span::new("y")),
                             expression: Expression::Literal(Literal::Int(Token::new(
                                 3,
@@ -0,0 +1,393 @@
+//! Branching on the shape of a value through Match statements
+//!
+//! Every arm consists of a pattern, an optional guard and a code block.
+//! The arms are tried from top to bottom, the first matching arm is executed.
+//!
+//! ```code
+//! match x do
+//!     case 0 do
+//!         // x is exactly 0
+//!     end
+//!     case 1..=9 do
+//!         // x is between 1 and 9
+//!     end
+//!     case [first, _] do
+//!         // x is an array with two elements, the first one is bound to `first`
+//!     end
+//!     case {"name": name} do
+//!         // x is a map with a key "name"
+//!     end
+//!     case n if n < 0 do
+//!         // x is bound to n and negative
+//!     end
+//!     case _ do
+//!         // Everything else
+//!     end
+//! end
+//! ```
+
+use std::collections::HashMap;
+
+use crate::parser::expression::{parse_expression, Expression};
+use crate::parser::literals::{
+    parse_literal, parse_str_raw, parse_variable, sp, Literal, Variable,
+};
+use crate::parser::position::TokenSpan;
+use crate::parser::statement::{parse_block, Block};
+use crate::parser::tokens::{case, end, ldo, lif, lmatch, range, range_inclusive};
+use crate::parser::{Res, Span};
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{alphanumeric1, char},
+    combinator::{cut, map, not, opt},
+    error::context,
+    multi::{many0, separated_list0},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
+};
+
+/// Struct to represent a Match-Block
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Match<'a> {
+    /// Expression which is matched against the patterns of the arms
+    pub expr: Expression<'a>,
+
+    /// All arms in the order they are declared
+    pub arms: Vec<MatchArm<'a>>,
+}
+
+impl<'a> Match<'a> {
+    /// Returns all arms which can never be reached, because a previous
+    /// arm without a guard already matches every value.
+    ///
+    /// This does not check if the arms cover all possible values.
+    pub fn unreachable_arms(&self) -> Vec<&MatchArm<'a>> {
+        match self.arms.iter().position(MatchArm::is_catch_all) {
+            Some(idx) => self.arms[idx + 1..].iter().collect(),
+            None => vec![],
+        }
+    }
+}
+
+/// A single `case` of a match statement
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct MatchArm<'a> {
+    /// Pattern the matched value has to fit
+    pub pattern: Pattern<'a>,
+
+    /// Additional condition, which has to yield a truthy value
+    /// for the arm to be executed. This is optional
+    pub guard: Option<Expression<'a>>,
+
+    /// Code block to execute
+    pub block: Block<'a>,
+}
+
+impl<'a> MatchArm<'a> {
+    /// An arm is a catch all, if it matches every value
+    pub fn is_catch_all(&self) -> bool {
+        self.guard.is_none() && self.pattern.is_irrefutable()
+    }
+}
+
+/// All patterns which can be used in a match arm
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
+pub enum Pattern<'a> {
+    /// Matches if the value equals the literal
+    Literal(Literal<'a>),
+
+    /// Matches if the value lies in between the bounds
+    Range(RangePattern<'a>),
+
+    /// Matches every value, written as `_`
+    Wildcard(TokenSpan<'a>),
+
+    /// Matches every value and binds it to the name
+    Binding(Variable<'a>),
+
+    /// Matches an array with exactly as many elements as patterns
+    Array(Vec<Pattern<'a>>),
+
+    /// Matches a map, which contains all keys
+    Map(HashMap<String, Pattern<'a>>),
+}
+
+impl<'a> Pattern<'a> {
+    /// A pattern is irrefutable, if there is no value it does not match
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard(_) | Pattern::Binding(_))
+    }
+}
+
+/// Represents a pattern of the form `start..end` or `start..=end`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct RangePattern<'a> {
+    pub start: Literal<'a>,
+    pub end: Literal<'a>,
+
+    /// If the end bound is part of the range
+    pub inclusive: bool,
+}
+
+/// Parses the input into a Match struct
+pub fn parse_match(input: Span) -> Res<Match> {
+    context(
+        "Match",
+        tuple((
+            match_subject,
+            terminated(many0(parse_match_arm), preceded(sp, end)),
+        )),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            Match {
+                expr: res.0,
+                arms: res.1,
+            },
+        )
+    })
+}
+
+fn match_subject(input: Span) -> Res<Expression> {
+    context(
+        "MatchSubject",
+        delimited(
+            preceded(sp, lmatch),
+            preceded(sp, parse_expression),
+            preceded(sp, ldo),
+        ),
+    )(input)
+}
+
+fn parse_match_arm(input: Span) -> Res<MatchArm> {
+    context(
+        "MatchArm",
+        tuple((
+            preceded(preceded(sp, case), parse_pattern),
+            opt(preceded(preceded(sp, lif), preceded(sp, parse_expression))),
+            delimited(preceded(sp, ldo), parse_block, preceded(sp, end)),
+        )),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            MatchArm {
+                pattern: res.0,
+                guard: res.1,
+                block: res.2,
+            },
+        )
+    })
+}
+
+pub(crate) fn parse_pattern(input: Span) -> Res<Pattern> {
+    context(
+        "Pattern",
+        preceded(
+            sp,
+            alt((
+                // Ranges have to be tried before single literals
+                map(parse_range_pattern, Pattern::Range),
+                map(parse_literal, Pattern::Literal),
+                map(parse_wildcard, |span| Pattern::Wildcard(span.into())),
+                map(parse_variable, Pattern::Binding),
+                map(parse_array_pattern, Pattern::Array),
+                map(parse_map_pattern, Pattern::Map),
+            )),
+        ),
+    )(input)
+}
+
+fn parse_range_pattern(input: Span) -> Res<RangePattern> {
+    context(
+        "RangePattern",
+        tuple((parse_literal, alt((range_inclusive, range)), parse_literal)),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            RangePattern {
+                start: res.0,
+                inclusive: *res.1.fragment() == "..=",
+                end: res.2,
+            },
+        )
+    })
+}
+
+fn parse_wildcard(input: Span) -> Res<Span> {
+    context(
+        "Wildcard",
+        terminated(tag("_"), not(alt((alphanumeric1, tag("_"))))),
+    )(input)
+}
+
+fn parse_array_pattern(input: Span) -> Res<Vec<Pattern>> {
+    context(
+        "ArrayPattern",
+        preceded(
+            char('['),
+            cut(terminated(
+                separated_list0(preceded(sp, char(',')), parse_pattern),
+                preceded(sp, char(']')),
+            )),
+        ),
+    )(input)
+}
+
+fn parse_map_pattern(input: Span) -> Res<HashMap<String, Pattern>> {
+    context(
+        "MapPattern",
+        preceded(
+            char('{'),
+            cut(terminated(
+                map(
+                    separated_list0(
+                        preceded(sp, char(',')),
+                        separated_pair(
+                            preceded(sp, parse_str_raw),
+                            cut(preceded(sp, char(':'))),
+                            parse_pattern,
+                        ),
+                    ),
+                    |tuple_vec| {
+                        tuple_vec
+                            .into_iter()
+                            .map(|(k, v)| (String::from(*k.fragment()), v))
+                            .collect()
+                    },
+                ),
+                preceded(sp, char('}')),
+            )),
+        ),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::literals::Token;
+    use crate::parser::expression::{binary::BinaryOp, ExprOrVarname, PrefixExpr};
+    use crate::parser::tokens::Operator;
+
+    #[test]
+    fn parse_literal_and_range_patterns() {
+        let (_, res) = parse_pattern(Span::new("3")).unwrap();
+        assert_eq!(
+            res,
+            Pattern::Literal(Literal::Int(Token::new(3, Span::new("3"))))
+        );
+
+        let (_, res) = parse_pattern(Span::new("1..=9")).unwrap();
+        assert_eq!(
+            res,
+            Pattern::Range(RangePattern {
+                start: Literal::Int(Token::new(1, Span::new("1"))),
+                end: Literal::Int(Token::new(9, Span::new("9"))),
+                inclusive: true,
+            })
+        );
+
+        let (_, res) = parse_pattern(Span::new("1..9")).unwrap();
+        assert!(matches!(
+            res,
+            Pattern::Range(RangePattern {
+                inclusive: false,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_wildcard_and_binding_patterns() {
+        let (_, res) = parse_pattern(Span::new("_")).unwrap();
+        assert!(matches!(res, Pattern::Wildcard(_)));
+
+        let (_, res) = parse_pattern(Span::new("_name")).unwrap();
+        assert_eq!(
+            res,
+            Pattern::Binding(Token::new("_name", Span::new("_name")))
+        );
+    }
+
+    #[test]
+    fn parse_collection_patterns() {
+        let (_, res) = parse_pattern(Span::new("[first, _, 3]")).unwrap();
+        match res {
+            Pattern::Array(patterns) => {
+                assert_eq!(patterns.len(), 3);
+                assert_eq!(
+                    patterns[0],
+                    Pattern::Binding(Token::new("first", Span::new("first")))
+                );
+                assert!(matches!(patterns[1], Pattern::Wildcard(_)));
+            }
+            _ => panic!("Expected array pattern"),
+        }
+
+        let (_, res) = parse_pattern(Span::new("{\"name\": name}")).unwrap();
+        let mut hm = HashMap::new();
+        hm.insert(
+            String::from("name"),
+            Pattern::Binding(Token::new("name", Span::new("name"))),
+        );
+        assert_eq!(res, Pattern::Map(hm));
+    }
+
+    #[test]
+    fn parse_match_with_guard() {
+        let string = "match x do\n    case n if n < 0 do\n        return 0\n    end\n    case _ do\n    end\nend";
+        let (_, res) = parse_match(Span::new(string)).unwrap();
+
+        assert_eq!(res.arms.len(), 2);
+        assert_eq!(
+            res.arms[0].guard,
+            Some(Expression::BinaryOp(Box::new(BinaryOp {
+                left: Expression::PrefixExpr(Box::new(PrefixExpr {
+                    prefix: ExprOrVarname::Varname(Token::new("n", Span::new("n"))),
+                    suffix_chain: vec![],
+                })),
+                op: Operator::Lt,
+                right: Expression::Literal(Literal::Int(Token::new(0, Span::new("0")))),
+            })))
+        );
+        assert!(res.arms[1].is_catch_all());
+        assert!(res.unreachable_arms().is_empty());
+    }
+
+    #[test]
+    fn unreachable_arms_after_wildcard() {
+        let string =
+            "match x do\n case 1 do end\n case _ do end\n case 2 do end\n case y do end\nend";
+        let (_, res) = parse_match(Span::new(string)).unwrap();
+
+        let unreachable = res.unreachable_arms();
+        assert_eq!(unreachable.len(), 2);
+        assert_eq!(
+            unreachable[0].pattern,
+            Pattern::Literal(Literal::Int(Token::new(2, Span::new("2"))))
+        );
+    }
+
+    #[test]
+    fn guarded_binding_is_not_catch_all() {
+        let string = "match x do\n case y if y do end\n case 2 do end\nend";
+        let (_, res) = parse_match(Span::new(string)).unwrap();
+        assert!(res.unreachable_arms().is_empty());
+    }
+}
@@ -3,4 +3,6 @@ pub mod class;
 pub mod for_statement;
 pub mod function;
 pub mod if_statement;
+pub mod match_statement;
+pub mod try_statement;
 pub mod while_statement;
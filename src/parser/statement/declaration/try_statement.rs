@@ -0,0 +1,177 @@
+//! Error handling through Try statements
+//!
+//! Errors raised inside of the try block can be caught by the catch block,
+//! which optionally binds the raised value to a name. The finally block is
+//! executed in any case. At least one of both blocks is required.
+//!
+//! ```code
+//! try
+//!     let file = open("data.txt")
+//! catch err do
+//!     print(err)
+//! finally
+//!     close(file)
+//! end
+//! ```
+
+use crate::parser::literals::{parse_variable, sp, Variable};
+use crate::parser::statement::{parse_block, Block};
+use crate::parser::tokens::{catch, end, finally, ldo, ltry};
+use crate::parser::{Res, Span};
+
+use nom::{
+    combinator::{opt, verify},
+    error::context,
+    sequence::{preceded, terminated, tuple},
+};
+
+/// Struct to represent a Try-Block
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Try<'a> {
+    /// Code block which may raise an error
+    pub block: Block<'a>,
+
+    /// Handler for errors raised in the try block.
+    /// This is optional
+    pub catch: Option<Catch<'a>>,
+
+    /// Code block which is always executed at the end.
+    /// This is optional
+    pub finally: Option<Block<'a>>,
+}
+
+/// The catch clause of a try statement
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct Catch<'a> {
+    /// Name the raised value is bound to
+    pub binding: Option<Variable<'a>>,
+
+    /// Code block to execute if a error was raised
+    pub block: Block<'a>,
+}
+
+/// Parses the input into a Try struct
+pub fn parse_try(input: Span) -> Res<Try> {
+    context(
+        "Try",
+        terminated(
+            verify(
+                tuple((
+                    preceded(preceded(sp, ltry), parse_block),
+                    opt(parse_catch),
+                    opt(parse_finally),
+                )),
+                |(_, catch, finally)| catch.is_some() || finally.is_some(),
+            ),
+            preceded(sp, end),
+        ),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            Try {
+                block: res.0,
+                catch: res.1,
+                finally: res.2,
+            },
+        )
+    })
+}
+
+fn parse_catch(input: Span) -> Res<Catch> {
+    context(
+        "Catch",
+        tuple((
+            preceded(preceded(sp, catch), opt(preceded(sp, parse_variable))),
+            preceded(preceded(sp, ldo), parse_block),
+        )),
+    )(input)
+    .map(|(next_input, res)| {
+        (
+            next_input,
+            Catch {
+                binding: res.0,
+                block: res.1,
+            },
+        )
+    })
+}
+
+fn parse_finally(input: Span) -> Res<Block> {
+    context("Finally", preceded(preceded(sp, finally), parse_block))(input)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::literals::Token;
+    use crate::parser::expression::{call::Call, ExprOrVarname, ExprSuffix, PrefixExpr};
+    use crate::parser::statement::Statement;
+
+    fn call_stmt(name: &'static str) -> Statement<'static> {
+        Statement::FuncCall(PrefixExpr {
+            prefix: ExprOrVarname::Varname(Token::new(name, Span::new(name))),
+            suffix_chain: vec![ExprSuffix::FuncCall(Call {
+                callee: None,
+                args: vec![],
+            })],
+        })
+    }
+
+    #[test]
+    fn parse_try_catch_finally() {
+        let string = "try\n    risky()\ncatch err do\n    handle()\nfinally\n    cleanup()\nend";
+        let (_, res) = parse_try(Span::new(string)).unwrap();
+
+        assert_eq!(
+            res,
+            Try {
+                block: Block {
+                    statements: vec![call_stmt("risky")],
+                    return_stmt: None
+                },
+                catch: Some(Catch {
+                    binding: Some(Token::new("err", Span::new("err"))),
+                    block: Block {
+                        statements: vec![call_stmt("handle")],
+                        return_stmt: None
+                    }
+                }),
+                finally: Some(Block {
+                    statements: vec![call_stmt("cleanup")],
+                    return_stmt: None
+                })
+            }
+        )
+    }
+
+    #[test]
+    fn parse_try_without_binding() {
+        let string = "try risky() catch do end";
+        let (_, res) = parse_try(Span::new(string)).unwrap();
+
+        assert_eq!(res.catch.unwrap().binding, None);
+        assert_eq!(res.finally, None);
+    }
+
+    #[test]
+    fn parse_try_finally_only() {
+        let string = "try risky() finally cleanup() end";
+        let (_, res) = parse_try(Span::new(string)).unwrap();
+
+        assert_eq!(res.catch, None);
+        assert!(res.finally.is_some());
+    }
+
+    #[test]
+    fn parse_try_requires_handler() {
+        let string = "try risky() end";
+        assert!(parse_try(Span::new(string)).is_err());
+    }
+}
@@ -13,6 +13,8 @@ use nom::{
 };
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct While<'a> {
     pub cond: Expression<'a>,
     pub block: Block<'a>,
@@ -93,6 +95,8 @@ mod tests {
                 block: Block {
                     statements: vec![
                         Statement::LAssignment(LAssignment {
+                            exported: false,
+                            doc: None,
                             variable: Token::new("z", Span::new("z")),
                             expression: Expression::BinaryOp(Box::new(BinaryOp {
                                 left: Expression::PrefixExpr(Box::new(PrefixExpr {
@@ -107,6 +111,8 @@ mod tests {
                             }))
                         }),
                         Statement::LAssignment(LAssignment {
+                            exported: false,
+                            doc: None,
                             variable: Token::new("y", Span::new("y")),
                             expression: Expression::Literal(Literal::Int(Token::new(
                                 3,
@@ -3,28 +3,36 @@
 //! The import statement allowes to import modules from
 //! the standard lib, local modules or thid-party modules
 //!
+//! Single names of a module can be imported selectively
+//!
 //! ```py
 //! import math
 //! import lib.functools
 //!
 //! external import frosch.handler as fhandler
+//!
+//! from math import sin, cos
+//! import math.{sin, cos}
 //! ```
 use crate::parser::{
     literals::{parse_variable, sp},
-    tokens::{dot, external, import, las},
+    tokens::{comma, dot, external, from, import, las, left_brace, right_brace},
     Res, Span,
 };
 
 use crate::literals::Variable;
 use nom::{
-    combinator::opt,
+    branch::alt,
+    combinator::{map, opt},
     error::context,
     multi::separated_list1,
-    sequence::{preceded, tuple},
+    sequence::{delimited, preceded, tuple},
 };
 
 /// The import struct represents one import statement
 #[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Import<'a> {
     /// If a module is a third-party module, therefore not
     /// in the local project or part of the standard lib
@@ -38,6 +46,10 @@ pub struct Import<'a> {
     /// For convenience can a import be aliased, which
     /// represented the imported module
     pub alias: Option<Variable<'a>>,
+
+    /// Names which are imported selectively from the module.
+    /// If empty, the module itself is imported
+    pub items: Vec<Variable<'a>>,
 }
 
 /// Parses the input inot a Import struct
@@ -48,26 +60,73 @@ pub fn parse_import(input: Span) -> Res<Import> {
             sp,
             tuple((
                 opt(preceded(sp, external)),
-                preceded(
-                    preceded(sp, import),
-                    separated_list1(preceded(sp, dot), preceded(sp, parse_variable)),
-                ),
-                opt(preceded(preceded(sp, las), preceded(sp, parse_variable))),
+                alt((parse_from_import, parse_path_import)),
             )),
         ),
     )(input)
-    .map(|(next_input, res)| {
+    .map(|(next_input, (external, (path, items, alias)))| {
         (
             next_input,
             Import {
-                external: res.0.is_some(),
-                path: res.1,
-                alias: res.2,
+                external: external.is_some(),
+                path,
+                alias,
+                items,
             },
         )
     })
 }
 
+type ImportParts<'a> = (Vec<Variable<'a>>, Vec<Variable<'a>>, Option<Variable<'a>>);
+
+/// <from-keyword> <path> <import-keyword> <items>
+fn parse_from_import(input: Span) -> Res<ImportParts> {
+    context(
+        "FromImport",
+        tuple((
+            preceded(preceded(sp, from), parse_module_path),
+            preceded(preceded(sp, import), parse_import_items),
+        )),
+    )(input)
+    .map(|(next_input, (path, items))| (next_input, (path, items, None)))
+}
+
+/// <import-keyword> <path> (.{<items>} | as <alias>)
+fn parse_path_import(input: Span) -> Res<ImportParts> {
+    context(
+        "PathImport",
+        preceded(
+            preceded(sp, import),
+            tuple((
+                parse_module_path,
+                alt((
+                    map(
+                        delimited(
+                            preceded(preceded(sp, dot), preceded(sp, left_brace)),
+                            parse_import_items,
+                            preceded(sp, right_brace),
+                        ),
+                        |items| (items, None),
+                    ),
+                    map(
+                        opt(preceded(preceded(sp, las), preceded(sp, parse_variable))),
+                        |alias| (vec![], alias),
+                    ),
+                )),
+            )),
+        ),
+    )(input)
+    .map(|(next_input, (path, (items, alias)))| (next_input, (path, items, alias)))
+}
+
+fn parse_module_path(input: Span) -> Res<Vec<Variable>> {
+    separated_list1(preceded(sp, dot), preceded(sp, parse_variable))(input)
+}
+
+fn parse_import_items(input: Span) -> Res<Vec<Variable>> {
+    separated_list1(preceded(sp, comma), preceded(sp, parse_variable))(input)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -84,7 +143,8 @@ mod tests {
             Import {
                 external: false,
                 path: vec![Token::new("hello", Span::new("hello"))],
-                alias: None
+                alias: None,
+                items: vec![]
             }
         )
     }
@@ -102,7 +162,8 @@ mod tests {
                     Token::new("hello", Span::new("hello")),
                     Token::new("world", Span::new("world"))
                 ],
-                alias: Some(Token::new("tuna", Span::new("tuna")))
+                alias: Some(Token::new("tuna", Span::new("tuna"))),
+                items: vec![]
             }
         )
     }
@@ -117,7 +178,8 @@ mod tests {
             Import {
                 external: true,
                 path: vec![Token::new("hello", Span::new("hello"))],
-                alias: None
+                alias: None,
+                items: vec![]
             }
         )
     }
@@ -131,7 +193,47 @@ mod tests {
             Import {
                 external: false,
                 path: vec![Token::new("hello", Span::new("hello"))],
-                alias: Some(Token::new("h", Span::new("h")))
+                alias: Some(Token::new("h", Span::new("h"))),
+                items: vec![]
+            }
+        )
+    }
+
+    #[test]
+    fn test_from_import() {
+        let string = "from math.trig import sin, cos";
+        let (_, res) = parse_import(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Import {
+                external: false,
+                path: vec![
+                    Token::new("math", Span::new("math")),
+                    Token::new("trig", Span::new("trig"))
+                ],
+                alias: None,
+                items: vec![
+                    Token::new("sin", Span::new("sin")),
+                    Token::new("cos", Span::new("cos"))
+                ]
+            }
+        )
+    }
+
+    #[test]
+    fn test_import_item_group() {
+        let string = "external import math.{ sin, cos }";
+        let (_, res) = parse_import(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Import {
+                external: true,
+                path: vec![Token::new("math", Span::new("math"))],
+                alias: None,
+                items: vec![
+                    Token::new("sin", Span::new("sin")),
+                    Token::new("cos", Span::new("cos"))
+                ]
             }
         )
     }
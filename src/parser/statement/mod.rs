@@ -6,24 +6,28 @@ pub mod import;
 use nom::branch::alt;
 use nom::character::complete::line_ending;
 use nom::combinator::{map, opt};
-use nom::error::context;
+use nom::error::{context, ErrorKind, ParseError, VerboseError};
 use nom::multi::{many0, separated_list0};
 use nom::sequence::{preceded, tuple};
 use nom::Err;
 
 use crate::parser::{
+    comment::parse_doc,
     expression::{parse_expression, prefixexpr, ExprSuffix, Expression, PrefixExpr},
     literals::sp,
+    nested,
     statement::declaration::{
         assignment::{Assignment, LAssignment},
         class::Class,
         for_statement::For,
         function::Function,
         if_statement::If,
+        match_statement::Match,
+        try_statement::Try,
         while_statement::While,
     },
     statement::import::{parse_import, Import},
-    tokens::{comma, lreturn},
+    tokens::{comma, export, lpub, lreturn, raise},
     Res, Span,
 };
 
@@ -33,10 +37,14 @@ use declaration::{
     for_statement::parse_for,
     function::parse_function,
     if_statement::parse_if,
+    match_statement::parse_match,
+    try_statement::parse_try,
     while_statement::parse_while,
 };
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct Block<'a> {
     pub statements: Vec<Statement<'a>>,
     pub return_stmt: Option<ReturnStmt<'a>>,
@@ -52,6 +60,11 @@ impl<'a> IntoIterator for Block<'a> {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "node", bound(deserialize = "'de: 'a"))
+)]
 pub enum Statement<'a> {
     Assignment(Assignment<'a>),
     LAssignment(LAssignment<'a>),
@@ -59,26 +72,107 @@ pub enum Statement<'a> {
     While(While<'a>),
     For(For<'a>),
     If(If<'a>),
+    Match(Match<'a>),
+    Try(Try<'a>),
     Fun(Function<'a>),
     Class(Class<'a>),
     Return(ReturnStmt<'a>),
     Import(Import<'a>),
+    Raise(RaiseStmt<'a>),
+}
+
+impl<'a> Block<'a> {
+    /// A return at the end of the statements is the return statement of the
+    /// block. Returns in the middle of a block stay statements, the
+    /// statements after them are never executed
+    pub(crate) fn from_statements(mut statements: Vec<Statement<'a>>) -> Self {
+        let return_stmt = match statements.pop() {
+            Some(Statement::Return(return_stmt)) => Some(return_stmt),
+            Some(statement) => {
+                statements.push(statement);
+                None
+            }
+            None => None,
+        };
+        Block {
+            statements,
+            return_stmt,
+        }
+    }
 }
 
 pub fn parse_block(input: Span) -> Res<Block> {
+    nested(input, |input| {
+        context(
+            "Block",
+            many0(alt((
+                parse_statement,
+                map(parse_return_stmt, Statement::Return),
+            ))),
+        )(input)
+    })
+    .map(|(next_input, res)| (next_input, Block::from_statements(res)))
+}
+
+/// Parses the top level block of a source file. In contrast to [`parse_block`]
+/// functions, classes and variables can be exported here
+pub fn parse_module_block(input: Span) -> Res<Block> {
+    context("ModuleBlock", many0(parse_module_statement))(input)
+        .map(|(next_input, res)| (next_input, Block::from_statements(res)))
+}
+
+/// Parses a single statement of the top level block, including returns
+pub(crate) fn parse_module_statement(input: Span) -> Res<Statement> {
+    alt((
+        parse_exported_statement,
+        parse_statement,
+        map(parse_return_stmt, Statement::Return),
+    ))(input)
+}
+
+/// Parses a declaration, which is marked as visible to other modules
+/// by a leading `pub` or `export`
+fn parse_exported_statement(input: Span) -> Res<Statement> {
     context(
-        "Block",
-        tuple((many0(parse_statement), opt(parse_return_stmt))),
+        "ExportedStmt",
+        tuple((
+            parse_doc,
+            preceded(sp, alt((lpub, export))),
+            alt((
+                map(parse_function, |function| {
+                    Statement::Fun(Function {
+                        exported: true,
+                        ..function
+                    })
+                }),
+                map(parse_class, |class| {
+                    Statement::Class(Class {
+                        exported: true,
+                        ..class
+                    })
+                }),
+                map(parse_lassignment, |assignment| {
+                    Statement::LAssignment(LAssignment {
+                        exported: true,
+                        ..assignment
+                    })
+                }),
+            )),
+        )),
     )(input)
-    .map(|(next_input, res)| {
-        (
-            next_input,
-            Block {
-                statements: res.0,
-                return_stmt: res.1,
-            },
-        )
-    })
+    .map(|(next_input, (doc, _, stmt))| (next_input, with_doc(stmt, doc)))
+}
+
+/// Attaches the documentation to functions, classes and variables
+fn with_doc(stmt: Statement, doc: Option<String>) -> Statement {
+    match stmt {
+        Statement::Fun(function) => Statement::Fun(Function { doc, ..function }),
+        Statement::Class(class) => Statement::Class(Class { doc, ..class }),
+        Statement::LAssignment(assignment) => {
+            Statement::LAssignment(LAssignment { doc, ..assignment })
+        }
+        stmt => stmt,
+    }
 }
 
 /// Parse a single statement into a [`Statement`]
@@ -102,6 +196,8 @@ pub fn parse_block(input: Span) -> Res<Block> {
 /// assert_eq!(
 ///     res,
 ///     Statement::LAssignment(LAssignment {
+///         exported: false,
+///         doc: None,
 ///         variable: Token::new("x", Span::new("x")),
 ///         expression: Expression::Literal(Literal::Int(Token::new(3, Span::new("3"))))
 ///     })
@@ -110,46 +206,64 @@ pub fn parse_block(input: Span) -> Res<Block> {
 pub fn parse_statement(input: Span) -> Res<Statement> {
     context(
         "Stmt",
-        preceded(
-            sp,
+        tuple((
+            parse_doc,
             alt((
                 map(parse_assignment, Statement::Assignment),
                 map(parse_lassignment, Statement::LAssignment),
                 map(parse_if, Statement::If),
+                map(parse_match, Statement::Match),
                 map(parse_for, Statement::For),
                 map(parse_while, Statement::While),
                 map(parse_function, Statement::Fun),
                 map(parse_class, Statement::Class),
                 map(parse_import, Statement::Import),
+                map(parse_try, Statement::Try),
                 map(parse_function_call, Statement::FuncCall),
+                map(parse_raise_stmt, Statement::Raise),
             )),
-        ),
+        )),
     )(input)
+    .map(|(next_input, (doc, stmt))| (next_input, with_doc(stmt, doc)))
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
 pub struct ReturnStmt<'a> {
     pub values: Vec<Expression<'a>>,
 }
 
+/// Raises the value of the expression as an error, which can
+/// be caught by a surrounding [`Try`] statement
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(deserialize = "'de: 'a")))]
+pub struct RaiseStmt<'a> {
+    pub value: Expression<'a>,
+}
+
 fn parse_function_call(input: Span) -> Res<PrefixExpr> {
     let func_call_expr = prefixexpr(input);
 
+    // A prefix expression without suffixes, like a parenthesized
+    // expression, is no call either
     let is_func_call = match func_call_expr {
-        // There should be an uncatched unwrap
-        // Shoudl return a error message
-        Ok((_, ref o)) => matches!(o.suffix_chain.last().unwrap(), ExprSuffix::FuncCall(_)),
+        Ok((_, ref o)) => o.suffix_chain.last().is_some_and(ExprSuffix::is_call),
         _ => false,
     };
 
     if is_func_call {
         func_call_expr
     } else {
-        Err(Err::Error(nom::error::VerboseError { errors: vec![] }))
+        Err(Err::Error(VerboseError::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        )))
     }
 }
 
-fn parse_return_stmt(input: Span) -> Res<ReturnStmt> {
+pub(crate) fn parse_return_stmt(input: Span) -> Res<ReturnStmt> {
     context(
         "ReturnStmt",
         preceded(preceded(sp, lreturn), preceded(sp, parse_return_list)),
@@ -157,6 +271,14 @@ fn parse_return_stmt(input: Span) -> Res<ReturnStmt> {
     .map(|(next_input, res)| (next_input, ReturnStmt { values: res }))
 }
 
+fn parse_raise_stmt(input: Span) -> Res<RaiseStmt> {
+    context(
+        "RaiseStmt",
+        preceded(preceded(sp, raise), preceded(sp, parse_expression)),
+    )(input)
+    .map(|(next_input, res)| (next_input, RaiseStmt { value: res }))
+}
+
 fn parse_return_list(input: Span) -> Res<Vec<Expression>> {
     context(
         "ReturnList",
@@ -167,7 +289,7 @@ fn parse_return_list(input: Span) -> Res<Vec<Expression>> {
     )(input)
 }
 
-pub(crate) fn opt_line_ending(input: Span) -> Res<&str> {
+pub(crate) fn opt_line_ending(input: Span<'_>) -> Res<'_, &str> {
     opt(many0(line_ending))(input).map(|(next_input, _)| (next_input, ""))
 }
 
@@ -177,12 +299,14 @@ mod tests {
     use super::*;
     use crate::parser::expression::binary::BinaryOp;
     use crate::parser::expression::call::Call;
-    use crate::parser::expression::ExprOrVarname;
+    use crate::parser::expression::{ExprOrVarname, ExprSuffix};
     use crate::parser::literals::{Literal, Token};
     use crate::parser::tokens::Operator;
 
     fn ass_x_eq_3() -> Statement<'static> {
         Statement::LAssignment(LAssignment {
+            exported: false,
+            doc: None,
             variable: Token::new("x", Span::new("x")),
             expression: Expression::Literal(Literal::Int(Token::new(3, Span::new("3")))),
         })
@@ -195,6 +319,8 @@ mod tests {
         assert_eq!(
             res,
             Statement::LAssignment(LAssignment {
+                exported: false,
+                doc: None,
                 variable: Token::new("x", Span::new("x")),
                 expression: Expression::Literal(Literal::Int(Token::new(3, Span::new("3.0"))))
             })
@@ -295,6 +421,112 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_raise_stmt() {
+        let string = "raise \"Not found\"";
+        let (_, res) = parse_statement(Span::new(string)).unwrap();
+        assert_eq!(
+            res,
+            Statement::Raise(RaiseStmt {
+                value: Expression::Literal(Literal::Str(Token::new(
+                    String::from("Not found"),
+                    Span::new("Not found")
+                )))
+            })
+        )
+    }
+
+    #[test]
+    fn test_raise_prefixed_call() {
+        let string = "raised()";
+        let (_, res) = parse_statement(Span::new(string)).unwrap();
+        assert!(matches!(res, Statement::FuncCall(_)));
+    }
+
+    #[test]
+    fn test_safe_call_stmt() {
+        let string = "user?.greet()";
+        let (_, res) = parse_statement(Span::new(string)).unwrap();
+        assert!(matches!(res, Statement::FuncCall(_)));
+    }
+
+    #[test]
+    fn test_exported_declarations() {
+        let string = "pub fun hello() end\nexport let x = 3\nclass Private end";
+        let (_, res) = parse_module_block(Span::new(string)).unwrap();
+        assert!(matches!(
+            res.statements[0],
+            Statement::Fun(Function { exported: true, .. })
+        ));
+        assert!(matches!(
+            res.statements[1],
+            Statement::LAssignment(LAssignment { exported: true, .. })
+        ));
+        assert!(matches!(
+            res.statements[2],
+            Statement::Class(Class {
+                exported: false,
+                doc: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_nested_export_is_rejected() {
+        let string = "fun outer()\n    pub let x = 3\nend";
+        let (rest, _) = parse_module_block(Span::new(string)).unwrap();
+        assert_ne!(rest.fragment(), &"");
+    }
+
+    #[test]
+    fn test_early_return() {
+        let string = "return 1\nprint(2)\nreturn 3";
+        let (rest, res) = parse_block(Span::new(string)).unwrap();
+        assert_eq!(rest.fragment(), &"");
+        assert_eq!(res.statements.len(), 2);
+        assert!(matches!(res.statements[0], Statement::Return(_)));
+        assert!(res.return_stmt.is_some());
+
+        // Returns can follow each other, as well as exported declarations
+        let string = "return 1\nreturn\n";
+        let (rest, res) = parse_block(Span::new(string)).unwrap();
+        assert_eq!(rest.fragment().trim(), "");
+        assert_eq!(res.statements.len(), 1);
+        assert_eq!(res.return_stmt, Some(ReturnStmt { values: vec![] }));
+
+        let string = "return 1\npub let x = 2\n";
+        let (rest, res) = parse_module_block(Span::new(string)).unwrap();
+        assert_eq!(rest.fragment().trim(), "");
+        assert_eq!(res.statements.len(), 2);
+    }
+
+    #[test]
+    fn test_nested_early_returns() {
+        // The statements after a return are parsed once, nesting them must
+        // not double the parse time with every level
+        let depth = 40;
+        let mut string = String::new();
+        for level in 0..depth {
+            string.push_str(&format!("{}if x do\n", "    ".repeat(level)));
+            string.push_str(&format!("{}return 1\n", "    ".repeat(level + 1)));
+        }
+        for level in (0..depth).rev() {
+            string.push_str(&format!("{}end\n", "    ".repeat(level)));
+        }
+
+        let (rest, mut res) = parse_block(Span::new(&string)).unwrap();
+        assert_eq!(rest.fragment().trim(), "");
+        for _ in 1..depth {
+            let inner = match res.statements.pop() {
+                Some(Statement::If(if_stmt)) => if_stmt.stmts,
+                stmt => panic!("expected an if statement, got {:?}", stmt),
+            };
+            assert!(matches!(inner.statements[0], Statement::Return(_)));
+            res = inner;
+        }
+    }
+
     #[test]
     fn test_stmt_leading_nl_sp() {
         let string = "\n\n// Hello Test \nfun hello() end";
@@ -302,7 +534,9 @@ mod tests {
         assert_eq!(
             res,
             Statement::Fun(Function {
-                name: "hello",
+                exported: false,
+                doc: Some(String::from("Hello Test")),
+                name: Token::new("hello", Span::new("hello")),
                 parameters: vec![],
                 block: Block {
                     statements: vec![],
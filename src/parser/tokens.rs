@@ -5,27 +5,63 @@ use crate::parser::{Res, Span};
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
+use nom::character::complete::satisfy;
+use nom::combinator::{not, value};
 use nom::error::context;
-use nom::sequence::preceded;
+use nom::sequence::{preceded, terminated};
 
 /// Reserved keywords for the liva lang, which can not be used as identifiers
-pub const KEYWORDS: [&str; 14] = [
+pub const KEYWORDS: [&str; 24] = [
     "return", "class", "end", "fun", "do", "while", "for", "if", "let", "in", "else", "external",
-    "as", "import",
+    "as", "import", "match", "case", "try", "catch", "finally", "raise", "then", "pub", "export",
+    "from",
 ];
 
+/// Operators which are written as words, they are no keywords
+pub const WORD_OPERATORS: [&str; 3] = ["and", "or", "not"];
+
+/// Literals which are written as words, they are case insensitive
+pub const LITERAL_WORDS: [&str; 3] = ["true", "false", "nil"];
+
+/// All words, which can not be used as identifiers: the keywords, the word
+/// operators and the literal words
+pub const RESERVED_WORDS: [&str; 30] = [
+    "return", "class", "end", "fun", "do", "while", "for", "if", "let", "in", "else", "external",
+    "as", "import", "match", "case", "try", "catch", "finally", "raise", "then", "pub", "export",
+    "from", "and", "or", "not", "true", "false", "nil",
+];
+
+/// Returns true, if the word can not be used as identifier
+pub fn is_reserved(word: &str) -> bool {
+    RESERVED_WORDS.contains(&word) || LITERAL_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+/// Returns true for the characters of identifiers
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Succeeds, if no identifier continues at the input. A word token has to end
+/// here, so that `format` is one identifier and not `for` followed by `mat`
+pub(crate) fn word_end(input: Span) -> Res<()> {
+    not(satisfy(is_word_char))(input)
+}
+
 lazy_static! {
 
     /// Vector of unary operators which show the precedence of the operators
     /// with which unary expressions should be evaluated
     pub static ref UNOPS: Vec<UnOperator> = {
-        let table = &mut [UnOperator::Sub, UnOperator::Not];
+        let table = &mut [UnOperator::Add, UnOperator::Sub, UnOperator::Not];
         table.sort();
         table.to_vec()
     };
 
     /// Vector of binary operators which show the precedence of the operators
     /// with which binary expressions should be evaluated
+    ///
+    /// The nil coalescing operator `??` binds weaker than every operator in this
+    /// table, conditional expressions `if a then b else c` bind weaker than `??`
     pub static ref BINOP_PRECEDENCE: Vec<Vec<Operator>> = {
         let table: &mut [&mut [Operator]] = &mut [
             &mut [Operator::Pow],
@@ -57,7 +93,10 @@ lazy_static! {
 }
 
 macro_rules! define_token {
-    ( $( { $fn_name:ident, $name:literal, $token:literal } ), *) => {
+    ( $table:ident; $( { $fn_name:ident, $name:literal, $token:literal } ), *) => {
+        /// Macro generated table of all tokens of the group
+        pub const $table: &[&str] = &[$($token),*];
+
         $(
             /// Macro generated function
             /// Token parser to parse token
@@ -68,15 +107,33 @@ macro_rules! define_token {
                 )(input)
             }
         )*
+    };
+    ( words $table:ident; $( { $fn_name:ident, $name:literal, $token:literal } ), *) => {
+        /// Macro generated table of all tokens of the group
+        pub const $table: &[&str] = &[$($token),*];
+
+        $(
+            /// Macro generated function
+            /// Token parser to parse token, which is not followed by an
+            /// identifier character
+            pub fn $fn_name(input: Span) -> Res<Span> {
+                context(
+                    $name,
+                    terminated(tag($token), word_end)
+                )(input)
+            }
+        )*
     }
 }
 
 define_token! {
+    SYMBOL_TOKENS;
     {add, "Add", "+"},
     {sub, "Sub", "-"},
     {mul, "Mul", "*"},
     {div, "Div", "/"},
     {modulo, "Modulu", "%"},
+    {pow, "Pow", "^"},
     {equal, "Equal", "=="},
     {unequal, "Unequal", "!="},
     {dot, "Dot", "."},
@@ -85,15 +142,27 @@ define_token! {
     {right_paren, "RightParen", ")"},
     {left_bracket, "LeftBracket", "["},
     {right_bracket, "RightBracket", "]"},
+    {left_brace, "LeftBrace", "{"},
+    {right_brace, "RightBrace", "}"},
     {less_than, "LessThan", "<"},
     {greater_than, "GreaterThan", ">"},
     {less_eq_than, "LessEqThan", "<="},
     {greater_eq_than, "GreaterEqThan", ">="},
+    {nil_coalesce, "NilCoalesce", "??"},
+    {safe_dot, "SafeDot", "?."},
+    {safe_left_bracket, "SafeLeftBracket", "?["},
+    {colon, "Colon", ":"},
+    {range_inclusive, "RangeInclusive", "..="},
+    {range, "Range", ".."},
     {comment, "Comment", "//"},
+    {block_comment_start, "BlockCommentStart", "/*"},
+    {block_comment_end, "BlockCommentEnd", "*/"},
+    {shebang, "Shebang", "#!"},
     {newline, "Newline", "\n"}
 }
 
 define_token! {
+    words WORD_TOKENS;
     {lreturn, "Return", "return"},
     {class, "Class", "class"},
     {end, "End", "end"},
@@ -107,14 +176,26 @@ define_token! {
     {lin, "In", "in"},
     {lor, "Or", "or"},
     {land, "And", "and"},
+    {lnot, "Not", "not"},
     {external, "External", "external"},
     {import, "Import", "import"},
-    {las, "As", "as"}
+    {las, "As", "as"},
+    {lmatch, "Match", "match"},
+    {case, "Case", "case"},
+    {ltry, "Try", "try"},
+    {catch, "Catch", "catch"},
+    {finally, "Finally", "finally"},
+    {raise, "Raise", "raise"},
+    {then, "Then", "then"},
+    {lpub, "Pub", "pub"},
+    {export, "Export", "export"},
+    {from, "From", "from"}
 }
 
 /// All Operators which are used in the language
 /// for binary expressions
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     Add,
     Sub,
@@ -135,24 +216,60 @@ pub enum Operator {
 /// All Operators which are used in the language
 /// for unary expressions
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnOperator {
     Add,
     Sub,
     Not,
 }
 
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Div => "/",
+            Operator::Mul => "*",
+            Operator::Pow => "^",
+            Operator::Mod => "%",
+            Operator::EQ => "==",
+            Operator::And => "and",
+            Operator::Lt => "<",
+            Operator::Gt => ">",
+            Operator::Or => "or",
+            Operator::Leq => "<=",
+            Operator::Geq => ">=",
+            Operator::Neq => "!=",
+        };
+        f.write_str(symbol)
+    }
+}
+
+impl std::fmt::Display for UnOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let symbol = match self {
+            UnOperator::Add => "+",
+            UnOperator::Sub => "-",
+            UnOperator::Not => "not",
+        };
+        f.write_str(symbol)
+    }
+}
+
 pub(crate) fn parse_unary_operator(input: Span) -> Res<UnOperator> {
-    context("UnaryOperator", preceded(sp, alt((add, sub, mul, div))))(input).map(
-        |(next_input, res)| {
-            match *res.fragment() {
-                "+" => (next_input, UnOperator::Add),
-                "-" => (next_input, UnOperator::Sub),
-                "not" => (next_input, UnOperator::Not),
-                // This should never reach!
-                _ => (next_input, UnOperator::Add),
-            }
-        },
-    )
+    // Every operator parsed here has to be in `UNOPS`, otherwise the
+    // expression tree can not be built
+    context(
+        "UnaryOperator",
+        preceded(
+            sp,
+            alt((
+                value(UnOperator::Add, add),
+                value(UnOperator::Sub, sub),
+                value(UnOperator::Not, lnot),
+            )),
+        ),
+    )(input)
 }
 
 pub(crate) fn parse_binary_operator(input: Span) -> Res<Operator> {
@@ -161,38 +278,24 @@ pub(crate) fn parse_binary_operator(input: Span) -> Res<Operator> {
         preceded(
             sp,
             alt((
-                add,
-                sub,
-                mul,
-                div,
-                equal,
-                unequal,
-                less_than,
-                greater_than,
-                greater_eq_than,
-                less_eq_than,
-                land,
-                modulo,
+                value(Operator::Add, add),
+                value(Operator::Sub, sub),
+                value(Operator::Mul, mul),
+                value(Operator::Div, div),
+                value(Operator::Mod, modulo),
+                value(Operator::Pow, pow),
+                value(Operator::EQ, equal),
+                value(Operator::Neq, unequal),
+                // `<=` and `>=` have to be tried before `<` and `>`
+                value(Operator::Leq, less_eq_than),
+                value(Operator::Geq, greater_eq_than),
+                value(Operator::Lt, less_than),
+                value(Operator::Gt, greater_than),
+                value(Operator::And, land),
+                value(Operator::Or, lor),
             )),
         ),
     )(input)
-    .map(|(next_input, res)| {
-        match *res.fragment() {
-            "+" => (next_input, Operator::Add),
-            "-" => (next_input, Operator::Sub),
-            "*" => (next_input, Operator::Mul),
-            "/" => (next_input, Operator::Div),
-            "%" => (next_input, Operator::Mod),
-            "==" => (next_input, Operator::EQ),
-            "<" => (next_input, Operator::Lt),
-            ">" => (next_input, Operator::Gt),
-            "<=" => (next_input, Operator::Leq),
-            ">=" => (next_input, Operator::Geq),
-            "and" => (next_input, Operator::And),
-            // This should never reach!
-            _ => (next_input, Operator::Add),
-        }
-    })
 }
 
 pub(crate) fn parse_tokens(input: Span) -> Res<Span> {
@@ -213,3 +316,68 @@ pub(crate) fn parse_tokens(input: Span) -> Res<Span> {
         modulo,
     ))(input)
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_binary_operator() {
+        let parse = |source| parse_binary_operator(Span::new(source)).map(|(_, op)| op);
+        assert_eq!(parse(" ^ 2"), Ok(Operator::Pow));
+        assert_eq!(parse("!= 2"), Ok(Operator::Neq));
+        assert_eq!(parse("or b"), Ok(Operator::Or));
+        assert_eq!(parse("and b"), Ok(Operator::And));
+
+        // The longer operators must not be parsed as `<` or `>`
+        let (rest, op) = parse_binary_operator(Span::new("<= 2")).unwrap();
+        assert_eq!((op, *rest.fragment()), (Operator::Leq, " 2"));
+        let (rest, op) = parse_binary_operator(Span::new(">= 2")).unwrap();
+        assert_eq!((op, *rest.fragment()), (Operator::Geq, " 2"));
+        assert_eq!(parse("< 2"), Ok(Operator::Lt));
+        assert_eq!(parse("> 2"), Ok(Operator::Gt));
+
+        assert!(parse("= 2").is_err());
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        let words: Vec<&str> = KEYWORDS
+            .iter()
+            .chain(WORD_OPERATORS.iter())
+            .chain(LITERAL_WORDS.iter())
+            .copied()
+            .collect();
+        assert_eq!(words, RESERVED_WORDS);
+        for token in WORD_TOKENS.iter() {
+            assert!(is_reserved(token), "`{}` is not reserved", token);
+        }
+
+        assert!(is_reserved("nil"));
+        assert!(is_reserved("TRUE"));
+        assert!(!is_reserved("End"));
+        assert!(!is_reserved("format"));
+    }
+
+    #[test]
+    fn test_word_tokens() {
+        let (rest, word) = lfor(Span::new("for x")).unwrap();
+        assert_eq!(*word.fragment(), "for");
+        assert_eq!(*rest.fragment(), " x");
+        assert_eq!(*end(Span::new("end(")).unwrap().0.fragment(), "(");
+
+        assert!(lfor(Span::new("format")).is_err());
+        assert!(end(Span::new("end_")).is_err());
+        assert!(lif(Span::new("if1")).is_err());
+    }
+
+    #[test]
+    fn test_parse_unary_operator() {
+        let parse = |source| parse_unary_operator(Span::new(source)).map(|(_, op)| op);
+        assert_eq!(parse("-x"), Ok(UnOperator::Sub));
+        assert_eq!(parse(" +x"), Ok(UnOperator::Add));
+        assert_eq!(parse("not x"), Ok(UnOperator::Not));
+        assert!(parse("nothing").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+//! Resolves the imports of a liva program into a module graph
+//!
+//! Starting at a root file, every imported module is located, parsed and
+//! its imports are resolved in turn. Modules are looked up as follows:
+//!
+//! * `import hello.world` looks for `hello/world.lv` or the directory `hello/world/`,
+//!   first in the local project, then in the standard library
+//! * `external import hello.world` only looks in the site packages
+//!
+//! A directory module is loaded from its `init.lv`. Without one, the directory
+//! is a module without any content.
+//!
+//! ```no_run
+//! use std::path::{Path, PathBuf};
+//!
+//! use liva_parser::resolver::{Resolver, SearchPaths};
+//!
+//! let resolver = Resolver::new(SearchPaths {
+//!     project: PathBuf::from("project"),
+//!     stdlib: Some(PathBuf::from("/usr/lib/liva")),
+//!     site_packages: None,
+//! });
+//! let graph = resolver.resolve(Path::new("project/main.lv"));
+//!
+//! for error in graph.errors.iter() {
+//!     eprintln!("{}", error);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parser::{self, expect_end, module::parse_module, position::Position, Span};
+
+/// Directories in which imported modules are looked up
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchPaths {
+    /// Root directory of the local project
+    pub project: PathBuf,
+
+    /// Directory of the standard library
+    pub stdlib: Option<PathBuf>,
+
+    /// Directory of third-party modules, used by `external` imports
+    pub site_packages: Option<PathBuf>,
+}
+
+/// Describes where a module was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleOrigin {
+    Root,
+    Project,
+    Stdlib,
+    External,
+}
+
+/// A single module in the [`ModuleGraph`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleNode {
+    /// Dotted import path of the module, or the file name for the root module
+    pub name: String,
+
+    /// Location of the module file, or of the directory for directory
+    /// modules without a `init.lv`
+    pub path: PathBuf,
+
+    pub origin: ModuleOrigin,
+
+    /// Resolved imports of this module
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Edge of the [`ModuleGraph`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    /// Index of the imported module in [`ModuleGraph::modules`]
+    pub module: usize,
+
+    /// Position of the module path in the import statement
+    pub position: Position,
+}
+
+/// Everything which can go wrong while resolving imports
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    /// A imported module could not be found in the search paths
+    MissingModule {
+        importer: PathBuf,
+        module: String,
+        external: bool,
+        position: Position,
+    },
+
+    /// A module file could not be read
+    Read { path: PathBuf, message: String },
+
+    /// A module file could not be parsed completely
+    Parse { path: PathBuf, position: Position },
+
+    /// Modules which import each other, the first module imports the
+    /// second one and the last module imports the first one
+    Cycle { modules: Vec<PathBuf> },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::MissingModule {
+                importer,
+                module,
+                external,
+                position,
+            } => {
+                let kind = if *external {
+                    "external module"
+                } else {
+                    "module"
+                };
+                write!(
+                    f,
+                    "{}:{}: {} `{}` not found",
+                    importer.display(),
+                    position,
+                    kind,
+                    module
+                )
+            }
+            ResolveError::Read { path, message } => {
+                write!(f, "{}: could not read module: {}", path.display(), message)
+            }
+            ResolveError::Parse { path, position } => {
+                write!(f, "{}:{}: could not parse module", path.display(), position)
+            }
+            ResolveError::Cycle { modules } => {
+                let names: Vec<String> = modules.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "import cycle: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for ResolveError {}
+
+/// All modules reachable from the root module and the imports between them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleGraph {
+    /// All modules, the root module is the first one
+    pub modules: Vec<ModuleNode>,
+
+    /// All errors found while resolving, including import cycles
+    pub errors: Vec<ResolveError>,
+}
+
+impl ModuleGraph {
+    pub fn root(&self) -> Option<&ModuleNode> {
+        self.modules.first()
+    }
+
+    /// Returns the index of the module with the given dotted name
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+
+    /// Returns all import cycles as lists of module indices
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Active,
+            Done,
+        }
+
+        fn visit(
+            graph: &ModuleGraph,
+            idx: usize,
+            state: &mut Vec<State>,
+            stack: &mut Vec<usize>,
+            cycles: &mut Vec<Vec<usize>>,
+        ) {
+            state[idx] = State::Active;
+            stack.push(idx);
+
+            for dep in graph.modules[idx].dependencies.iter() {
+                match state[dep.module] {
+                    State::New => visit(graph, dep.module, state, stack, cycles),
+                    State::Active => {
+                        let start = stack.iter().position(|&m| m == dep.module).unwrap();
+                        cycles.push(stack[start..].to_vec());
+                    }
+                    State::Done => {}
+                }
+            }
+
+            stack.pop();
+            state[idx] = State::Done;
+        }
+
+        let mut state = vec![State::New; self.modules.len()];
+        let mut cycles = Vec::new();
+        for idx in 0..self.modules.len() {
+            if state[idx] == State::New {
+                visit(self, idx, &mut state, &mut Vec::new(), &mut cycles);
+            }
+        }
+        cycles
+    }
+}
+
+/// Owned copy of the parts of a import statement the resolver needs
+struct ImportRequest {
+    path: Vec<String>,
+    external: bool,
+    position: Position,
+}
+
+pub struct Resolver {
+    search_paths: SearchPaths,
+}
+
+impl Resolver {
+    pub fn new(search_paths: SearchPaths) -> Self {
+        Resolver { search_paths }
+    }
+
+    /// Parses the root file and all transitively imported modules
+    pub fn resolve(&self, root: &Path) -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        let mut known: HashMap<PathBuf, usize> = HashMap::new();
+
+        graph.modules.push(ModuleNode {
+            name: root
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: root.to_path_buf(),
+            origin: ModuleOrigin::Root,
+            dependencies: vec![],
+        });
+        known.insert(canonical(root), 0);
+
+        let mut queue = vec![0];
+        while let Some(idx) = queue.pop() {
+            let importer = graph.modules[idx].path.clone();
+            let imports = match read_imports(&importer) {
+                Ok(imports) => imports,
+                Err(error) => {
+                    graph.errors.push(error);
+                    continue;
+                }
+            };
+
+            for import in imports {
+                let name = import.path.join(".");
+                let (path, origin) = match self.locate(&import.path, import.external) {
+                    Some(found) => found,
+                    None => {
+                        graph.errors.push(ResolveError::MissingModule {
+                            importer: importer.clone(),
+                            module: name,
+                            external: import.external,
+                            position: import.position,
+                        });
+                        continue;
+                    }
+                };
+
+                let module = match known.get(&canonical(&path)) {
+                    Some(&module) => module,
+                    None => {
+                        let module = graph.modules.len();
+                        known.insert(canonical(&path), module);
+                        graph.modules.push(ModuleNode {
+                            name,
+                            path,
+                            origin,
+                            dependencies: vec![],
+                        });
+                        queue.push(module);
+                        module
+                    }
+                };
+
+                graph.modules[idx].dependencies.push(Dependency {
+                    module,
+                    position: import.position,
+                });
+            }
+        }
+
+        for cycle in graph.cycles() {
+            let modules = cycle
+                .iter()
+                .map(|&idx| graph.modules[idx].path.clone())
+                .collect();
+            graph.errors.push(ResolveError::Cycle { modules });
+        }
+
+        graph
+    }
+
+    /// Looks up the module of the import path, `external` imports are only
+    /// searched in the site packages
+    pub fn locate(&self, path: &[String], external: bool) -> Option<(PathBuf, ModuleOrigin)> {
+        let search_paths = &self.search_paths;
+        let candidates = if external {
+            vec![(search_paths.site_packages.as_ref(), ModuleOrigin::External)]
+        } else {
+            vec![
+                (Some(&search_paths.project), ModuleOrigin::Project),
+                (search_paths.stdlib.as_ref(), ModuleOrigin::Stdlib),
+            ]
+        };
+
+        candidates
+            .into_iter()
+            .filter_map(|(dir, origin)| dir.map(|dir| (dir, origin)))
+            .find_map(|(dir, origin)| find_module(dir, path).map(|path| (path, origin)))
+    }
+}
+
+/// Looks for `dir/a/b.lv` or the directory `dir/a/b` for the path `a.b`
+fn find_module(dir: &Path, path: &[String]) -> Option<PathBuf> {
+    let mut base = dir.to_path_buf();
+    base.extend(path);
+
+    let file = base.with_extension("lv");
+    if file.is_file() {
+        return Some(file);
+    }
+
+    if base.is_dir() {
+        let init = base.join("init.lv");
+        return Some(if init.is_file() { init } else { base });
+    }
+
+    None
+}
+
+fn read_imports(path: &Path) -> Result<Vec<ImportRequest>, ResolveError> {
+    // Directory modules without an init file have no content
+    if path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let source = fs::read_to_string(path).map_err(|error| ResolveError::Read {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    })?;
+
+    let error_at = |position| ResolveError::Parse {
+        path: path.to_path_buf(),
+        position,
+    };
+
+    let input = Span::new(source.as_str());
+    let (rest, module) =
+        parse_module(input).map_err(|err| error_at(parser::parse_error(input, err).position))?;
+    expect_end(rest).map_err(|err| error_at(err.position))?;
+
+    Ok(module
+        .imports()
+        .into_iter()
+        .map(|import| ImportRequest {
+            path: import.path.iter().map(|t| t.value.to_string()).collect(),
+            external: import.external,
+            position: Position::from(import.path[0].pos),
+        })
+        .collect())
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn node(name: &str, dependencies: &[usize]) -> ModuleNode {
+        ModuleNode {
+            name: name.to_string(),
+            path: PathBuf::from(format!("{}.lv", name)),
+            origin: ModuleOrigin::Project,
+            dependencies: dependencies
+                .iter()
+                .map(|&module| Dependency {
+                    module,
+                    position: Position::new(1, 1),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_cycles() {
+        let graph = ModuleGraph {
+            modules: vec![
+                node("main", &[1, 3]),
+                node("a", &[2]),
+                node("b", &[1]),
+                node("c", &[3]),
+            ],
+            errors: vec![],
+        };
+
+        assert_eq!(graph.cycles(), vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn test_no_cycles() {
+        let graph = ModuleGraph {
+            modules: vec![node("main", &[1, 2]), node("a", &[2]), node("b", &[])],
+            errors: vec![],
+        };
+
+        assert!(graph.cycles().is_empty());
+    }
+}
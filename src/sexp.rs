@@ -0,0 +1,455 @@
+//! Compact S-expression representation of the AST
+//!
+//! In contrast to the `Debug` output it only contains the structure of the
+//! program and no spans, which makes it suitable for golden tests:
+//!
+//! ```rust
+//! use liva_parser::{parse_source, sexp::ToSexp, Span};
+//!
+//! let source = "fun fib(n)\n    if n == 0 do\n        return 0\n    end\nend";
+//! let (_, block) = parse_source(Span::new(source)).unwrap();
+//! assert_eq!(block.to_sexp(), "(fun fib (n) (if (== n 0) (return 0)))\n");
+//! ```
+//!
+//! Every statement of a [`Block`] is printed on its own line, nested blocks
+//! are inlined into the form of their statement. Suffixes are printed inside
+//! out, `a.b[0](x)` becomes `(call (index (. a b) 0) x)`.
+
+use crate::parser::{
+    expression::{ExprOrVarname, ExprSuffix, Expression, PrefixExpr},
+    literals::{Collection, Literal, Variable},
+    statement::{
+        declaration::{
+            class::Class,
+            function::Function,
+            match_statement::{MatchArm, Pattern},
+        },
+        import::Import,
+        Block, Statement,
+    },
+};
+
+/// Nodes which can be printed as S-expression
+pub trait ToSexp {
+    fn write_sexp(&self, out: &mut String);
+
+    fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        self.write_sexp(&mut out);
+        out
+    }
+}
+
+impl<'a> ToSexp for Block<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        for stmt in self.statements.iter() {
+            stmt.write_sexp(out);
+            out.push('\n');
+        }
+        if let Some(return_stmt) = &self.return_stmt {
+            list(out, "return", &return_stmt.values);
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes the statements of a nested block, each preceded by a space
+fn body(out: &mut String, block: &Block) {
+    for stmt in block.statements.iter() {
+        out.push(' ');
+        stmt.write_sexp(out);
+    }
+    if let Some(return_stmt) = &block.return_stmt {
+        out.push(' ');
+        list(out, "return", &return_stmt.values);
+    }
+}
+
+/// Writes `(head node...)`
+fn list<T: ToSexp>(out: &mut String, head: &str, nodes: &[T]) {
+    out.push('(');
+    out.push_str(head);
+    for node in nodes.iter() {
+        out.push(' ');
+        node.write_sexp(out);
+    }
+    out.push(')');
+}
+
+fn names(out: &mut String, names: &[Variable]) {
+    out.push('(');
+    let names: Vec<&str> = names.iter().map(|name| name.value).collect();
+    out.push_str(&names.join(" "));
+    out.push(')');
+}
+
+/// Wraps the declaration in `(pub ...)`, if it is exported
+fn exported(out: &mut String, exported: bool, write: impl FnOnce(&mut String)) {
+    if exported {
+        out.push_str("(pub ");
+        write(out);
+        out.push(')');
+    } else {
+        write(out);
+    }
+}
+
+impl<'a> ToSexp for Statement<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        match self {
+            Statement::Assignment(assignment) => {
+                out.push_str("(= ");
+                assignment.variable.write_sexp(out);
+                out.push(' ');
+                assignment.expression.write_sexp(out);
+                out.push(')');
+            }
+            Statement::LAssignment(assignment) => exported(out, assignment.exported, |out| {
+                out.push_str("(let ");
+                out.push_str(assignment.variable.value);
+                out.push(' ');
+                assignment.expression.write_sexp(out);
+                out.push(')');
+            }),
+            Statement::FuncCall(call) => call.write_sexp(out),
+            Statement::While(while_stmt) => {
+                out.push_str("(while ");
+                while_stmt.cond.write_sexp(out);
+                body(out, &while_stmt.block);
+                out.push(')');
+            }
+            Statement::For(for_stmt) => {
+                out.push_str("(for ");
+                out.push_str(for_stmt.iter_item.value);
+                out.push(' ');
+                for_stmt.iterator.write_sexp(out);
+                body(out, &for_stmt.block);
+                out.push(')');
+            }
+            Statement::If(if_stmt) => {
+                out.push_str("(if ");
+                if_stmt.cond.write_sexp(out);
+                body(out, &if_stmt.stmts);
+                if let Some(else_block) = &if_stmt.else_statements {
+                    out.push_str(" (else");
+                    body(out, else_block);
+                    out.push(')');
+                }
+                out.push(')');
+            }
+            Statement::Match(match_stmt) => {
+                out.push_str("(match ");
+                match_stmt.expr.write_sexp(out);
+                for arm in match_stmt.arms.iter() {
+                    out.push(' ');
+                    arm.write_sexp(out);
+                }
+                out.push(')');
+            }
+            Statement::Try(try_stmt) => {
+                out.push_str("(try");
+                body(out, &try_stmt.block);
+                if let Some(catch) = &try_stmt.catch {
+                    out.push_str(" (catch ");
+                    out.push_str(catch.binding.map_or("_", |binding| binding.value));
+                    body(out, &catch.block);
+                    out.push(')');
+                }
+                if let Some(finally) = &try_stmt.finally {
+                    out.push_str(" (finally");
+                    body(out, finally);
+                    out.push(')');
+                }
+                out.push(')');
+            }
+            Statement::Fun(function) => function.write_sexp(out),
+            Statement::Class(class) => class.write_sexp(out),
+            Statement::Return(return_stmt) => list(out, "return", &return_stmt.values),
+            Statement::Import(import) => import.write_sexp(out),
+            Statement::Raise(raise) => {
+                out.push_str("(raise ");
+                raise.value.write_sexp(out);
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl<'a> ToSexp for Function<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        exported(out, self.exported, |out| {
+            out.push_str("(fun ");
+            out.push_str(self.name.value);
+            out.push(' ');
+            names(out, &self.parameters);
+            body(out, &self.block);
+            out.push(')');
+        })
+    }
+}
+
+impl<'a> ToSexp for Class<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        exported(out, self.exported, |out| {
+            out.push_str("(class ");
+            out.push_str(self.name.value);
+            for method in self.methods.iter() {
+                out.push(' ');
+                method.write_sexp(out);
+            }
+            out.push(')');
+        })
+    }
+}
+
+impl<'a> ToSexp for Import<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        out.push_str("(import ");
+        if self.external {
+            out.push_str("external ");
+        }
+        let path: Vec<&str> = self.path.iter().map(|name| name.value).collect();
+        out.push_str(&path.join("."));
+        if let Some(alias) = &self.alias {
+            out.push_str(" (as ");
+            out.push_str(alias.value);
+            out.push(')');
+        }
+        if !self.items.is_empty() {
+            out.push(' ');
+            names(out, &self.items);
+        }
+        out.push(')');
+    }
+}
+
+impl<'a> ToSexp for MatchArm<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        out.push_str("(case ");
+        self.pattern.write_sexp(out);
+        if let Some(guard) = &self.guard {
+            out.push_str(" (guard ");
+            guard.write_sexp(out);
+            out.push(')');
+        }
+        body(out, &self.block);
+        out.push(')');
+    }
+}
+
+impl<'a> ToSexp for Pattern<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        match self {
+            Pattern::Literal(literal) => literal.write_sexp(out),
+            Pattern::Range(range) => {
+                out.push_str(if range.inclusive { "(..= " } else { "(.. " });
+                range.start.write_sexp(out);
+                out.push(' ');
+                range.end.write_sexp(out);
+                out.push(')');
+            }
+            Pattern::Wildcard(_) => out.push('_'),
+            Pattern::Binding(name) => out.push_str(name.value),
+            Pattern::Array(patterns) => list(out, "array", patterns),
+            Pattern::Map(patterns) => {
+                let mut entries: Vec<_> = patterns.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                out.push_str("(map");
+                for (key, pattern) in entries {
+                    out.push_str(&format!(" ({:?} ", key));
+                    pattern.write_sexp(out);
+                    out.push(')');
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl<'a> ToSexp for Expression<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        match self {
+            Expression::Literal(literal) => literal.write_sexp(out),
+            Expression::Call(call) => {
+                out.push_str("(call ");
+                out.push_str(call.callee.map_or("_", |callee| callee.value));
+                for arg in call.args.iter() {
+                    out.push(' ');
+                    arg.write_sexp(out);
+                }
+                out.push(')');
+            }
+            Expression::BinaryOp(op) => {
+                out.push_str(&format!("({} ", op.op));
+                op.left.write_sexp(out);
+                out.push(' ');
+                op.right.write_sexp(out);
+                out.push(')');
+            }
+            Expression::UnaryOp(op) => {
+                out.push_str(&format!("({} ", op.op));
+                op.operand.write_sexp(out);
+                out.push(')');
+            }
+            Expression::PrefixExpr(prefix_expr) => prefix_expr.write_sexp(out),
+            Expression::Collection(Collection::Array(values)) => list(out, "array", values),
+            Expression::Collection(Collection::Map(values)) => {
+                let mut entries: Vec<_> = values.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                out.push_str("(map");
+                for (key, value) in entries {
+                    out.push_str(&format!(" ({:?} ", key));
+                    value.write_sexp(out);
+                    out.push(')');
+                }
+                out.push(')');
+            }
+            Expression::Conditional(conditional) => {
+                out.push_str("(?: ");
+                conditional.cond.write_sexp(out);
+                out.push(' ');
+                conditional.then_expr.write_sexp(out);
+                out.push(' ');
+                conditional.else_expr.write_sexp(out);
+                out.push(')');
+            }
+            Expression::NilCoalesce(coalesce) => {
+                out.push_str("(?? ");
+                coalesce.left.write_sexp(out);
+                out.push(' ');
+                coalesce.right.write_sexp(out);
+                out.push(')');
+            }
+        }
+    }
+}
+
+impl<'a> ToSexp for PrefixExpr<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        // The innermost form is written last, so the chain is built from the
+        // outside: every suffix wraps everything in front of it
+        let mut prefix = match &self.prefix {
+            ExprOrVarname::Varname(name) => name.value.to_string(),
+            ExprOrVarname::Exp(expr) => expr.to_sexp(),
+        };
+
+        for suffix in self.suffix_chain.iter() {
+            let mut form = String::from("(");
+            match suffix {
+                ExprSuffix::TableDot(name) => {
+                    form.push_str(&format!(". {} {}", prefix, name.value))
+                }
+                ExprSuffix::SafeTableDot(name) => {
+                    form.push_str(&format!("?. {} {}", prefix, name.value))
+                }
+                ExprSuffix::TableIdx(index) => {
+                    form.push_str(&format!("index {} ", prefix));
+                    index.write_sexp(&mut form);
+                }
+                ExprSuffix::SafeTableIdx(index) => {
+                    form.push_str(&format!("?index {} ", prefix));
+                    index.write_sexp(&mut form);
+                }
+                ExprSuffix::TableSlice(slice) => {
+                    form.push_str(&format!("slice {}", prefix));
+                    for bound in [&slice.start, &slice.end].iter() {
+                        form.push(' ');
+                        match bound {
+                            Some(bound) => bound.write_sexp(&mut form),
+                            None => form.push('_'),
+                        }
+                    }
+                }
+                ExprSuffix::FuncCall(call) | ExprSuffix::SafeFuncCall(call) => {
+                    let dot = if matches!(suffix, ExprSuffix::SafeFuncCall(_)) {
+                        "?."
+                    } else {
+                        "."
+                    };
+                    match call.callee {
+                        Some(method) => {
+                            form.push_str(&format!("call ({} {} {})", dot, prefix, method.value))
+                        }
+                        // Like `?index`, a safe call without a method name
+                        None if dot == "?." => form.push_str(&format!("?call {}", prefix)),
+                        None => form.push_str(&format!("call {}", prefix)),
+                    }
+                    for arg in call.args.iter() {
+                        form.push(' ');
+                        arg.write_sexp(&mut form);
+                    }
+                }
+            }
+            form.push(')');
+            prefix = form;
+        }
+
+        out.push_str(&prefix);
+    }
+}
+
+impl<'a> ToSexp for Literal<'a> {
+    fn write_sexp(&self, out: &mut String) {
+        match self {
+            Literal::Str(token) => out.push_str(&format!("{:?}", token.value)),
+            Literal::Boolean(token) => out.push_str(if token.value { "True" } else { "False" }),
+            Literal::Nil(_) => out.push_str("Nil"),
+            Literal::Float(token) => out.push_str(&format!("{:?}", token.value)),
+            Literal::Int(token) => out.push_str(&token.value.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::parser::{parse_source, Span};
+
+    fn sexp(source: &str) -> String {
+        let (rest, block) = parse_source(Span::new(source)).unwrap();
+        assert_eq!(rest.fragment().trim(), "");
+        block.to_sexp()
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(sexp("x = 1 + 2 * 3"), "(= x (+ 1 (* 2 3)))\n");
+        assert_eq!(
+            sexp("x = -a.b[0](y, 2.0)"),
+            "(= x (- (call (index (. a b) 0) y 2.0)))\n"
+        );
+        assert_eq!(
+            sexp("x = a?.b(1)?[0]"),
+            "(= x (?index (call (?. a b) 1) 0))\n"
+        );
+        assert_eq!(sexp("x = f?.(1)"), "(= x (?call f 1))\n");
+        assert_eq!(
+            sexp("x = a ^ 2 <= b != c or d"),
+            "(= x (or (!= (<= (^ a 2) b) c) d))\n"
+        );
+        assert_eq!(
+            sexp("x = s[:2] ?? \"a b\""),
+            "(= x (?? (slice s _ 2) \"a b\"))\n"
+        );
+        assert_eq!(
+            sexp("x = if a then [1] else {\"k\": Nil}"),
+            "(= x (?: a (array 1) (map (\"k\" Nil))))\n"
+        );
+        assert_eq!(sexp("obj.method(True)"), "(call (. obj method) True)\n");
+    }
+
+    #[test]
+    fn test_statements() {
+        let source = "pub class A\n    fun f(a, b)\n        return\n    end\nend\ntry\n    raise 1\ncatch e do\n    print(e)\nfinally\n    for x in xs do\n    end\nend\nmatch x do\n    case 1..3 if y do\n    end\n    case [_, z] do\n    end\nend\nexternal import a.b as c\nfrom m import x, y\n";
+        assert_eq!(
+            sexp(source),
+            "(pub (class A (fun f (a b) (return))))
+(try (raise 1) (catch e (call print e)) (finally (for x xs)))
+(match x (case (.. 1 3) (guard y)) (case (array _ z)))
+(import external a.b (as c))
+(import m (x y))
+"
+        );
+    }
+}
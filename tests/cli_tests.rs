@@ -0,0 +1,105 @@
+//! Runs the `liva` binary
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn liva(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_liva"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Usage errors exit before stdin is read
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn parse_prints_sexp() {
+    let output = liva(&["parse", "-"], "let x = 1 + 2\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "(let x (+ 1 2))\n");
+}
+
+#[test]
+fn check_reports_errors() {
+    let output = liva(&["check", "examples"], "");
+    assert!(output.status.success());
+
+    let output = liva(&["check", "-"], "x = 1\nprint(y)\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "<stdin>:1:1: error: `x` is assigned before it is declared\n\
+         <stdin>:2:7: error: `y` is not defined\n"
+    );
+
+    let output = liva(&["check", "-"], "let x = \n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("<stdin>:1:1: error: "));
+}
+
+#[test]
+fn fmt_formats_stdin() {
+    let output = liva(&["fmt", "-"], "let  x=1\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "let x = 1\n");
+
+    let output = liva(&["fmt", "--check", "-"], "let  x=1\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "would reformat <stdin>\n");
+
+    let output = liva(&["fmt", "--check", "-"], "let x = 1\n");
+    assert!(output.status.success());
+}
+
+#[test]
+fn tokens_prints_positions() {
+    let output = liva(&["tokens", "-"], "let x\n");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "1:1 Keyword let\n1:5 Identifier x\n");
+}
+
+#[test]
+fn invalid_arguments() {
+    assert_eq!(liva(&[], "").status.code(), Some(2));
+    assert_eq!(liva(&["build", "-"], "").status.code(), Some(2));
+    assert_eq!(liva(&["check", "--check", "-"], "").status.code(), Some(2));
+    assert_eq!(liva(&["parse"], "").status.code(), Some(2));
+}
+
+#[test]
+fn livadoc_writes_modules() {
+    let dir = std::env::temp_dir().join(format!("livadoc-{}", std::process::id()));
+    let (project, output) = (dir.join("project"), dir.join("doc"));
+    std::fs::create_dir_all(project.join("lib")).unwrap();
+    std::fs::write(
+        project.join("lib").join("math.lv"),
+        "/// Squares a number\npub fun square(x)\n    return x * x\nend\n",
+    )
+    .unwrap();
+    std::fs::write(project.join("broken.lv"), "fun (\n").unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_livadoc"))
+        .arg("--output")
+        .arg(&output)
+        .arg(&project)
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    let module = std::fs::read_to_string(output.join("lib.math.md"));
+    let index = std::fs::read_to_string(output.join("index.md"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(
+        module.unwrap(),
+        "# Module `lib.math`\n\n## `fun square(x)`\n\nSquares a number\n"
+    );
+    assert_eq!(index.unwrap(), "# Modules\n\n* [`lib.math`](lib.math.md)\n");
+}
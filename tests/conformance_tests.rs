@@ -0,0 +1,357 @@
+//! Conformance tests of the grammar
+//!
+//! Every `.lv` file below tests/fixtures/conformance starts with a comment,
+//! which annotates what the parser has to make of the file:
+//!
+//! - `// expect: ok`, the file parses completely
+//! - `// expect-error: line 3 col 7 "message"`, parsing fails at exactly this
+//!   position with exactly this message
+//! - `// expect-ast: name.sexp`, the file parses to the S-expression in the
+//!   named file next to it
+//!
+//! Together the files are an executable specification of the grammar, so every
+//! kind of statement, expression, suffix, pattern and operator has to show up
+//! in one of them. Run with `LIVA_BLESS=1` to write the current output to the
+//! `.sexp` files.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use liva_parser::analysis::visit::{
+    walk_block, walk_expression, walk_pattern, walk_prefix_expr, walk_statement, Visitor,
+};
+use liva_parser::expression::{ExprSuffix, Expression, PrefixExpr};
+use liva_parser::literals::{Collection, Literal};
+use liva_parser::sexp::ToSexp;
+use liva_parser::statement::{declaration::match_statement::Pattern, Block, Statement};
+use liva_parser::tokens::{BINOP_PRECEDENCE, UNOPS};
+use liva_parser::{parse_complete, Span};
+
+const CONFORMANCE_DIR: &str = "tests/fixtures/conformance";
+
+#[derive(Debug, PartialEq)]
+enum Expectation {
+    Ok,
+    Error {
+        line: u32,
+        column: usize,
+        message: String,
+    },
+    Ast(PathBuf),
+}
+
+fn sources(dir: &Path, sources: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            self::sources(&path, sources);
+        } else if path.extension().is_some_and(|ext| ext == "lv") {
+            sources.push(path);
+        }
+    }
+}
+
+/// Reads the annotation from the comments at the start of the file, a
+/// shebang may come first
+fn expectation(path: &Path, source: &str) -> Result<Expectation, String> {
+    let mut expectations = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        if index == 0 && line.starts_with("#!") {
+            continue;
+        }
+        let comment = match line.trim().strip_prefix("//") {
+            Some(comment) => comment.trim(),
+            None => break,
+        };
+        if !comment.starts_with("expect") {
+            continue;
+        }
+        let expectation = if comment == "expect: ok" {
+            Expectation::Ok
+        } else if let Some(name) = comment.strip_prefix("expect-ast:") {
+            Expectation::Ast(path.with_file_name(name.trim()))
+        } else if let Some(error) = comment.strip_prefix("expect-error:") {
+            parse_error(error.trim())
+                .ok_or_else(|| format!("malformed annotation `{}`", comment))?
+        } else {
+            return Err(format!("unknown annotation `{}`", comment));
+        };
+        expectations.push(expectation);
+    }
+
+    match expectations.len() {
+        0 => Err("missing `// expect` annotation".to_string()),
+        1 => Ok(expectations.remove(0)),
+        _ => Err("more than one `// expect` annotation".to_string()),
+    }
+}
+
+/// Parses `line 3 col 7 "message"`
+fn parse_error(error: &str) -> Option<Expectation> {
+    let error = error.strip_prefix("line ")?;
+    let (line, error) = error.split_once(' ')?;
+    let error = error.strip_prefix("col ")?;
+    let (column, message) = error.split_once(' ')?;
+    let message = message.strip_prefix('"')?.strip_suffix('"')?;
+    Some(Expectation::Error {
+        line: line.parse().ok()?,
+        column: column.parse().ok()?,
+        message: message.replace("\\\"", "\"").replace("\\\\", "\\"),
+    })
+}
+
+/// Checks the file against its annotation, successfully parsed files are
+/// returned for the coverage check
+fn check<'a>(path: &Path, source: &'a str, bless: bool) -> Result<Option<Block<'a>>, String> {
+    let expectation = expectation(path, source)?;
+    let result = parse_complete(Span::new(source));
+
+    match (expectation, result) {
+        (Expectation::Ok, Ok(block)) => Ok(Some(block)),
+        (Expectation::Ast(sexp), Ok(block)) => {
+            let actual = block.to_sexp();
+            if bless {
+                fs::write(&sexp, &actual).unwrap();
+                return Ok(Some(block));
+            }
+            match fs::read_to_string(&sexp) {
+                Ok(expected) if expected == actual => Ok(Some(block)),
+                Ok(expected) => Err(format!(
+                    "{}:\n--- expected\n{}--- actual\n{}",
+                    sexp.display(),
+                    expected,
+                    actual
+                )),
+                Err(_) => Err(format!("{} is missing", sexp.display())),
+            }
+        }
+        (
+            Expectation::Error {
+                line,
+                column,
+                message,
+            },
+            Err(err),
+        ) => {
+            if err.position.line == line && err.position.column == column && err.message == message
+            {
+                Ok(None)
+            } else {
+                Err(format!(
+                    "expected error `line {} col {} {:?}`, got `line {} col {} {:?}`",
+                    line, column, message, err.position.line, err.position.column, err.message
+                ))
+            }
+        }
+        (Expectation::Error { .. }, Ok(_)) => Err("expected an error, but parsed".to_string()),
+        (_, Err(err)) => Err(format!("expected to parse, but failed: {}", err)),
+    }
+}
+
+/// Collects the kind of every node of the AST
+#[derive(Default)]
+struct Kinds(BTreeSet<String>);
+
+impl Kinds {
+    fn add(&mut self, kind: &str) {
+        self.0.insert(kind.to_string());
+    }
+}
+
+impl<'a> Visitor<'a> for Kinds {
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        self.add(match stmt {
+            Statement::Assignment(_) => "Assignment",
+            Statement::LAssignment(_) => "LAssignment",
+            Statement::FuncCall(_) => "FuncCallStatement",
+            Statement::While(_) => "While",
+            Statement::For(_) => "For",
+            Statement::If(_) => "If",
+            Statement::Match(_) => "Match",
+            Statement::Try(_) => "Try",
+            Statement::Fun(_) => "Fun",
+            Statement::Class(_) => "Class",
+            Statement::Return(_) => "Return",
+            Statement::Import(_) => "Import",
+            Statement::Raise(_) => "Raise",
+        });
+        walk_statement(self, stmt)
+    }
+
+    fn visit_block(&mut self, block: &Block<'a>) {
+        if block.return_stmt.is_some() {
+            self.add("Return");
+        }
+        walk_block(self, block)
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern<'a>) {
+        self.add(match pattern {
+            Pattern::Literal(_) => "LiteralPattern",
+            Pattern::Range(_) => "RangePattern",
+            Pattern::Wildcard(_) => "WildcardPattern",
+            Pattern::Binding(_) => "BindingPattern",
+            Pattern::Array(_) => "ArrayPattern",
+            Pattern::Map(_) => "MapPattern",
+        });
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression<'a>) {
+        self.add(match expr {
+            Expression::Literal(Literal::Str(_)) => "Str",
+            Expression::Literal(Literal::Boolean(_)) => "Boolean",
+            Expression::Literal(Literal::Nil(_)) => "Nil",
+            Expression::Literal(Literal::Float(_)) => "Float",
+            Expression::Literal(Literal::Int(_)) => "Int",
+            Expression::Call(_) => "Call",
+            Expression::BinaryOp(_) => "BinaryOp",
+            Expression::UnaryOp(_) => "UnaryOp",
+            Expression::PrefixExpr(_) => "PrefixExpr",
+            Expression::Collection(Collection::Array(_)) => "Array",
+            Expression::Collection(Collection::Map(_)) => "Map",
+            Expression::Conditional(_) => "Conditional",
+            Expression::NilCoalesce(_) => "NilCoalesce",
+        });
+        match expr {
+            Expression::BinaryOp(op) => self.add(&format!("{:?}", op.op)),
+            Expression::UnaryOp(op) => self.add(&format!("Unary{:?}", op.op)),
+            _ => {}
+        }
+        walk_expression(self, expr)
+    }
+
+    fn visit_prefix_expr(&mut self, prefix_expr: &PrefixExpr<'a>) {
+        for suffix in prefix_expr.suffix_chain.iter() {
+            self.add(match suffix {
+                ExprSuffix::TableDot(_) => "TableDot",
+                ExprSuffix::TableIdx(_) => "TableIdx",
+                ExprSuffix::TableSlice(_) => "TableSlice",
+                ExprSuffix::FuncCall(_) => "FuncCall",
+                ExprSuffix::SafeTableDot(_) => "SafeTableDot",
+                ExprSuffix::SafeTableIdx(_) => "SafeTableIdx",
+                ExprSuffix::SafeFuncCall(_) => "SafeFuncCall",
+            });
+        }
+        walk_prefix_expr(self, prefix_expr)
+    }
+}
+
+/// Every kind of node, which the files have to cover. `Expression::Call` is
+/// left out, the parser only builds calls as suffixes
+fn all_kinds() -> BTreeSet<String> {
+    let kinds = [
+        "Assignment",
+        "LAssignment",
+        "FuncCallStatement",
+        "While",
+        "For",
+        "If",
+        "Match",
+        "Try",
+        "Fun",
+        "Class",
+        "Return",
+        "Import",
+        "Raise",
+        "LiteralPattern",
+        "RangePattern",
+        "WildcardPattern",
+        "BindingPattern",
+        "ArrayPattern",
+        "MapPattern",
+        "Str",
+        "Boolean",
+        "Nil",
+        "Float",
+        "Int",
+        "BinaryOp",
+        "UnaryOp",
+        "PrefixExpr",
+        "Array",
+        "Map",
+        "Conditional",
+        "NilCoalesce",
+        "TableDot",
+        "TableIdx",
+        "TableSlice",
+        "FuncCall",
+        "SafeTableDot",
+        "SafeTableIdx",
+        "SafeFuncCall",
+    ];
+    let operators = BINOP_PRECEDENCE
+        .iter()
+        .flatten()
+        .map(|op| format!("{:?}", op));
+    let unary_operators = UNOPS.iter().map(|op| format!("Unary{:?}", op));
+    kinds
+        .iter()
+        .map(|kind| kind.to_string())
+        .chain(operators)
+        .chain(unary_operators)
+        .collect()
+}
+
+#[test]
+fn conformance_files() {
+    let bless = env::var_os("LIVA_BLESS").is_some();
+    let mut paths = Vec::new();
+    sources(Path::new(CONFORMANCE_DIR), &mut paths);
+    paths.sort();
+    assert!(!paths.is_empty(), "no files in {}", CONFORMANCE_DIR);
+
+    let mut failures = Vec::new();
+    let mut kinds = Kinds::default();
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        match check(&path, &source, bless) {
+            Ok(Some(block)) => kinds.visit_block(&block),
+            Ok(None) => {}
+            Err(failure) => failures.push(format!("{}: {}", path.display(), failure)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\nRun with LIVA_BLESS=1 to update the .sexp files",
+        failures.join("\n")
+    );
+
+    let missing: Vec<String> = all_kinds().difference(&kinds.0).cloned().collect();
+    assert!(
+        missing.is_empty(),
+        "no conformance file covers {}",
+        missing.join(", ")
+    );
+}
+
+#[test]
+fn annotations() {
+    let path = Path::new("dir/file.lv");
+    assert_eq!(expectation(path, "// expect: ok\n"), Ok(Expectation::Ok));
+    assert_eq!(
+        expectation(
+            path,
+            "#!/bin/liva\n// A comment\n// expect-ast: file.sexp\n"
+        ),
+        Ok(Expectation::Ast(PathBuf::from("dir/file.sexp")))
+    );
+    assert_eq!(
+        expectation(
+            path,
+            "// expect-error: line 3 col 7 \"unexpected `\\\"`\"\n"
+        ),
+        Ok(Expectation::Error {
+            line: 3,
+            column: 7,
+            message: "unexpected `\"`".to_string()
+        })
+    );
+    assert!(expectation(path, "print(1)\n// expect: ok\n").is_err());
+    assert!(expectation(path, "// expect: ok\n// expect: ok\n").is_err());
+    assert!(expectation(path, "// expect: fine\n").is_err());
+    assert!(expectation(path, "// expect-error: line 3 \"message\"\n").is_err());
+}
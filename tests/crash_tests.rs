@@ -0,0 +1,92 @@
+//! The parser, the lexer and the formatter must never panic
+//!
+//! Every input in tests/fixtures/crashes once crashed one of them, most were
+//! found and minimised by the fuzz targets in `fuzz/`. Add the minimised
+//! input of every new crash there.
+
+use std::fs;
+use std::path::PathBuf;
+
+use liva_parser::fuzzing::{check_lexer, check_roundtrip};
+use liva_parser::{parse_complete, parse_source, Span, MAX_NESTING};
+use proptest::prelude::*;
+
+const CRASHES_DIR: &str = "tests/fixtures/crashes";
+
+/// Runs everything the fuzz targets run on the source
+fn check(source: &str) -> Result<(), String> {
+    let _ = parse_source(Span::new(source));
+    check_lexer(source)?;
+    check_roundtrip(source)
+}
+
+#[test]
+fn crashes_are_fixed() {
+    let mut inputs: Vec<PathBuf> = fs::read_dir(CRASHES_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lv"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "no inputs in {}", CRASHES_DIR);
+
+    for path in inputs {
+        let source = fs::read_to_string(&path).unwrap();
+        if let Err(message) = check(&source) {
+            panic!("{}: {}", path.display(), message);
+        }
+    }
+}
+
+/// Parentheses, arrays and blocks nested `depth` times
+fn nested_sources(depth: usize) -> Vec<String> {
+    vec![
+        format!("x = {}1{}\n", "(".repeat(depth), ")".repeat(depth)),
+        format!("x = {}1{}\n", "[".repeat(depth), "]".repeat(depth)),
+        format!("{}{}", "if x do\n".repeat(depth), "end\n".repeat(depth)),
+    ]
+}
+
+#[test]
+fn deep_nesting_is_rejected() {
+    for source in nested_sources(10_000) {
+        let err = parse_complete(Span::new(&source)).unwrap_err();
+        assert_eq!(err.message, "nesting too deep");
+        assert_eq!(check(&source), Ok(()));
+    }
+}
+
+#[test]
+fn nesting_up_to_the_limit_is_parsed() {
+    for source in nested_sources(MAX_NESTING - 1) {
+        assert!(parse_complete(Span::new(&source)).is_ok(), "{}", source);
+        assert_eq!(check(&source), Ok(()));
+    }
+}
+
+const FRAGMENTS: &[&str] = &[
+    "let", "fun", "class", "end", "do", "if", "else", "while", "for", "in", "match", "case", "try",
+    "catch", "finally", "raise", "return", "import", "from", "as", "pub", "and", "or", "not",
+    "nil", "true", "x", "f", "1", "2.5", "\"s\"", "'s'", "(", ")", "[", "]", "{", "}", ",", ".",
+    "?.", "..", "..=", ":", "=", "==", "+", "-", "*", "/", "^", "<", ">=", "??", "//", "/*", "*/",
+    "#!", "\\", "\n", " ", "    ",
+];
+
+fn token_soup() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(FRAGMENTS), 0..24)
+        .prop_map(|fragments| fragments.concat())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn never_panics_on_token_soup(source in token_soup()) {
+        prop_assert_eq!(check(&source), Ok(()));
+    }
+
+    #[test]
+    fn never_panics_on_any_string(source in "\\PC{0,32}") {
+        prop_assert_eq!(check(&source), Ok(()));
+    }
+}
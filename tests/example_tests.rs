@@ -28,10 +28,10 @@ fn run_all_examples() {
         println!("Testing example file: {}", example.display());
         let ast = parse_source(Span::new(source.as_str()));
 
-        if !ast.is_ok() {
+        if ast.is_err() {
             println!("{:?}", ast);
         }
 
-        assert_eq!(ast.is_ok(), true);
+        assert!(ast.is_ok());
     }
 }
@@ -0,0 +1,122 @@
+//! Golden tests of the parser and the formatter
+//!
+//! Every `.lv` file in tests/fixtures/golden is parsed and printed as
+//! S-expression, the output has to match the `.sexp` file next to it. Every
+//! `.lv` file in tests/fixtures/format is formatted, the output or the error
+//! has to match the `.fmt` file next to it. Run with `LIVA_BLESS=1` to write
+//! the current output to the `.sexp` and `.fmt` files.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use liva_parser::formatter::format_source;
+use liva_parser::sexp::ToSexp;
+use liva_parser::{parse_source, Span};
+
+const GOLDEN_DIR: &str = "tests/fixtures/golden";
+const FORMAT_DIR: &str = "tests/fixtures/format";
+
+fn sources(dir: &Path) -> Vec<PathBuf> {
+    let mut sources: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lv"))
+        .collect();
+    sources.sort();
+    sources
+}
+
+fn render(path: &Path) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    let (rest, block) = parse_source(Span::new(&source)).unwrap();
+    assert!(
+        rest.fragment().trim().is_empty(),
+        "{} was not parsed completely, rest at line {}",
+        path.display(),
+        rest.location_line()
+    );
+    block.to_sexp()
+}
+
+/// Compares the output for every source file with the golden file with the
+/// given extension
+fn check_golden(dir: &str, extension: &str, render: impl Fn(&Path) -> String) {
+    let bless = env::var_os("LIVA_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for path in sources(Path::new(dir)) {
+        let actual = render(&path);
+        let golden = path.with_extension(extension);
+
+        if bless {
+            fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}:\n--- expected\n{}--- actual\n{}",
+                golden.display(),
+                expected,
+                actual
+            )),
+            Err(_) => failures.push(format!("{} is missing", golden.display())),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\nRun with LIVA_BLESS=1 to update the golden files",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn golden_files() {
+    check_golden(GOLDEN_DIR, "sexp", render);
+}
+
+/// Formatted files also have to be stable
+#[test]
+fn formatted_files() {
+    check_golden(FORMAT_DIR, "fmt", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        match format_source(&source) {
+            Ok(formatted) => {
+                assert_eq!(
+                    format_source(&formatted).unwrap(),
+                    formatted,
+                    "{}",
+                    path.display()
+                );
+                formatted
+            }
+            Err(err) => format!("error: {}\n", err),
+        }
+    });
+}
+
+/// Formatting must not change the AST and formatting twice must not change
+/// the output anymore
+#[test]
+fn formatting_keeps_ast() {
+    let mut paths = sources(Path::new(GOLDEN_DIR));
+    paths.extend(sources(Path::new("examples")));
+
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let formatted = format_source(&source).unwrap();
+
+        let (_, before) = parse_source(Span::new(&source)).unwrap();
+        let (_, after) = parse_source(Span::new(&formatted)).unwrap();
+        assert_eq!(before.to_sexp(), after.to_sexp(), "{}", path.display());
+        assert_eq!(
+            format_source(&formatted).unwrap(),
+            formatted,
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! The editor grammars in the examples directory have to match the grammars
+//! generated from the token tables of the parser.
+//! Run with `LIVA_BLESS=1` to write the generated grammars to the examples.
+
+use std::env;
+use std::fs;
+
+use liva_parser::highlight::grammar::{textmate_grammar, vim_syntax};
+
+fn check(path: &str, generated: String) {
+    if env::var_os("LIVA_BLESS").is_some() {
+        fs::write(path, &generated).unwrap();
+        return;
+    }
+    let current = fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        current == generated,
+        "{} is out of date, run with LIVA_BLESS=1 to update it",
+        path
+    );
+}
+
+#[test]
+fn vim_syntax_is_up_to_date() {
+    check("examples/liva.vim", vim_syntax());
+}
+
+#[test]
+fn textmate_grammar_is_up_to_date() {
+    let grammar = textmate_grammar();
+    let json: serde_json::Value = serde_json::from_str(&grammar).unwrap();
+    assert_eq!(json["scopeName"], "source.liva");
+    assert_eq!(
+        json["repository"]["keywords"]["match"],
+        format!("\\b({})\\b", liva_parser::tokens::KEYWORDS.join("|"))
+    );
+    check("examples/liva.tmLanguage.json", grammar);
+}
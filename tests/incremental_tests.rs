@@ -0,0 +1,105 @@
+//! Incremental reparsing has to give the same result as a full parse
+//!
+//! Programs are built from snippets of statements and edited with random
+//! fragments, which often break or join blocks.
+
+use liva_parser::incremental::{IncrementalParse, TextEdit};
+use liva_parser::{parse_source, Span};
+use proptest::prelude::*;
+
+const SNIPPETS: &[&str] = &[
+    "let a = 1\n",
+    "let b = a + 2 * 3\n",
+    "c = [1, 2, 3]\n",
+    "print(a, b)\n",
+    "fun f(x)\n    return x + 1\nend\n",
+    "fun g()\n    let y = f(2)\n    print(y)\nend\n",
+    "if a < 2 do\n    print(a)\nelse\n    print(b)\nend\n",
+    "while a > 0 do\n    a = a - 1\nend\n",
+    "for i in c do\n    print(i)\nend\n",
+    "class Point\n    fun new(x)\n        return x\n    end\nend\n",
+    "match a do\n    case 1 do\n        print(1)\n    end\n    case _ do\n        print(0)\n    end\nend\n",
+    "try\n    raise \"error\"\ncatch err do\n    print(err)\nend\n",
+    "import math\n",
+    "pub fun h()\nend\n",
+    "// comment\n",
+    "return a\n",
+    "\n",
+];
+
+const FRAGMENTS: &[&str] = &[
+    "",
+    "end",
+    "end\n",
+    "\n",
+    " ",
+    "do",
+    "x",
+    "1",
+    "+ 1",
+    "(",
+    ")",
+    "let ",
+    "fun f()\n",
+    "if x do\n",
+    "return 1\n",
+    "\"",
+    "//",
+    "print(x)\n",
+];
+
+fn program() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(SNIPPETS), 0..8)
+        .prop_map(|snippets| snippets.concat())
+}
+
+/// Positions and lengths are scaled to the source, which is edited
+fn edits() -> impl Strategy<Value = Vec<(f64, f64, &'static str)>> {
+    prop::collection::vec(
+        (
+            0.0..=1.0,
+            prop::sample::select(vec![0.0, 0.0, 0.02, 0.1, 0.3]),
+            prop::sample::select(FRAGMENTS),
+        ),
+        1..5,
+    )
+}
+
+fn edit(source: &str, (position, length, text): (f64, f64, &str)) -> TextEdit {
+    let start = (position * source.len() as f64) as usize;
+    let end = (start + (length * source.len() as f64) as usize).min(source.len());
+    TextEdit::new(start..end, text)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn reparse_equals_full_parse(source in program(), edits in edits()) {
+        let mut sources = vec![source];
+        for &change in edits.iter() {
+            let last = sources.last().unwrap();
+            let edited = edit(last, change).apply(last);
+            sources.push(edited);
+        }
+
+        let mut parse = IncrementalParse::new(&sources[0]).ok();
+        for (i, &change) in edits.iter().enumerate() {
+            let (previous, source) = (&sources[i], &sources[i + 1]);
+            let edit = edit(previous, change);
+
+            // After a failed parse there is nothing to reuse
+            let result = match parse {
+                Some(parse) => parse.reparse(source, &edit),
+                None => IncrementalParse::new(source),
+            };
+            let actual = format!(
+                "{:?}",
+                result.as_ref().map(|parse| (parse.rest(), parse.block()))
+            );
+            let expected = format!("{:?}", parse_source(Span::new(source)));
+            prop_assert_eq!(actual, expected, "source {:?}", source);
+            parse = result.ok();
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fs;
+
+use liva_parser::interp::{value::Value, Interpreter};
+use liva_parser::{parse_source, Span};
+
+/// Parses and runs an example file, returns the interpreter to inspect
+/// its globals and output
+fn run_example(source: &str) -> Interpreter<'_, Vec<u8>> {
+    let (rest, block) = parse_source(Span::new(source)).unwrap();
+    assert_eq!(rest.fragment().trim(), "");
+
+    let mut interpreter = Interpreter::with_output(Vec::new());
+    interpreter.run(&block).unwrap();
+    interpreter
+}
+
+#[test]
+fn run_fib_example() {
+    let source = fs::read_to_string("examples/fib.lv").unwrap();
+    let mut interpreter = run_example(&source);
+
+    let fib = interpreter.get("fib").unwrap();
+    let results: Vec<Value> = (1..=10)
+        .map(|n| interpreter.call(&fib, vec![Value::Int(n)]).unwrap())
+        .collect();
+    let expected: Vec<Value> = [1, 1, 2, 3, 5, 8, 13, 21, 34, 55]
+        .iter()
+        .map(|n| Value::Int(*n))
+        .collect();
+    assert_eq!(results, expected);
+}
+
+#[test]
+fn run_class_example() {
+    let source = fs::read_to_string("examples/class.lv").unwrap();
+    let interpreter = run_example(&source);
+
+    let output = String::from_utf8(interpreter.output().clone()).unwrap();
+    assert_eq!(output, "1.0\n0.5\n0.3333333333333333\n0.25\n");
+}
@@ -0,0 +1,96 @@
+//! Reserved words are only matched as whole words
+//!
+//! Every reserved word is tried as prefix of an identifier, like `format`,
+//! `ending` or `nilly`, in every position an identifier can take. The
+//! identifier must parse as one name, not as the reserved word followed by
+//! another name.
+
+use liva_parser::sexp::ToSexp;
+use liva_parser::tokens::RESERVED_WORDS;
+use liva_parser::{parse_complete, Span};
+
+/// Sources with the identifier `N` and the S-expressions they parse to
+const CASES: &[(&str, &str)] = &[
+    ("let N = 1", "(let N 1)"),
+    ("N = N + 1", "(= N (+ N 1))"),
+    ("N(N, -N)", "(call N N (- N))"),
+    ("N.N = [N]", "(= (. N N) (array N))"),
+    ("N?.N(N[N])", "(call (?. N N) (index N N))"),
+    ("x = {\"k\": N}", "(= x (map (\"k\" N)))"),
+    ("fun N(N)\n    return N\nend", "(fun N (N) (return N))"),
+    (
+        "class N\n    fun N(N)\n    end\nend",
+        "(class N (fun N (N)))",
+    ),
+    ("for N in N do\nend", "(for N N)"),
+    ("while N do\nend", "(while N)"),
+    ("if N do\nelse\nend", "(if N (else))"),
+    ("x = N and N or not N", "(= x (or (and N N) (not N)))"),
+    ("x = if N then N else N ?? N", "(= x (?: N N (?? N N)))"),
+    (
+        "match N do\n    case N if N do\n    end\n    case [N] do\n    end\nend",
+        "(match N (case N (guard N)) (case (array N)))",
+    ),
+    ("try\n    N()\ncatch N do\nend", "(try (call N) (catch N))"),
+    ("raise N", "(raise N)"),
+    ("import N.N as N", "(import N.N (as N))"),
+    ("external import N", "(import external N)"),
+    ("from N import N, N", "(import N (N N))"),
+    ("pub let N = N", "(pub (let N N))"),
+    ("return N, N", "(return N N)"),
+];
+
+/// Identifiers, which start with the reserved word
+fn identifiers(word: &str) -> Vec<String> {
+    let mut identifiers = vec![
+        format!("{}x", word),
+        format!("{}_", word),
+        format!("{}1", word),
+        format!("{}{}", word, word),
+    ];
+    // Literal words are case insensitive
+    if ["true", "false", "nil"].contains(&word) {
+        identifiers.push(format!("{}X", word.to_uppercase()));
+    }
+    identifiers
+}
+
+#[test]
+fn identifiers_may_start_with_reserved_words() {
+    let mut failures = Vec::new();
+    for word in RESERVED_WORDS.iter() {
+        for name in identifiers(word) {
+            for (source, sexp) in CASES {
+                let source = source.replace('N', &name);
+                let expected = sexp.replace('N', &name);
+                match parse_complete(Span::new(&source)) {
+                    Ok(block) if block.to_sexp().trim_end() == expected => {}
+                    Ok(block) => failures.push(format!(
+                        "{:?} parsed to {}",
+                        source,
+                        block.to_sexp().trim_end()
+                    )),
+                    Err(err) => failures.push(format!("{:?} failed: {}", source, err)),
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn reserved_words_are_no_identifiers() {
+    for word in RESERVED_WORDS.iter() {
+        for source in [
+            format!("let {} = 1", word),
+            format!("fun {}()\nend", word),
+            format!("for {} in x do\nend", word),
+        ] {
+            assert!(
+                parse_complete(Span::new(&source)).is_err(),
+                "{:?} parsed",
+                source
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Drives the `liva-lsp` binary with a scripted JSON-RPC client
+#![cfg(feature = "lsp")]
+
+use std::fs;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use liva_parser::lsp::transport::{read_message, write_message};
+use serde_json::{json, Value};
+
+const MAIN: &str = "from util import double
+
+fun twice(a)
+    return double(double(a))
+end
+
+print(twice(x))
+";
+
+const UTIL: &str = "// Helpers
+pub fun double(n)
+    return n * 2
+end
+";
+
+/// Sends all messages and returns the messages of the server and its exit code
+fn session(messages: &[Value]) -> (Vec<Value>, Option<i32>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_liva-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    for message in messages {
+        write_message(&mut stdin, message).unwrap();
+    }
+    stdin.flush().unwrap();
+    drop(stdin);
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut received = Vec::new();
+    while let Some(body) = read_message(&mut stdout).unwrap() {
+        received.push(serde_json::from_str(&body).unwrap());
+    }
+    (received, child.wait().unwrap().code())
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    let message = messages
+        .iter()
+        .find(|message| message["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {}", id));
+    &message["result"]
+}
+
+fn diagnostics(messages: &[Value]) -> Vec<&Vec<Value>> {
+    messages
+        .iter()
+        .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+        .map(|message| message["params"]["diagnostics"].as_array().unwrap())
+        .collect()
+}
+
+fn workspace() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("liva-lsp-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.lv"), MAIN).unwrap();
+    fs::write(dir.join("util.lv"), UTIL).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+#[test]
+fn scripted_session() {
+    let root = workspace();
+    let root_uri = format!("file://{}", root.display());
+    let uri = format!("{}/main.lv", root_uri);
+    let document = json!({"uri": uri});
+    let position = |line: u64, character: u64| json!({"textDocument": document, "position": {"line": line, "character": character}});
+
+    let (messages, code) = session(&[
+        request(
+            1,
+            "initialize",
+            json!({"rootUri": root_uri, "capabilities": {}}),
+        ),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({"textDocument": {"uri": uri, "languageId": "liva", "version": 1, "text": MAIN}}),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": {"uri": uri, "version": 2},
+                "contentChanges": [{"text": MAIN.replace("print(twice(x))", "let x = 1\nprint(twice(x))")}],
+            }),
+        ),
+        request(
+            2,
+            "textDocument/documentSymbol",
+            json!({"textDocument": document}),
+        ),
+        request(3, "textDocument/hover", position(3, 12)),
+        request(4, "textDocument/definition", position(3, 20)),
+        request(5, "textDocument/definition", position(7, 7)),
+        request(
+            6,
+            "textDocument/foldingRange",
+            json!({"textDocument": document}),
+        ),
+        request(7, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ]);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        response(&messages, 1)["capabilities"]["definitionProvider"],
+        true
+    );
+
+    // `x` is undefined until the second version of the document
+    let published = diagnostics(&messages);
+    assert_eq!(published.len(), 2);
+    assert_eq!(published[0].len(), 1);
+    assert_eq!(published[0][0]["message"], "`x` is not defined");
+    assert_eq!(
+        published[0][0]["range"],
+        json!({"start": {"line": 6, "character": 12}, "end": {"line": 6, "character": 13}})
+    );
+    assert!(published[1].is_empty());
+
+    let symbols = response(&messages, 2).as_array().unwrap();
+    let names: Vec<&str> = symbols
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["util", "twice", "x"]);
+    assert_eq!(symbols[1]["detail"], "(a)");
+    assert_eq!(
+        symbols[1]["range"],
+        json!({"start": {"line": 2, "character": 0}, "end": {"line": 4, "character": 3}})
+    );
+
+    assert_eq!(
+        response(&messages, 3)["contents"]["value"],
+        "(import) double"
+    );
+
+    // Imported names lead to the declaration in the module
+    let definition = response(&messages, 4);
+    assert_eq!(definition["uri"], format!("{}/util.lv", root_uri));
+    assert_eq!(
+        definition["range"],
+        json!({"start": {"line": 1, "character": 8}, "end": {"line": 1, "character": 14}})
+    );
+
+    let definition = response(&messages, 5);
+    assert_eq!(definition["uri"], uri);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({"line": 2, "character": 4})
+    );
+
+    assert_eq!(
+        response(&messages, 6),
+        &json!([{"startLine": 2, "endLine": 3}])
+    );
+}
+
+#[test]
+fn exit_without_shutdown() {
+    let (messages, code) = session(&[
+        request(1, "initialize", json!({"capabilities": {}})),
+        request(
+            2,
+            "textDocument/hover",
+            json!({"textDocument": {"uri": "file:///missing.lv"}, "position": {"line": 0, "character": 0}}),
+        ),
+        notification("exit", Value::Null),
+    ]);
+    assert_eq!(code, Some(1));
+    assert_eq!(messages[1]["error"]["code"], -32602);
+}
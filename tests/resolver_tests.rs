@@ -0,0 +1,101 @@
+//! Resolve the imports of the fixture projects in tests/fixtures/resolver
+
+use liva_parser::position::Position;
+use liva_parser::resolver::{ModuleOrigin, ResolveError, Resolver, SearchPaths};
+use std::path::{Path, PathBuf};
+
+fn resolver() -> Resolver {
+    let fixtures = Path::new("tests/fixtures/resolver");
+    Resolver::new(SearchPaths {
+        project: fixtures.join("project"),
+        stdlib: Some(fixtures.join("stdlib")),
+        site_packages: Some(fixtures.join("site-packages")),
+    })
+}
+
+#[test]
+fn resolve_module_graph() {
+    let graph = resolver().resolve(Path::new("tests/fixtures/resolver/project/main.lv"));
+
+    let names: Vec<(&str, ModuleOrigin)> = graph
+        .modules
+        .iter()
+        .map(|m| (m.name.as_str(), m.origin))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("main", ModuleOrigin::Root),
+            ("util", ModuleOrigin::Project),
+            ("shapes.circle", ModuleOrigin::Project),
+            ("math", ModuleOrigin::Stdlib),
+            ("colors", ModuleOrigin::External),
+        ]
+    );
+
+    let colors = graph.find("colors").unwrap();
+    assert!(graph.modules[colors].path.ends_with("colors/init.lv"));
+
+    let circle = graph.find("shapes.circle").unwrap();
+    let deps: Vec<usize> = graph.modules[circle]
+        .dependencies
+        .iter()
+        .map(|d| d.module)
+        .collect();
+    assert_eq!(
+        deps,
+        vec![graph.find("util").unwrap(), graph.find("math").unwrap()]
+    );
+}
+
+#[test]
+fn report_missing_modules_and_cycles() {
+    let graph = resolver().resolve(Path::new("tests/fixtures/resolver/project/main.lv"));
+
+    assert_eq!(graph.errors.len(), 2);
+    assert_eq!(
+        graph.errors[0],
+        ResolveError::MissingModule {
+            importer: PathBuf::from("tests/fixtures/resolver/project/main.lv"),
+            module: String::from("missing.module"),
+            external: false,
+            position: Position::new(5, 8),
+        }
+    );
+
+    match &graph.errors[1] {
+        ResolveError::Cycle { modules } => {
+            assert_eq!(modules.len(), 2);
+            assert!(modules[0].ends_with("util.lv"));
+            assert!(modules[1].ends_with("shapes/circle.lv"));
+        }
+        error => panic!("Expected import cycle, got {:?}", error),
+    }
+}
+
+#[test]
+fn report_parse_errors_at_their_position() {
+    let graph = resolver().resolve(Path::new("tests/fixtures/resolver/project/broken.lv"));
+
+    assert_eq!(
+        graph.errors,
+        vec![ResolveError::Parse {
+            path: PathBuf::from("tests/fixtures/resolver/project/broken.lv"),
+            position: Position::new(3, 13),
+        }]
+    );
+}
+
+#[test]
+fn external_imports_only_use_site_packages() {
+    let graph = resolver().resolve(Path::new("tests/fixtures/resolver/project/flat.lv"));
+
+    let empty = graph.find("empty").unwrap();
+    assert!(graph.modules[empty].path.is_dir());
+
+    assert_eq!(graph.errors.len(), 1);
+    assert_eq!(
+        graph.errors[0].to_string(),
+        "tests/fixtures/resolver/project/flat.lv:2:17: external module `util` not found"
+    );
+}
@@ -0,0 +1,508 @@
+//! Printing a syntax tree and parsing the output has to give the same tree
+//!
+//! Random trees are generated, printed with `format_block` and parsed again.
+//! Operands are put in parentheses wherever `BINOP_PRECEDENCE` requires it,
+//! so a tree which comes back with a different nesting shows a precedence
+//! bug of the parser. Failures are shrunk to minimal programs.
+//!
+//! Spans of reparsed trees differ from the generated ones, so the trees are
+//! compared by their S-expressions, which show the nesting, and by their
+//! printed source, which shows the parentheses and the spelling of literals.
+
+use liva_parser::expression::{
+    binary::{BinaryOp, UnaryOp},
+    call::Call,
+    conditional::{Conditional, NilCoalesce},
+    ExprOrVarname, ExprSuffix, Expression, PrefixExpr, Slice,
+};
+use liva_parser::formatter::format_block;
+use liva_parser::literals::{Collection, Literal, Token, Variable};
+use liva_parser::sexp::ToSexp;
+use liva_parser::statement::{
+    declaration::{
+        assignment::{Assignment, LAssignment},
+        class::Class,
+        for_statement::For,
+        function::Function,
+        if_statement::If,
+        match_statement::{Match, MatchArm, Pattern, RangePattern},
+        try_statement::{Catch, Try},
+        while_statement::While,
+    },
+    import::Import,
+    Block, RaiseStmt, ReturnStmt, Statement,
+};
+use liva_parser::tokens::{
+    is_reserved, Operator, UnOperator, BINOP_PRECEDENCE, RESERVED_WORDS, UNOPS,
+};
+use liva_parser::{parse_complete, Span};
+use proptest::collection::{hash_map, vec};
+use proptest::option;
+use proptest::prelude::*;
+
+type Expr = Expression<'static>;
+
+/// Trees borrow their names and literals from the source code, generated
+/// ones live until the end of the test
+fn leak(text: String) -> &'static str {
+    Box::leak(text.into_boxed_str())
+}
+
+fn token<T>(value: T, text: &'static str) -> Token<'static, T> {
+    Token::new(value, Span::new(text))
+}
+
+/// Names as accepted by `parse_variable_raw`, which are no reserved words.
+/// Names starting with a reserved word, like `iffy` or `nil_value`, are
+/// generated on purpose
+fn identifier() -> impl Strategy<Value = Variable<'static>> {
+    let prefixed = (
+        prop::sample::select(&RESERVED_WORDS[..]),
+        "[A-Za-z0-9_]{0,3}",
+    )
+        .prop_map(|(word, rest)| format!("{}{}", word, rest));
+    prop_oneof!["[A-Za-z_][A-Za-z0-9_]{0,5}", prefixed]
+        .prop_filter("reserved words are no identifiers", |name| {
+            !is_reserved(name)
+        })
+        .prop_map(|name| {
+            let name = leak(name);
+            token(name, name)
+        })
+}
+
+/// The raw content of a string literal
+fn string_content() -> impl Strategy<Value = String> {
+    vec(
+        prop_oneof![
+            "[A-Za-z0-9 ]{1,4}",
+            Just(String::from("\\n")),
+            Just(String::from("\\\"")),
+            Just(String::from("\\\\")),
+        ],
+        0..3,
+    )
+    .prop_map(|parts| parts.concat())
+}
+
+fn literal() -> impl Strategy<Value = Literal<'static>> {
+    prop_oneof![
+        (0..=i32::MAX).prop_map(|value| Literal::Int(token(value, leak(value.to_string())))),
+        ("[0-9]{1,3}", "[0-9]{1,3}").prop_map(|(int, fraction)| {
+            let text = leak(format!("{}.{}", int, fraction));
+            Literal::Float(token(text.parse().unwrap(), text))
+        }),
+        string_content().prop_map(|content| {
+            let text = leak(content.clone());
+            Literal::Str(token(content, text))
+        }),
+        any::<bool>()
+            .prop_map(|value| Literal::Boolean(token(value, if value { "True" } else { "False" }))),
+        Just(Literal::Nil(Span::new("Nil").into())),
+    ]
+}
+
+/// How strong an expression binds, operands which bind weaker than their
+/// operator have to be put in parentheses
+fn binding(expr: &Expr) -> usize {
+    match expr {
+        Expression::Conditional(_) => 0,
+        Expression::NilCoalesce(_) => 1,
+        Expression::BinaryOp(op) => operator_binding(&op.op),
+        // Unary operators bind stronger than all binary operators but `^`
+        Expression::UnaryOp(_) => operator_binding(&Operator::Pow) - 1,
+        _ => 2 * BINOP_PRECEDENCE.len() + 1,
+    }
+}
+
+fn operator_binding(op: &Operator) -> usize {
+    let level = BINOP_PRECEDENCE
+        .iter()
+        .position(|ops| ops.contains(op))
+        .unwrap();
+    2 * (BINOP_PRECEDENCE.len() - level)
+}
+
+fn parenthesized(expr: Expr) -> Expr {
+    Expression::PrefixExpr(Box::new(PrefixExpr {
+        prefix: ExprOrVarname::Exp(expr),
+        suffix_chain: vec![],
+    }))
+}
+
+/// Puts the expression in parentheses, unless it binds at least as strong
+fn operand(expr: Expr, at_least: usize) -> Expr {
+    if binding(&expr) < at_least {
+        parenthesized(expr)
+    } else {
+        expr
+    }
+}
+
+/// All binary operators are left associative
+fn binary(left: Expr, op: Operator, right: Expr) -> Expr {
+    let strength = operator_binding(&op);
+    Expression::BinaryOp(Box::new(BinaryOp {
+        left: operand(left, strength),
+        right: operand(right, strength + 1),
+        op,
+    }))
+}
+
+fn unary(op: UnOperator, operand_expr: Expr) -> Expr {
+    let operand_expr = operand(operand_expr, operator_binding(&Operator::Pow) - 1);
+    Expression::UnaryOp(Box::new(UnaryOp {
+        op,
+        operand: operand_expr,
+    }))
+}
+
+fn binary_operator() -> impl Strategy<Value = Operator> {
+    prop::sample::select(BINOP_PRECEDENCE.concat())
+}
+
+fn unary_operator() -> impl Strategy<Value = UnOperator> {
+    prop::sample::select(UNOPS.clone())
+}
+
+/// `.name` followed by a call is the method call `.name(...)`
+fn method_calls(suffixes: Vec<ExprSuffix<'static>>) -> Vec<ExprSuffix<'static>> {
+    let mut chain: Vec<ExprSuffix<'static>> = Vec::new();
+    for suffix in suffixes {
+        let suffix = match (chain.last(), suffix) {
+            (
+                Some(ExprSuffix::TableDot(name)),
+                ExprSuffix::FuncCall(Call { callee: None, args }),
+            ) => {
+                let callee = Some(*name);
+                chain.pop();
+                ExprSuffix::FuncCall(Call { callee, args })
+            }
+            (
+                Some(ExprSuffix::SafeTableDot(name)),
+                ExprSuffix::FuncCall(Call { callee: None, args }),
+            ) => {
+                let callee = Some(*name);
+                chain.pop();
+                ExprSuffix::SafeFuncCall(Call { callee, args })
+            }
+            (_, suffix) => suffix,
+        };
+        chain.push(suffix);
+    }
+    chain
+}
+
+fn call(expr: BoxedStrategy<Expr>) -> impl Strategy<Value = Call<'static>> {
+    (option::of(identifier()), vec(expr, 0..3)).prop_map(|(callee, args)| Call { callee, args })
+}
+
+fn suffix(expr: BoxedStrategy<Expr>) -> impl Strategy<Value = ExprSuffix<'static>> {
+    prop_oneof![
+        identifier().prop_map(ExprSuffix::TableDot),
+        identifier().prop_map(ExprSuffix::SafeTableDot),
+        expr.clone().prop_map(ExprSuffix::TableIdx),
+        expr.clone().prop_map(ExprSuffix::SafeTableIdx),
+        (option::of(expr.clone()), option::of(expr.clone()))
+            .prop_map(|(start, end)| ExprSuffix::TableSlice(Slice { start, end })),
+        call(expr.clone()).prop_map(|call| ExprSuffix::FuncCall(Call {
+            callee: None,
+            ..call
+        })),
+        call(expr).prop_map(ExprSuffix::SafeFuncCall),
+    ]
+}
+
+fn variable(name: Variable<'static>) -> PrefixExpr<'static> {
+    PrefixExpr {
+        prefix: ExprOrVarname::Varname(name),
+        suffix_chain: vec![],
+    }
+}
+
+fn expression() -> BoxedStrategy<Expr> {
+    let leaf = prop_oneof![
+        literal().prop_map(Expression::Literal),
+        identifier().prop_map(|name| Expression::PrefixExpr(Box::new(variable(name)))),
+    ];
+    leaf.prop_recursive(4, 32, 3, |inner| {
+        prop_oneof![
+            (inner.clone(), binary_operator(), inner.clone())
+                .prop_map(|(left, op, right)| binary(left, op, right)),
+            (unary_operator(), inner.clone()).prop_map(|(op, operand)| unary(op, operand)),
+            (
+                prop_oneof![
+                    identifier().prop_map(ExprOrVarname::Varname),
+                    inner.clone().prop_map(ExprOrVarname::Exp),
+                ],
+                vec(suffix(inner.clone()), 0..3),
+            )
+                .prop_map(|(prefix, suffixes)| {
+                    Expression::PrefixExpr(Box::new(PrefixExpr {
+                        prefix,
+                        suffix_chain: method_calls(suffixes),
+                    }))
+                }),
+            vec(inner.clone(), 0..3)
+                .prop_map(|values| Expression::Collection(Collection::Array(values))),
+            hash_map(string_content(), inner.clone(), 0..3)
+                .prop_map(|values| Expression::Collection(Collection::Map(values))),
+            (inner.clone(), inner.clone(), inner.clone()).prop_map(
+                |(cond, then_expr, else_expr)| {
+                    Expression::Conditional(Box::new(Conditional {
+                        cond,
+                        then_expr,
+                        else_expr,
+                    }))
+                }
+            ),
+            // `??` is right associative and binds weaker than binary operators
+            (inner.clone(), inner).prop_map(|(left, right)| {
+                Expression::NilCoalesce(Box::new(NilCoalesce {
+                    left: operand(left, 2),
+                    right: operand(right, 1),
+                }))
+            }),
+        ]
+    })
+    .boxed()
+}
+
+/// Prefix expressions of statements start with a name, a parenthesis could
+/// continue the expression in front of the statement
+fn statement_target() -> impl Strategy<Value = PrefixExpr<'static>> {
+    (identifier(), vec(suffix(expression()), 0..3)).prop_map(|(name, suffixes)| PrefixExpr {
+        prefix: ExprOrVarname::Varname(name),
+        suffix_chain: method_calls(suffixes),
+    })
+}
+
+fn pattern() -> impl Strategy<Value = Pattern<'static>> {
+    let leaf = prop_oneof![
+        literal().prop_map(Pattern::Literal),
+        (literal(), literal(), any::<bool>()).prop_map(|(start, end, inclusive)| {
+            Pattern::Range(RangePattern {
+                start,
+                end,
+                inclusive,
+            })
+        }),
+        Just(Pattern::Wildcard(Span::new("_").into())),
+        identifier()
+            .prop_filter("`_` is the wildcard", |name| name.value != "_")
+            .prop_map(Pattern::Binding),
+    ];
+    leaf.prop_recursive(2, 8, 3, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..3).prop_map(Pattern::Array),
+            hash_map(string_content(), inner, 0..3).prop_map(Pattern::Map),
+        ]
+    })
+}
+
+fn function(depth: u32) -> impl Strategy<Value = Function<'static>> {
+    (identifier(), vec(identifier(), 0..3), block(depth, false)).prop_map(
+        |(name, parameters, block)| Function {
+            exported: false,
+            doc: None,
+            name,
+            parameters,
+            block,
+        },
+    )
+}
+
+fn import() -> impl Strategy<Value = Import<'static>> {
+    (
+        any::<bool>(),
+        vec(identifier(), 1..3),
+        vec(identifier(), 0..3),
+        option::of(identifier()),
+    )
+        .prop_map(|(external, path, items, alias)| Import {
+            external,
+            path,
+            // Selectively imported names can not be aliased
+            alias: if items.is_empty() { alias } else { None },
+            items,
+        })
+}
+
+/// Statements, which contain no blocks
+fn simple_statement() -> impl Strategy<Value = Statement<'static>> {
+    let call = (statement_target(), call(expression()), any::<bool>()).prop_map(
+        |(mut target, call, safe)| {
+            target.suffix_chain.push(match safe {
+                true => ExprSuffix::SafeFuncCall(call),
+                false => ExprSuffix::FuncCall(Call {
+                    callee: None,
+                    ..call
+                }),
+            });
+            target.suffix_chain = method_calls(target.suffix_chain);
+            Statement::FuncCall(target)
+        },
+    );
+    prop_oneof![
+        (statement_target(), expression()).prop_map(|(variable, expression)| {
+            Statement::Assignment(Assignment {
+                variable,
+                expression,
+            })
+        }),
+        (identifier(), expression()).prop_map(|(variable, expression)| {
+            Statement::LAssignment(LAssignment {
+                exported: false,
+                doc: None,
+                variable,
+                expression,
+            })
+        }),
+        call,
+        expression().prop_map(|value| Statement::Raise(RaiseStmt { value })),
+        import().prop_map(Statement::Import),
+        // Without values the next statement would be returned
+        vec(expression(), 1..3).prop_map(|values| Statement::Return(ReturnStmt { values })),
+    ]
+}
+
+fn statement(depth: u32) -> BoxedStrategy<Statement<'static>> {
+    if depth == 0 {
+        return simple_statement().boxed();
+    }
+    let depth = depth - 1;
+    let arm = (pattern(), option::of(expression()), block(depth, false)).prop_map(
+        |(pattern, guard, block)| MatchArm {
+            pattern,
+            guard,
+            block,
+        },
+    );
+    let catch = (option::of(identifier()), block(depth, false))
+        .prop_map(|(binding, block)| Catch { binding, block });
+    prop_oneof![
+        4 => simple_statement(),
+        1 => (expression(), block(depth, false))
+            .prop_map(|(cond, block)| Statement::While(While { cond, block })),
+        1 => (identifier(), expression(), block(depth, false)).prop_map(
+            |(iter_item, iterator, block)| Statement::For(For {
+                iter_item,
+                iterator,
+                block,
+            })
+        ),
+        1 => (expression(), block(depth, false), option::of(block(depth, false))).prop_map(
+            |(cond, stmts, else_statements)| Statement::If(If {
+                cond,
+                stmts,
+                else_statements,
+            })
+        ),
+        1 => (expression(), vec(arm, 0..3))
+            .prop_map(|(expr, arms)| Statement::Match(Match { expr, arms })),
+        1 => (
+            block(depth, false),
+            option::of(catch),
+            option::of(block(depth, false))
+        )
+            .prop_map(|(block, catch, finally)| {
+                // Either a catch or a finally block is required
+                let finally = match catch {
+                    None => finally.or_else(|| Some(Block {
+                        statements: vec![],
+                        return_stmt: None,
+                    })),
+                    Some(_) => finally,
+                };
+                Statement::Try(Try {
+                    block,
+                    catch,
+                    finally,
+                })
+            }),
+        1 => function(depth).prop_map(Statement::Fun),
+        1 => (identifier(), vec(function(depth), 0..3)).prop_map(|(name, methods)| {
+            Statement::Class(Class {
+                exported: false,
+                doc: None,
+                name,
+                methods,
+            })
+        }),
+    ]
+    .boxed()
+}
+
+/// Functions, classes and variables of the top level block can be exported
+fn exported(stmt: Statement<'static>, exported: bool) -> Statement<'static> {
+    match stmt {
+        Statement::Fun(function) => Statement::Fun(Function {
+            exported,
+            ..function
+        }),
+        Statement::Class(class) => Statement::Class(Class { exported, ..class }),
+        Statement::LAssignment(assignment) => Statement::LAssignment(LAssignment {
+            exported,
+            ..assignment
+        }),
+        stmt => stmt,
+    }
+}
+
+fn block(depth: u32, module: bool) -> BoxedStrategy<Block<'static>> {
+    (
+        vec((statement(depth), any::<bool>()), 0..4),
+        option::of(vec(expression(), 0..3)),
+    )
+        .prop_map(move |(statements, values)| {
+            let mut statements: Vec<Statement<'static>> = statements
+                .into_iter()
+                .map(|(stmt, export)| exported(stmt, module && export))
+                .collect();
+
+            // A return statement at the end of a block is the return of the block
+            let mut return_stmt = values.map(|values| ReturnStmt { values });
+            if let Some(Statement::Return(last)) = statements.last() {
+                let last = last.clone();
+                statements.pop();
+                return_stmt = return_stmt.or(Some(last));
+            }
+            Block {
+                statements,
+                return_stmt,
+            }
+        })
+        .boxed()
+}
+
+fn roundtrip(block: &Block<'static>) -> Result<(), TestCaseError> {
+    let source = format_block(block);
+    let reparsed = parse_complete(Span::new(&source))
+        .map_err(|err| TestCaseError::fail(format!("{}\nin\n{}", err, source)))?;
+    prop_assert_eq!(reparsed.to_sexp(), block.to_sexp(), "source:\n{}", source);
+    prop_assert_eq!(format_block(&reparsed), source);
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1024))]
+
+    #[test]
+    fn expressions_roundtrip(expr in expression()) {
+        let block = Block {
+            statements: vec![Statement::LAssignment(LAssignment {
+                exported: false,
+                doc: None,
+                variable: token("x", "x"),
+                expression: expr,
+            })],
+            return_stmt: None,
+        };
+        roundtrip(&block)?;
+    }
+
+    #[test]
+    fn programs_roundtrip(block in block(2, true)) {
+        roundtrip(&block)?;
+    }
+}
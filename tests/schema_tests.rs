@@ -0,0 +1,131 @@
+//! Checks the serialized ASTs of the examples against schema/ast.schema.json
+#![cfg(feature = "serde")]
+
+use std::fs;
+use std::path::PathBuf;
+
+use liva_parser::schema::{Document, JSON_SCHEMA};
+use liva_parser::{parse_source, Span};
+use serde_json::Value;
+
+fn sources() -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    for dir in ["examples", "tests/fixtures/vm"].iter() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "lv") {
+                sources.push(path);
+            }
+        }
+    }
+    sources.sort();
+    sources
+}
+
+/// Validates `value` against the subset of JSON schema used by the AST schema
+/// and returns the path of the first mismatch
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/$defs/");
+        return validate(root, &root["$defs"][name], value, path);
+    }
+    if let Some(variants) = schema["oneOf"].as_array() {
+        let matching = variants
+            .iter()
+            .filter(|variant| validate(root, variant, value, path).is_ok())
+            .count();
+        return match matching {
+            1 => Ok(()),
+            n => Err(format!("{}: {} variants match {}", path, n, value)),
+        };
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{}: expected {}", path, constant));
+        }
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        if !values.contains(value) {
+            return Err(format!("{}: {} is not allowed", path, value));
+        }
+    }
+
+    let valid_type = match schema["type"].as_str() {
+        None => true,
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("number") => value.is_number(),
+        Some("boolean") => value.is_boolean(),
+        Some("null") => value.is_null(),
+        Some(other) => panic!("unsupported type {}", other),
+    };
+    if !valid_type {
+        return Err(format!(
+            "{}: {} is not of type {}",
+            path, value, schema["type"]
+        ));
+    }
+    if let (Some(minimum), Some(number)) = (schema["minimum"].as_i64(), value.as_i64()) {
+        if number < minimum {
+            return Err(format!("{}: {} is less than {}", path, number, minimum));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        for (idx, item) in items.iter().enumerate() {
+            validate(root, &schema["items"], item, &format!("{}[{}]", path, idx))?;
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for key in schema["required"].as_array().into_iter().flatten() {
+            if !object.contains_key(key.as_str().unwrap()) {
+                return Err(format!("{}: missing {}", path, key));
+            }
+        }
+        for (key, field) in object {
+            let field_path = format!("{}.{}", path, key);
+            match (
+                schema["properties"].get(key),
+                &schema["additionalProperties"],
+            ) {
+                (Some(property), _) => validate(root, property, field, &field_path)?,
+                (None, Value::Bool(false)) => {
+                    return Err(format!("{}: unexpected property", field_path))
+                }
+                (None, Value::Null) | (None, Value::Bool(true)) => {}
+                (None, additional) => validate(root, additional, field, &field_path)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn examples_match_schema() {
+    let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+
+    for path in sources() {
+        let source = fs::read_to_string(&path).unwrap();
+        let (_, block) = parse_source(Span::new(&source)).unwrap();
+        let document = serde_json::to_value(Document::new(block)).unwrap();
+
+        if let Err(err) = validate(&schema, &schema, &document, "$") {
+            panic!("{} does not match the schema: {}", path.display(), err);
+        }
+    }
+}
+
+#[test]
+fn validator_rejects_unknown_nodes() {
+    let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+    let document = serde_json::json!({
+        "version": 1,
+        "ast": {
+            "statements": [{"kind": "Goto", "node": {}}],
+            "return_stmt": null
+        }
+    });
+    assert!(validate(&schema, &schema, &document, "$").is_err());
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use liva_parser::compile::{compile, vm::Vm};
+use liva_parser::interp::{Interpreter, RuntimeError, MAX_CALL_DEPTH};
+use liva_parser::optimize::optimize;
+use liva_parser::{parse_source, Span};
+
+/// All test programs, each `.lv` file has a `.out` file with its expected output
+fn programs() -> Vec<PathBuf> {
+    let mut programs: Vec<PathBuf> = fs::read_dir("tests/fixtures/vm")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lv"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+fn expected_output(program: &Path) -> String {
+    fs::read_to_string(program.with_extension("out")).unwrap()
+}
+
+#[test]
+fn run_programs_on_vm() {
+    for program in programs() {
+        let source = fs::read_to_string(&program).unwrap();
+        let (rest, block) = parse_source(Span::new(&source)).unwrap();
+        assert_eq!(rest.fragment().trim(), "", "{}", program.display());
+
+        let script = compile(&block).unwrap();
+        let mut vm = Vm::with_output(Vec::new());
+        vm.run(script).unwrap();
+
+        let output = String::from_utf8(vm.output().clone()).unwrap();
+        assert_eq!(output, expected_output(&program), "{}", program.display());
+    }
+}
+
+/// The interpreter is the reference for the semantics of the VM
+#[test]
+fn run_programs_on_interpreter() {
+    for program in programs() {
+        let source = fs::read_to_string(&program).unwrap();
+        let (_, block) = parse_source(Span::new(&source)).unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&block).unwrap();
+
+        let output = String::from_utf8(interpreter.output().clone()).unwrap();
+        assert_eq!(output, expected_output(&program), "{}", program.display());
+    }
+}
+
+/// Optimizing a program must not change its output
+#[test]
+fn run_optimized_programs() {
+    for program in programs() {
+        let source = fs::read_to_string(&program).unwrap();
+        let (_, block) = parse_source(Span::new(&source)).unwrap();
+
+        let script = compile(&optimize(block)).unwrap();
+        let mut vm = Vm::with_output(Vec::new());
+        vm.run(script).unwrap();
+
+        let output = String::from_utf8(vm.output().clone()).unwrap();
+        assert_eq!(output, expected_output(&program), "{}", program.display());
+    }
+}
+
+/// Both stop at the same call depth
+#[test]
+fn call_depth_limit() {
+    let source = "fun depth(n)\n    if n == 1 do\n        return 1\n    end\n    return 1 + depth(n - 1)\nend\n";
+    for (depth, expected) in [
+        (MAX_CALL_DEPTH, Ok(())),
+        (MAX_CALL_DEPTH + 1, Err(RuntimeError::StackOverflow)),
+    ] {
+        let source = format!("{}depth({})\n", source, depth);
+        let (_, block) = parse_source(Span::new(&source)).unwrap();
+
+        let mut vm = Vm::with_output(Vec::new());
+        assert_eq!(vm.run(compile(&block).unwrap()).map(drop), expected);
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        assert_eq!(interpreter.run(&block).map(drop), expected);
+    }
+}